@@ -1,5 +1,56 @@
-// generated by `sqlx migrate build-script`
+use std::{fs, path::Path};
+
+use sha2::{Digest, Sha256};
+
+/// Checksum of the postgres migrations the `.sqlx` query metadata was prepared against,
+/// written by `scripts/prepare_sqlx.sh`
+const SQLX_MIGRATIONS_CHECKSUM: &str = ".sqlx/migrations.sha256";
+
 fn main() {
     // trigger recompilation when a new migration is added
     println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed={SQLX_MIGRATIONS_CHECKSUM}");
+    println!("cargo:rerun-if-env-changed=SQLX_OFFLINE");
+
+    if std::env::var("CARGO_FEATURE_POSTGRES").is_err() || !is_sqlx_offline() {
+        return;
+    }
+
+    // offline, the query macros only see the metadata, so queries broken by a new migration
+    // would still compile
+    let expected = fs::read_to_string(SQLX_MIGRATIONS_CHECKSUM).unwrap_or_default();
+    let actual = migrations_checksum(Path::new("migrations"));
+    if expected.trim() != actual {
+        panic!(
+            "the .sqlx query metadata is out of date with the migrations, \
+            regenerate it with ./scripts/prepare_sqlx.sh"
+        );
+    }
+}
+
+/// Whether the query macros check against `.sqlx`, which is the default in
+/// `.cargo/config.toml`
+fn is_sqlx_offline() -> bool {
+    !matches!(std::env::var("SQLX_OFFLINE").as_deref(), Ok("false" | "0"))
+}
+
+/// sha256 of the `.sql` files of `dir` concatenated in name order, same as
+/// `cat migrations/*.sql | sha256sum`
+fn migrations_checksum(dir: &Path) -> String {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .expect("migrations directory exists")
+        .map(|entry| entry.expect("migrations directory is readable").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+        .collect();
+    paths.sort();
+
+    let mut hasher = Sha256::new();
+    for path in paths {
+        hasher.update(fs::read(&path).expect("migration is readable"));
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
 }
@@ -0,0 +1,140 @@
+//! Load test for password hashing: hammers `POST /login` against a running server while
+//! probing `GET /login`, which doesn't hash, to show whether hashing stalls other requests.
+//!
+//! ```sh
+//! cargo run --release --example login_bench
+//! ```
+//!
+//! Configured with `BENCH_ADDR` (default `127.0.0.1:3000`), `BENCH_LOGINS` (default 200)
+//! and `BENCH_CONCURRENCY` (default 32).
+
+use std::{
+    env,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const PASSWORD: &str = "bench-password";
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let addr = env::var("BENCH_ADDR").unwrap_or_else(|_| String::from("127.0.0.1:3000"));
+    let logins = env_number("BENCH_LOGINS", 200);
+    let concurrency = env_number("BENCH_CONCURRENCY", 32);
+
+    let username = format!("bench-{}", std::process::id());
+    let body = format!("username={username}&password={PASSWORD}");
+    let status = request(&addr, "POST", "/register", &body).await?;
+    assert_eq!(status, 302, "failed to register the bench user");
+
+    let remaining = Arc::new(AtomicUsize::new(logins));
+    let started = Instant::now();
+
+    let workers = (0..concurrency).map(|_| {
+        let addr = addr.clone();
+        let body = body.clone();
+        let remaining = Arc::clone(&remaining);
+        tokio::spawn(async move {
+            let mut results = vec![];
+            while remaining
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                let sent = Instant::now();
+                let status = request(&addr, "POST", "/login", &body).await?;
+                results.push((status, sent.elapsed()));
+            }
+            std::io::Result::Ok(results)
+        })
+    });
+    let workers: Vec<_> = workers.collect();
+
+    let probe = {
+        let addr = addr.clone();
+        let remaining = Arc::clone(&remaining);
+        tokio::spawn(async move {
+            let mut latencies = vec![];
+            while remaining.load(Ordering::SeqCst) > 0 {
+                let sent = Instant::now();
+                request(&addr, "GET", "/login", "").await?;
+                latencies.push(sent.elapsed());
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            std::io::Result::Ok(latencies)
+        })
+    };
+
+    let mut login_latencies = vec![];
+    let mut busy = 0;
+    let mut failed = 0;
+    for worker in workers {
+        for (status, latency) in worker.await.expect("worker panicked")? {
+            match status {
+                302 => login_latencies.push(latency),
+                503 => busy += 1,
+                _ => failed += 1,
+            }
+        }
+    }
+    let elapsed = started.elapsed();
+    let probe_latencies = probe.await.expect("probe panicked")?;
+
+    println!("{logins} logins with concurrency {concurrency} in {elapsed:.2?}");
+    println!(
+        "  throughput: {:.1} logins/s ({busy} busy, {failed} failed)",
+        login_latencies.len() as f64 / elapsed.as_secs_f64()
+    );
+    print_percentiles("  login", login_latencies);
+    print_percentiles("  GET /login", probe_latencies);
+
+    Ok(())
+}
+
+fn env_number(name: &str, default: usize) -> usize {
+    env::var(name)
+        .map(|value| value.parse().expect("expected a number"))
+        .unwrap_or(default)
+}
+
+fn print_percentiles(label: &str, mut latencies: Vec<Duration>) {
+    if latencies.is_empty() {
+        println!("{label}: no samples");
+        return;
+    }
+    latencies.sort();
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p).round() as usize];
+    println!(
+        "{label}: p50 {:.2?}, p99 {:.2?}, max {:.2?}",
+        percentile(0.5),
+        percentile(0.99),
+        latencies[latencies.len() - 1]
+    );
+}
+
+/// Minimal HTTP/1.1 request over a fresh connection, returning the response status.
+async fn request(addr: &str, method: &str, path: &str, body: &str) -> std::io::Result<u16> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\
+         Content-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await?;
+    let status = String::from_utf8_lossy(&response)
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .unwrap_or(0);
+    Ok(status)
+}
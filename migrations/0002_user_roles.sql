@@ -0,0 +1 @@
+ALTER TABLE Users ADD COLUMN role varchar(32) NOT NULL DEFAULT 'user';
@@ -0,0 +1,17 @@
+CREATE TABLE AuditEvents (
+    id varchar(255) NOT NULL,
+    actor_id varchar(255),
+    event_type varchar(64) NOT NULL,
+    ip varchar(255),
+    user_agent varchar,
+    created_at timestamptz NOT NULL,
+    payload jsonb NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX audit_events_by_actor_id ON AuditEvents (actor_id, created_at DESC);
+CREATE INDEX audit_events_by_created_at ON AuditEvents (created_at DESC);
+
+-- audit events are append-only
+CREATE RULE audit_events_no_update AS ON UPDATE TO AuditEvents DO INSTEAD NOTHING;
+CREATE RULE audit_events_no_delete AS ON DELETE TO AuditEvents DO INSTEAD NOTHING;
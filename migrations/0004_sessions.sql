@@ -0,0 +1,14 @@
+CREATE TABLE Sessions (
+    id varchar(255) NOT NULL,
+    token_hash varchar(64) NOT NULL,
+    user_id varchar(255) NOT NULL,
+    device varchar,
+    ip varchar(255),
+    created_at timestamptz NOT NULL,
+    last_seen_at timestamptz NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (user_id) REFERENCES Users(id)
+);
+
+CREATE UNIQUE INDEX sessions_by_token_hash ON Sessions (token_hash);
+CREATE INDEX sessions_by_user_id ON Sessions (user_id);
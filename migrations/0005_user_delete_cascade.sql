@@ -0,0 +1,8 @@
+-- deleting a user also deletes their todos and sessions
+ALTER TABLE Todos
+    DROP CONSTRAINT todos_user_id_fkey,
+    ADD CONSTRAINT todos_user_id_fkey FOREIGN KEY (user_id) REFERENCES Users(id) ON DELETE CASCADE;
+
+ALTER TABLE Sessions
+    DROP CONSTRAINT sessions_user_id_fkey,
+    ADD CONSTRAINT sessions_user_id_fkey FOREIGN KEY (user_id) REFERENCES Users(id) ON DELETE CASCADE;
@@ -0,0 +1,17 @@
+ALTER TABLE Todos
+    ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now(),
+    ADD COLUMN completed_at timestamptz,
+    ADD COLUMN position bigint NOT NULL DEFAULT 0;
+
+UPDATE Todos SET completed_at = now() WHERE complete;
+
+-- existing todos had no order, so they're numbered by id
+UPDATE Todos SET position = numbered.position
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY id) AS position FROM Todos
+) numbered
+WHERE Todos.id = numbered.id;
+
+DROP INDEX todos_by_user_id;
+CREATE INDEX todos_by_user_id ON Todos (user_id, position);
@@ -0,0 +1,7 @@
+ALTER TABLE Todos
+    ADD COLUMN due_date date,
+    ADD COLUMN due_time time,
+    -- 0 low, 1 normal, 2 high
+    ADD COLUMN priority smallint NOT NULL DEFAULT 1;
+
+CREATE INDEX todos_by_user_id_due_date ON Todos (user_id, due_date);
@@ -0,0 +1,17 @@
+CREATE TABLE Lists (
+    id varchar(255) NOT NULL,
+    user_id varchar(255) NOT NULL,
+    name varchar(255) NOT NULL,
+    created_at timestamptz NOT NULL,
+    archived_at timestamptz,
+    PRIMARY KEY (id),
+    FOREIGN KEY (user_id) REFERENCES Users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX lists_by_user_id ON Lists (user_id, created_at);
+
+-- todos without a list are in the user's inbox
+ALTER TABLE Todos ADD COLUMN list_id varchar(255) REFERENCES Lists(id) ON DELETE CASCADE;
+
+DROP INDEX todos_by_user_id;
+CREATE INDEX todos_by_user_id ON Todos (user_id, list_id, position);
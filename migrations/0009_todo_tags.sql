@@ -0,0 +1,19 @@
+CREATE TABLE Tags (
+    id varchar(255) NOT NULL,
+    user_id varchar(255) NOT NULL,
+    name varchar(64) NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (user_id) REFERENCES Users(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX tags_by_user_id_name ON Tags (user_id, name);
+
+CREATE TABLE TodoTags (
+    todo_id varchar(255) NOT NULL,
+    tag_id varchar(255) NOT NULL,
+    PRIMARY KEY (todo_id, tag_id),
+    FOREIGN KEY (todo_id) REFERENCES Todos(id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES Tags(id) ON DELETE CASCADE
+);
+
+CREATE INDEX todo_tags_by_tag_id ON TodoTags (tag_id);
@@ -0,0 +1,14 @@
+CREATE TABLE ChecklistItems (
+    id varchar(255) NOT NULL,
+    todo_id varchar(255) NOT NULL,
+    name varchar(255) NOT NULL,
+    complete boolean NOT NULL,
+    position bigint NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (todo_id) REFERENCES Todos(id) ON DELETE CASCADE
+);
+
+CREATE INDEX checklist_items_by_todo_id ON ChecklistItems (todo_id, position);
+
+-- completes the todo once all of its checklist items are done
+ALTER TABLE Todos ADD COLUMN auto_complete boolean NOT NULL DEFAULT false;
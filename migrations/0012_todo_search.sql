@@ -0,0 +1,6 @@
+-- the words of todo names for searching, with the 'simple' configuration so that words are
+-- matched as typed rather than stemmed as English
+ALTER TABLE Todos ADD COLUMN search tsvector
+    GENERATED ALWAYS AS (to_tsvector('simple', name)) STORED;
+
+CREATE INDEX todos_search ON Todos USING GIN (search);
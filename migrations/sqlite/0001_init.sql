@@ -0,0 +1,61 @@
+-- the schema of the postgres migrations up to 0005, which SQLite can't apply as is
+CREATE TABLE Users (
+    id varchar(255) NOT NULL,
+    username varchar(255) NOT NULL,
+    password_hash varchar NOT NULL,
+    role varchar(32) NOT NULL DEFAULT 'user',
+    PRIMARY KEY (id)
+);
+
+CREATE UNIQUE INDEX user_by_username ON Users (username);
+
+CREATE TABLE Todos (
+    id varchar(255) NOT NULL,
+    user_id varchar(255) NOT NULL,
+    name varchar(255) NOT NULL,
+    complete boolean NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (user_id) REFERENCES Users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX todos_by_user_id ON Todos (user_id);
+
+-- timestamps are stored as RFC 3339 text in UTC, so they sort chronologically
+CREATE TABLE AuditEvents (
+    id varchar(255) NOT NULL,
+    actor_id varchar(255),
+    event_type varchar(64) NOT NULL,
+    ip varchar(255),
+    user_agent varchar,
+    created_at text NOT NULL,
+    payload text NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX audit_events_by_actor_id ON AuditEvents (actor_id, created_at DESC);
+CREATE INDEX audit_events_by_created_at ON AuditEvents (created_at DESC);
+
+-- audit events are append-only
+CREATE TRIGGER audit_events_no_update BEFORE UPDATE ON AuditEvents
+BEGIN
+    SELECT RAISE(IGNORE);
+END;
+CREATE TRIGGER audit_events_no_delete BEFORE DELETE ON AuditEvents
+BEGIN
+    SELECT RAISE(IGNORE);
+END;
+
+CREATE TABLE Sessions (
+    id varchar(255) NOT NULL,
+    token_hash varchar(64) NOT NULL,
+    user_id varchar(255) NOT NULL,
+    device varchar,
+    ip varchar(255),
+    created_at text NOT NULL,
+    last_seen_at text NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (user_id) REFERENCES Users(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX sessions_by_token_hash ON Sessions (token_hash);
+CREATE INDEX sessions_by_user_id ON Sessions (user_id);
@@ -0,0 +1,20 @@
+-- SQLite can only add NOT NULL columns with a constant default, so the timestamps are
+-- filled in afterwards
+ALTER TABLE Todos ADD COLUMN created_at text NOT NULL DEFAULT '1970-01-01T00:00:00+00:00';
+ALTER TABLE Todos ADD COLUMN updated_at text NOT NULL DEFAULT '1970-01-01T00:00:00+00:00';
+ALTER TABLE Todos ADD COLUMN completed_at text;
+ALTER TABLE Todos ADD COLUMN position integer NOT NULL DEFAULT 0;
+
+UPDATE Todos SET
+    created_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now'),
+    updated_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now'),
+    completed_at = CASE WHEN complete THEN strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now') END;
+
+-- existing todos had no order, so they're numbered by id
+UPDATE Todos SET position = (
+    SELECT COUNT(*) FROM Todos earlier
+    WHERE earlier.user_id = Todos.user_id AND earlier.id <= Todos.id
+);
+
+DROP INDEX todos_by_user_id;
+CREATE INDEX todos_by_user_id ON Todos (user_id, position);
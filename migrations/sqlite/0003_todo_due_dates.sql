@@ -0,0 +1,7 @@
+-- dates and times are stored as ISO 8601 text, so they sort chronologically
+ALTER TABLE Todos ADD COLUMN due_date text;
+ALTER TABLE Todos ADD COLUMN due_time text;
+-- 0 low, 1 normal, 2 high
+ALTER TABLE Todos ADD COLUMN priority integer NOT NULL DEFAULT 1;
+
+CREATE INDEX todos_by_user_id_due_date ON Todos (user_id, due_date);
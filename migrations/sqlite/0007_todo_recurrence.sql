@@ -0,0 +1,2 @@
+-- an iCalendar RRULE, see utils/recurrence.rs
+ALTER TABLE Todos ADD COLUMN recurrence varchar(255);
@@ -0,0 +1,22 @@
+-- the words of todo names for searching, kept up to date by the triggers below. Diacritics are
+-- kept so that words match like they do in postgres.
+CREATE VIRTUAL TABLE TodoSearch USING fts5(
+    todo_id UNINDEXED,
+    name,
+    tokenize = 'unicode61 remove_diacritics 0'
+);
+
+INSERT INTO TodoSearch (todo_id, name) SELECT id, name FROM Todos;
+
+CREATE TRIGGER todo_search_insert AFTER INSERT ON Todos BEGIN
+    INSERT INTO TodoSearch (todo_id, name) VALUES (new.id, new.name);
+END;
+
+CREATE TRIGGER todo_search_update AFTER UPDATE OF name ON Todos BEGIN
+    UPDATE TodoSearch SET name=new.name WHERE todo_id=new.id;
+END;
+
+-- also run for todos deleted along with their list or user
+CREATE TRIGGER todo_search_delete AFTER DELETE ON Todos BEGIN
+    DELETE FROM TodoSearch WHERE todo_id=old.id;
+END;
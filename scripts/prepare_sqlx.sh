@@ -0,0 +1,17 @@
+#!/bin/bash
+# Regenerates the .sqlx query metadata, which the build checks queries against, from the
+# database at DATABASE_URL. Run after changing a query or adding a postgres migration.
+
+set -e
+export LC_ALL=C
+
+cd "$(dirname "$0")/.."
+
+echo "migrating database"
+sqlx migrate run
+
+echo "preparing query metadata"
+SQLX_OFFLINE=false cargo sqlx prepare -- --all-targets
+
+# lets the build check the metadata is still up to date with the migrations
+cat migrations/*.sql | sha256sum | cut -d ' ' -f 1 > .sqlx/migrations.sha256
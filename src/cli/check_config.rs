@@ -0,0 +1,190 @@
+use std::{error::Error, time::Duration};
+
+use crate::{
+    middleware::security_headers::SecurityHeaders,
+    repositories::health_repository::MigrationState,
+    services::argon2_password_hasher::Argon2PasswordHasher,
+    utils::{
+        database::{DatabasePool, DatabaseSettings},
+        global_auth::{
+            get_metrics_token, get_password_hash_max_concurrency, get_password_hash_queue_timeout,
+            get_secure_cookies, get_session_idle_timeout, get_session_max_age, get_session_mode,
+            get_shutdown_timeout_secs, get_user_cache_capacity, get_user_cache_ttl, SessionMode,
+        },
+        jwt_keyring::JwtKeyring,
+        logging::get_log_format,
+        tls::{ReloadingCertResolver, TlsSettings},
+    },
+    DatabaseRepositories,
+};
+use serde::Serialize;
+
+use super::{
+    output::{print_rows, OutputFormat, TableRow},
+    CliError, CliResult,
+};
+
+/// The database check only tries to connect once, rather than waiting for it to start
+const DATABASE_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What was checked, or why the check failed
+type CheckResult = Result<String, Box<dyn Error>>;
+
+#[derive(Serialize)]
+struct CheckRow {
+    check: &'static str,
+    ok: bool,
+    detail: String,
+}
+
+impl CheckRow {
+    fn new(check: &'static str, result: CheckResult) -> Self {
+        let (ok, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(e) => (false, e.to_string()),
+        };
+        CheckRow { check, ok, detail }
+    }
+}
+
+impl TableRow for CheckRow {
+    const HEADERS: &'static [&'static str] = &["check", "status", "detail"];
+
+    fn cells(&self) -> Vec<String> {
+        let status = if self.ok { "ok" } else { "FAILED" };
+        vec![
+            self.check.to_owned(),
+            status.to_owned(),
+            self.detail.clone(),
+        ]
+    }
+}
+
+/// Loads every setting the app reads at startup, reporting all problems rather than only the
+/// first.
+pub async fn run(format: OutputFormat) -> CliResult<()> {
+    let rows = vec![
+        CheckRow::new("sessions", check_sessions()),
+        CheckRow::new("password hashing", check_password_hashing()),
+        CheckRow::new("user cache", check_user_cache()),
+        CheckRow::new("security headers", check_security_headers()),
+        CheckRow::new("tls", check_tls()),
+        CheckRow::new("logging", check_logging()),
+        CheckRow::new("metrics", Ok(check_metrics())),
+        CheckRow::new("shutdown", check_shutdown()),
+        CheckRow::new("database", check_database().await),
+    ];
+
+    print_rows(format, &rows);
+    if rows.iter().any(|row| !row.ok) {
+        return Err(CliError::ChecksFailed);
+    }
+    Ok(())
+}
+
+fn check_sessions() -> CheckResult {
+    let secure = if get_secure_cookies()? {
+        "secure cookies"
+    } else {
+        "insecure cookies"
+    };
+    match get_session_mode()? {
+        SessionMode::Jwt => {
+            JwtKeyring::from_env()?;
+            Ok(format!("jwt, {secure}"))
+        }
+        SessionMode::Server => Ok(format!(
+            "server, idle timeout {}m, max age {}m, {secure}",
+            get_session_idle_timeout()?.num_minutes(),
+            get_session_max_age()?.num_minutes()
+        )),
+    }
+}
+
+fn check_password_hashing() -> CheckResult {
+    Argon2PasswordHasher::from_env()?;
+    Ok(format!(
+        "argon2id, {} concurrent hashes, queue timeout {}ms",
+        get_password_hash_max_concurrency()?,
+        get_password_hash_queue_timeout()?.as_millis()
+    ))
+}
+
+fn check_user_cache() -> CheckResult {
+    Ok(match get_user_cache_capacity()? {
+        Some(capacity) => format!("{capacity} users for {}s", get_user_cache_ttl()?.as_secs()),
+        None => String::from("disabled"),
+    })
+}
+
+fn check_security_headers() -> CheckResult {
+    SecurityHeaders::from_env()?;
+    Ok(String::from("loaded"))
+}
+
+fn check_tls() -> CheckResult {
+    match TlsSettings::from_env()? {
+        Some(settings) => {
+            ReloadingCertResolver::new(settings.cert_path.clone(), settings.key_path)?;
+            Ok(format!(
+                "HTTPS on port {} with {}",
+                settings.https_port,
+                settings.cert_path.display()
+            ))
+        }
+        None => Ok(String::from("disabled, serving HTTP")),
+    }
+}
+
+fn check_logging() -> CheckResult {
+    Ok(format!("{:?} format", get_log_format()?))
+}
+
+fn check_metrics() -> String {
+    match get_metrics_token() {
+        Some(_) => String::from("/metrics enabled"),
+        None => String::from("/metrics disabled, METRICS_TOKEN isn't set"),
+    }
+}
+
+fn check_shutdown() -> CheckResult {
+    Ok(format!("timeout {}s", get_shutdown_timeout_secs()?))
+}
+
+async fn check_database() -> CheckResult {
+    let settings = DatabaseSettings::from_env()?;
+    if settings.is_in_memory() {
+        if cfg!(feature = "memory") {
+            return Ok(String::from("in memory, data is lost when the app stops"));
+        }
+        return Err("this build can't keep data in memory".into());
+    }
+    let pool =
+        match tokio::time::timeout(DATABASE_CHECK_TIMEOUT, DatabasePool::connect(&settings.url))
+            .await
+        {
+            Ok(Ok(pool)) => pool,
+            Ok(Err(e)) => return Err(format!("failed to connect: {e}").into()),
+            Err(_) => return Err("timed out connecting".into()),
+        };
+    let migrations = DatabaseRepositories::new(&pool)
+        .health
+        .list_migrations()
+        .await;
+    pool.close().await;
+
+    let migrations = migrations?;
+    let count = |state| migrations.iter().filter(|m| m.state == state).count();
+    if count(MigrationState::Changed) > 0 {
+        return Err(format!(
+            "{} applied migrations have been changed",
+            count(MigrationState::Changed)
+        )
+        .into());
+    }
+    Ok(format!(
+        "connected, {} migrations applied, {} pending",
+        count(MigrationState::Applied),
+        count(MigrationState::Pending)
+    ))
+}
@@ -0,0 +1,96 @@
+use clap::Subcommand;
+use serde::Serialize;
+
+use crate::{
+    repositories::health_repository::{MigrationEntity, MigrationState},
+    DatabaseRepositories,
+};
+
+use super::{
+    connect_database,
+    output::{print_rows, OutputFormat, TableRow},
+    CliError, CliResult,
+};
+
+#[derive(Subcommand)]
+pub enum MigrateCommand {
+    /// Apply the pending migrations, as is done when serving
+    Up {
+        /// Only list the migrations which would be applied
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List the migrations and whether they are applied
+    Status,
+}
+
+#[derive(Serialize)]
+struct MigrationRow {
+    version: i64,
+    description: String,
+    state: &'static str,
+}
+
+impl From<MigrationEntity> for MigrationRow {
+    fn from(value: MigrationEntity) -> Self {
+        MigrationRow {
+            version: value.version,
+            description: value.description,
+            state: value.state.as_str(),
+        }
+    }
+}
+
+impl TableRow for MigrationRow {
+    const HEADERS: &'static [&'static str] = &["version", "description", "state"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.version.to_string(),
+            self.description.clone(),
+            self.state.to_owned(),
+        ]
+    }
+}
+
+pub async fn run(command: MigrateCommand, format: OutputFormat) -> CliResult<()> {
+    // migrations don't need the rest of the app's configuration
+    let pool = connect_database().await?;
+    let migrations = DatabaseRepositories::new(&pool)
+        .health
+        .list_migrations()
+        .await?;
+
+    if migrations
+        .iter()
+        .any(|m| m.state == MigrationState::Changed)
+    {
+        print_migrations(format, migrations);
+        return Err(CliError::Invalid(String::from(
+            "Applied migrations have been changed, they must be fixed by hand",
+        )));
+    }
+
+    match command {
+        MigrateCommand::Status => print_migrations(format, migrations),
+        MigrateCommand::Up { dry_run } => {
+            let mut pending: Vec<_> = migrations
+                .into_iter()
+                .filter(|m| m.state == MigrationState::Pending)
+                .collect();
+            if !dry_run {
+                pool.migrate().await?;
+                for migration in &mut pending {
+                    migration.state = MigrationState::Applied;
+                }
+            }
+            print_migrations(format, pending);
+        }
+    }
+    Ok(())
+}
+
+fn print_migrations(format: OutputFormat, migrations: Vec<MigrationEntity>) {
+    let rows: Vec<MigrationRow> = migrations.into_iter().map(From::from).collect();
+    print_rows(format, &rows);
+}
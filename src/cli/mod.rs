@@ -0,0 +1,159 @@
+//! Admin commands of the binary, for operators to manage the app without writing SQL. They
+//! use the same services and repositories as the app. Without a command, the app is served.
+
+pub mod check_config;
+pub mod migrate;
+pub mod output;
+pub mod todos;
+pub mod users;
+
+use std::{
+    io::{BufRead, IsTerminal},
+    sync::Arc,
+};
+
+use clap::{Parser, Subcommand};
+use thiserror::Error;
+
+use crate::{
+    repositories::RepositoryError,
+    services::{
+        audit_service::{AuditContext, AuditServiceError},
+        auth_service::AuthServiceError,
+        checklist_service::ChecklistServiceError,
+        list_service::ListServiceError,
+        metrics_service::MetricsService,
+        todo_service::TodoServiceError,
+    },
+    utils::{
+        database::{connect_with_retry, DatabasePool, DatabaseSettings, IN_MEMORY_DATABASE_URL},
+        global_auth::ConfigError,
+    },
+    AppState, AppStateError,
+};
+
+use self::{
+    migrate::MigrateCommand, output::OutputFormat, todos::TodosCommand, users::UserCommand,
+};
+
+/// Recorded as the user agent of audit events caused by admin commands
+const CLI_USER_AGENT: &str = "admin-cli";
+
+#[derive(Parser)]
+#[command(about = "The SHAAT stack todo app and its admin commands")]
+pub struct Cli {
+    /// How admin commands print their results
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the migrations and serve the app (the default)
+    Serve,
+    /// Apply or inspect database migrations
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Manage users
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Export and import todos as JSON
+    #[command(subcommand)]
+    Todos(TodosCommand),
+    /// Check the configuration in the environment without serving
+    CheckConfig,
+}
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("Some checks failed")]
+    ChecksFailed,
+    #[error("{0}, check the configuration with `check-config`")]
+    AppState(#[from] AppStateError),
+    #[error("{0}, check the configuration with `check-config`")]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Auth(#[from] AuthServiceError),
+    #[error(transparent)]
+    Todo(#[from] TodoServiceError),
+    #[error(transparent)]
+    List(#[from] ListServiceError),
+    #[error(transparent)]
+    Checklist(#[from] ChecklistServiceError),
+    #[error(transparent)]
+    Audit(#[from] AuditServiceError),
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Migration error: {0}")]
+    Migration(#[from] sqlx::migrate::MigrateError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type CliResult<T> = Result<T, CliError>;
+
+/// Runs an admin command. [Command::Serve] is handled by `main`.
+pub async fn run(command: Command, format: OutputFormat) -> CliResult<()> {
+    match command {
+        Command::Serve => unreachable!("served by main"),
+        Command::Migrate(command) => migrate::run(command, format).await,
+        Command::User(command) => users::run(command, format).await,
+        Command::Todos(command) => todos::run(command).await,
+        Command::CheckConfig => check_config::run(format).await,
+    }
+}
+
+/// The app's state, connected to the configured database. Its configuration is needed as
+/// well, e.g. to hash passwords, and reported as an error when invalid.
+async fn connect_app_state() -> CliResult<AppState> {
+    let pool = connect_database().await?;
+    Ok(AppState::new_with_pool(
+        pool,
+        Arc::new(MetricsService::new()),
+    )?)
+}
+
+/// Connects to the configured database. Data kept in memory belongs to the serving app, so
+/// it can't be managed with admin commands.
+async fn connect_database() -> CliResult<DatabasePool> {
+    let settings = DatabaseSettings::from_env()?;
+    if settings.is_in_memory() {
+        return Err(CliError::Invalid(format!(
+            "Admin commands need a database, DATABASE_URL is {IN_MEMORY_DATABASE_URL}"
+        )));
+    }
+    Ok(connect_with_retry(&settings).await?)
+}
+
+/// Audit context of changes made with admin commands, which have no signed in actor.
+fn cli_audit_context() -> AuditContext {
+    AuditContext {
+        user_agent: Some(CLI_USER_AGENT.to_owned()),
+        ..Default::default()
+    }
+}
+
+/// Reads a password from the first line of stdin, so it doesn't end up in the shell history.
+fn read_password(prompt: &str) -> CliResult<String> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        eprint!("{prompt}: ");
+    }
+    let mut password = String::new();
+    stdin.lock().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err(CliError::Invalid(String::from(
+            "The password can't be empty",
+        )));
+    }
+    Ok(password.to_owned())
+}
@@ -0,0 +1,50 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// How admin commands print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns, for people
+    Table,
+    /// A JSON array, for scripts
+    Json,
+}
+
+/// A row of a command's output table.
+pub trait TableRow: Serialize {
+    const HEADERS: &'static [&'static str];
+
+    fn cells(&self) -> Vec<String>;
+}
+
+pub fn print_rows<T: TableRow>(format: OutputFormat, rows: &[T]) {
+    match format {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(rows).expect("rows serialize to JSON")
+        ),
+        OutputFormat::Table => print_table(T::HEADERS, rows.iter().map(T::cells).collect()),
+    }
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let print_line = |cells: Vec<String>| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+    print_line(headers.iter().map(|h| h.to_uppercase()).collect());
+    for row in rows {
+        print_line(row);
+    }
+}
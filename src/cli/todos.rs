@@ -0,0 +1,235 @@
+use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
+
+use chrono::{NaiveDate, NaiveTime};
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    repositories::todo_repository::{TodoDetails, TodoFilter, TodoPriority},
+    utils::recurrence::Recurrence,
+};
+
+use super::{connect_app_state, CliError, CliResult};
+
+#[derive(Subcommand)]
+pub enum TodosCommand {
+    /// Print todos as a JSON array of
+    /// `{"username", "list", "name", "is_complete", "due_date", "due_time", "priority", "tags",
+    /// "checklist", "auto_complete", "recurrence"}`, where `list` is the name of the todo's list,
+    /// or null for the inbox, `tags` the names of its tags, `checklist` its
+    /// `{"name", "is_complete"}` items and `recurrence` an iCalendar `RRULE` or null
+    Export {
+        /// Only export the todos of this user
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// Add the todos of an export to their users, creating lists missing from a user. Nothing is
+    /// imported if a user is missing.
+    Import {
+        /// Read the export from this file instead of stdin
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct TodoRecord {
+    username: String,
+    #[serde(default)]
+    list: Option<String>,
+    name: String,
+    #[serde(default)]
+    is_complete: bool,
+    #[serde(default)]
+    due_date: Option<NaiveDate>,
+    #[serde(default)]
+    due_time: Option<NaiveTime>,
+    #[serde(default)]
+    priority: TodoPriority,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    checklist: Vec<ChecklistItemRecord>,
+    #[serde(default)]
+    auto_complete: bool,
+    /// an iCalendar `RRULE`
+    #[serde(default)]
+    recurrence: Option<Recurrence>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChecklistItemRecord {
+    name: String,
+    #[serde(default)]
+    is_complete: bool,
+}
+
+pub async fn run(command: TodosCommand) -> CliResult<()> {
+    let state = connect_app_state().await?;
+
+    match command {
+        TodosCommand::Export { user } => {
+            let users = match user {
+                Some(username) => vec![state
+                    .user_repository
+                    .get_user_by_username(&username)
+                    .await?
+                    .ok_or_else(|| {
+                        CliError::Invalid(format!("User '{username}' does not exist"))
+                    })?],
+                None => state.user_repository.list_users().await?,
+            };
+
+            let mut records = vec![];
+            for user in users {
+                let lists = state.list_service.list_lists(&user.id).await?;
+                // the inbox, then every list
+                for list in std::iter::once(None).chain(lists.iter().map(Some)) {
+                    let filter = TodoFilter {
+                        list_id: list.map(|list| list.id.clone()),
+                        ..Default::default()
+                    };
+                    let todos = state.todo_service.list_todos(&user.id, &filter).await?;
+                    let todo_ids: Vec<_> = todos.iter().map(|todo| todo.id.clone()).collect();
+                    let mut checklists: HashMap<_, Vec<_>> = HashMap::new();
+                    for item in state
+                        .checklist_service
+                        .list_items(&user.id, &todo_ids)
+                        .await?
+                    {
+                        checklists
+                            .entry(item.todo_id)
+                            .or_default()
+                            .push(ChecklistItemRecord {
+                                name: item.name,
+                                is_complete: item.is_complete,
+                            });
+                    }
+                    records.extend(todos.into_iter().map(|todo| TodoRecord {
+                        username: user.username.clone(),
+                        list: list.map(|list| list.name.clone()),
+                        checklist: checklists.remove(&todo.id).unwrap_or_default(),
+                        name: todo.name,
+                        is_complete: todo.is_complete,
+                        due_date: todo.due_date,
+                        due_time: todo.due_time,
+                        priority: todo.priority,
+                        tags: todo.tags,
+                        auto_complete: todo.auto_complete,
+                        recurrence: todo.recurrence,
+                    }));
+                }
+            }
+            println!("{}", serde_json::to_string_pretty(&records)?);
+        }
+        TodosCommand::Import { file } => {
+            let records: Vec<TodoRecord> = match file {
+                Some(path) => serde_json::from_reader(BufReader::new(File::open(path)?))?,
+                None => serde_json::from_reader(std::io::stdin().lock())?,
+            };
+
+            // resolve every user first, so a typo doesn't leave a partial import behind
+            let mut user_ids = HashMap::new();
+            // the lists of those users, by user ID and name
+            let mut list_ids = HashMap::new();
+            for record in &records {
+                if user_ids.contains_key(&record.username) {
+                    continue;
+                }
+                let user = state
+                    .user_repository
+                    .get_user_by_username(&record.username)
+                    .await?
+                    .ok_or_else(|| {
+                        CliError::Invalid(format!("User '{}' does not exist", record.username))
+                    })?;
+                // oldest first, so it wins when lists share a name
+                for list in state
+                    .list_service
+                    .list_lists(&user.id)
+                    .await?
+                    .into_iter()
+                    .rev()
+                {
+                    list_ids.insert((user.id.clone(), list.name), list.id);
+                }
+                user_ids.insert(record.username.clone(), user.id);
+            }
+            let mut lists_created = 0;
+
+            for record in &records {
+                let user_id = &user_ids[&record.username];
+                let list_id = match &record.list {
+                    Some(name) => {
+                        let key = (user_id.clone(), name.trim().to_owned());
+                        match list_ids.get(&key) {
+                            Some(list_id) => Some(list_id.clone()),
+                            None => {
+                                let list_id = state.list_service.create_list(user_id, name).await?;
+                                lists_created += 1;
+                                list_ids.insert(key, list_id.clone());
+                                Some(list_id)
+                            }
+                        }
+                    }
+                    None => None,
+                };
+                // the recurrence is set last, so completing the todo here doesn't add its next
+                // occurrence
+                let mut details = TodoDetails {
+                    due_date: record.due_date,
+                    due_time: record.due_time.filter(|_| record.due_date.is_some()),
+                    priority: record.priority,
+                    recurrence: None,
+                };
+                let todo_id = state
+                    .todo_service
+                    .add_todo(
+                        user_id,
+                        list_id.as_deref(),
+                        &record.name,
+                        &details,
+                        &record.tags,
+                    )
+                    .await?;
+                for item in &record.checklist {
+                    let item_id = state
+                        .checklist_service
+                        .add_item(user_id, &todo_id, &item.name)
+                        .await?;
+                    if item.is_complete {
+                        state
+                            .checklist_service
+                            .set_item_complete(user_id, &todo_id, &item_id, true)
+                            .await?;
+                    }
+                }
+                if record.auto_complete {
+                    state
+                        .checklist_service
+                        .set_auto_complete(user_id, &todo_id, true)
+                        .await?;
+                }
+                if record.is_complete {
+                    state
+                        .todo_service
+                        .set_todo_complete(user_id, &todo_id, true)
+                        .await?;
+                }
+                if record.recurrence.is_some() {
+                    details.recurrence = record.recurrence.clone();
+                    state
+                        .todo_service
+                        .update_todo_details(user_id, &todo_id, &details, &record.tags)
+                        .await?;
+                }
+            }
+            eprintln!(
+                "Imported {} todos for {} users, creating {} lists",
+                records.len(),
+                user_ids.len(),
+                lists_created
+            );
+        }
+    }
+    Ok(())
+}
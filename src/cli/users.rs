@@ -0,0 +1,124 @@
+use clap::Subcommand;
+use serde::Serialize;
+
+use crate::{
+    repositories::user_repository::{UserEntity, UserRole},
+    AppState,
+};
+
+use super::{
+    cli_audit_context, connect_app_state,
+    output::{print_rows, OutputFormat, TableRow},
+    read_password, CliError, CliResult,
+};
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// Create a user, reading their password from stdin
+    Create {
+        username: String,
+        /// Make the user an admin
+        #[arg(long)]
+        admin: bool,
+    },
+    /// List all users
+    List,
+    /// Delete a user along with their todos and sessions
+    Delete { username: String },
+    /// Replace a user's password, reading it from stdin
+    ResetPassword { username: String },
+    /// Change a user's role
+    SetRole {
+        username: String,
+        /// `user` or `admin`
+        role: UserRole,
+    },
+}
+
+#[derive(Serialize)]
+struct UserRow {
+    id: String,
+    username: String,
+    role: &'static str,
+}
+
+impl From<UserEntity> for UserRow {
+    fn from(value: UserEntity) -> Self {
+        UserRow {
+            id: value.id,
+            username: value.username,
+            role: value.role.as_str(),
+        }
+    }
+}
+
+impl TableRow for UserRow {
+    const HEADERS: &'static [&'static str] = &["id", "username", "role"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.id.clone(), self.username.clone(), self.role.to_owned()]
+    }
+}
+
+pub async fn run(command: UserCommand, format: OutputFormat) -> CliResult<()> {
+    let state = connect_app_state().await?;
+    let ctx = cli_audit_context();
+
+    match command {
+        UserCommand::Create { username, admin } => {
+            let password = read_password("Password")?;
+            let role = if admin {
+                UserRole::Admin
+            } else {
+                UserRole::User
+            };
+            state
+                .auth_service
+                .register_user(&username, &password, role, &ctx)
+                .await?;
+            print_user(&state, format, &username).await?;
+        }
+        UserCommand::List => {
+            let users = state.user_repository.list_users().await?;
+            let rows: Vec<UserRow> = users.into_iter().map(From::from).collect();
+            print_rows(format, &rows);
+        }
+        UserCommand::Delete { username } => {
+            let user = find_user(&state, &username).await?;
+            state.auth_service.delete_user(&user.id, &ctx).await?;
+            print_rows(format, &[UserRow::from(user)]);
+        }
+        UserCommand::ResetPassword { username } => {
+            let user = find_user(&state, &username).await?;
+            let password = read_password("New password")?;
+            state
+                .auth_service
+                .reset_password(&user.id, &password, &ctx)
+                .await?;
+            print_rows(format, &[UserRow::from(user)]);
+        }
+        UserCommand::SetRole { username, role } => {
+            let user = find_user(&state, &username).await?;
+            state
+                .auth_service
+                .set_user_role(&user.id, role, &ctx)
+                .await?;
+            print_user(&state, format, &username).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn find_user(state: &AppState, username: &str) -> CliResult<UserEntity> {
+    state
+        .user_repository
+        .get_user_by_username(username)
+        .await?
+        .ok_or_else(|| CliError::Invalid(format!("User '{username}' does not exist")))
+}
+
+async fn print_user(state: &AppState, format: OutputFormat, username: &str) -> CliResult<()> {
+    let user = find_user(state, username).await?;
+    print_rows(format, &[UserRow::from(user)]);
+    Ok(())
+}
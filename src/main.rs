@@ -1,98 +1,501 @@
+pub mod cli;
 pub mod middleware;
 pub mod pages;
 pub mod repositories;
 pub mod services;
 pub mod utils;
 
-use std::{env, sync::Arc};
+#[cfg(not(any(feature = "postgres", feature = "sqlite", feature = "memory")))]
+compile_error!("at least one storage backend feature must be enabled: postgres, sqlite, memory");
+
+use std::{sync::Arc, time::Duration};
 
 use actix_files::Files;
 
+use clap::Parser;
+
 use chrono::{DateTime, Utc};
-use middleware::jwt_session::JwtSession;
+use cli::{Cli, Command};
+use middleware::{
+    http_metrics::HttpMetrics, jwt_session::JwtSession, request_tracing::RequestTracing,
+    security_headers::SecurityHeaders,
+};
+use repositories::{
+    audit_repository::AuditRepository, cached_user_repository::CachedUserRepository,
+    checklist_repository::ChecklistRepository, health_repository::HealthRepository,
+    list_repository::ListRepository, session_store::SessionStore, tag_repository::TagRepository,
+    todo_repository::TodoRepository, unit_of_work::UnitOfWork, user_repository::UserRepository,
+};
+#[cfg(feature = "memory")]
 use repositories::{
+    in_memory_audit_repository::InMemoryAuditRepository,
+    in_memory_checklist_repository::InMemoryChecklistRepository,
+    in_memory_health_repository::InMemoryHealthRepository,
+    in_memory_list_repository::InMemoryListRepository,
+    in_memory_session_store::InMemorySessionStore,
+    in_memory_tag_repository::InMemoryTagRepository,
     in_memory_todo_repository::InMemoryTodoRepository,
-    in_memory_user_repository::InMemoryUserRepository, sql_todo_repository::SqlTodoRepository,
-    sql_user_repository::SqlUserRepository, user_repository::UserRepository,
+    in_memory_unit_of_work::{InMemoryUnitOfWork, TransactionLock},
+    in_memory_user_repository::InMemoryUserRepository,
+};
+#[cfg(feature = "postgres")]
+use repositories::{
+    sql_audit_repository::SqlAuditRepository, sql_checklist_repository::SqlChecklistRepository,
+    sql_health_repository::SqlHealthRepository, sql_list_repository::SqlListRepository,
+    sql_session_store::SqlSessionStore, sql_tag_repository::SqlTagRepository,
+    sql_todo_repository::SqlTodoRepository, sql_unit_of_work::SqlUnitOfWork,
+    sql_user_repository::SqlUserRepository,
+};
+#[cfg(feature = "sqlite")]
+use repositories::{
+    sqlite_audit_repository::SqliteAuditRepository,
+    sqlite_checklist_repository::SqliteChecklistRepository,
+    sqlite_health_repository::SqliteHealthRepository, sqlite_list_repository::SqliteListRepository,
+    sqlite_session_store::SqliteSessionStore, sqlite_tag_repository::SqliteTagRepository,
+    sqlite_todo_repository::SqliteTodoRepository, sqlite_unit_of_work::SqliteUnitOfWork,
+    sqlite_user_repository::SqliteUserRepository,
 };
 use services::{
-    auth_service::AuthService, db_auth_service::DbAuthService, todo_service::TodoService,
+    argon2_password_hasher::Argon2PasswordHasher, audit_service::AuditService,
+    auth_service::AuthService, checklist_service::ChecklistService,
+    csp_report_service::CspReportService, db_auth_service::DbAuthService,
+    health_service::HealthService, jwt_session_service::JwtSessionService,
+    list_service::ListService, metrics_service::MetricsService,
+    password_hashing_pool::PasswordHashingPool, server_session_service::ServerSessionService,
+    session_service::SessionService, tag_service::TagService, todo_service::TodoService,
 };
-use sqlx::{Pool, Postgres};
 pub use utils::askama_to_actix_responder::*;
 
-use actix_web::{web, App, HttpServer};
+use actix_web::{
+    http::header::HeaderValue, rt::task::JoinHandle, web, App, HttpRequest, HttpServer,
+};
 use pages::{
+    admin::admin_audit_page,
+    csp_reports::{admin_csp_reports_page, csp_report_submit},
+    health::{healthz, readyz},
     index::index_redirect,
+    jwks::jwks,
+    lists::{archive_list_submit, create_list_submit, delete_list_submit, rename_list_submit},
     login::{login_page, login_submit},
+    metrics::metrics,
+    profile::profile_page,
     register::{register_page, register_submit},
-    todos::{create_todo_submit, todos_page},
+    sessions::{revoke_session_submit, sessions_page},
+    tags::{delete_tag_submit, merge_tag_submit, rename_tag_submit, tags_page},
+    todo::{
+        add_checklist_item_submit, auto_complete_todo_submit, complete_checklist_item_submit,
+        delete_checklist_item_submit, move_checklist_item_submit, todo_page,
+    },
+    todos::{
+        complete_todo_submit, create_todo_submit, delete_todo_submit, move_todo_submit,
+        move_todo_to_list_submit, todos_page, update_todo_details_submit,
+    },
 };
 use serde::{Deserialize, Serialize};
+use services::password_hasher::PasswordHasherError;
+use sqlx::migrate::MigrateError;
+use thiserror::Error;
+use utils::{
+    database::{DatabasePool, DatabaseSettings},
+    global_auth::{
+        get_metrics_token, get_password_hash_max_concurrency, get_password_hash_queue_timeout,
+        get_secure_cookies, get_session_idle_timeout, get_session_max_age, get_session_mode,
+        get_shutdown_timeout_secs, get_user_cache_capacity, get_user_cache_ttl, ConfigError,
+        SessionMode,
+    },
+    jwt_keyring::{JwtKeyring, JwtKeyringError},
+    logging::{init_cli_logging, init_logging},
+    tls::{redirect_to_https, ReloadingCertResolver, TlsError, TlsSettings},
+};
+
+/// How many CSP violation reports are kept
+const CSP_REPORTS_LIMIT: usize = 100;
+/// Readiness checks taking longer than this are reported as down
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Serves the app, or only redirects to HTTPS when TLS is configured
+const HTTP_PORT: u16 = 3000;
+/// How often expired sessions are removed from the session store
+const SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Configuration in the environment the app's state can't be built with
+#[derive(Error, Debug)]
+pub enum AppStateError {
+    #[error("Failed to load JWT keys: {0}")]
+    JwtKeyring(#[from] JwtKeyringError),
+    #[error("Failed to configure password hashing: {0}")]
+    PasswordHashing(#[from] PasswordHasherError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+/// Why the app couldn't be served
+#[derive(Error, Debug)]
+pub enum ServeError {
+    #[error(transparent)]
+    AppState(#[from] AppStateError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Tls(#[from] TlsError),
+    #[error("Failed to connect to the database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Failed to execute migrations: {0}")]
+    Migrate(#[from] MigrateError),
+}
 
 pub struct AppState {
     auth_service: Box<dyn AuthService>,
     user_repository: Arc<dyn UserRepository>,
+    session_service: Arc<dyn SessionService>,
+    /// only loaded in JWT session mode
+    jwt_keyring: Option<Arc<JwtKeyring>>,
     todo_service: TodoService,
+    list_service: ListService,
+    tag_service: TagService,
+    checklist_service: ChecklistService,
+    audit_service: Arc<AuditService>,
+    csp_report_service: CspReportService,
+    health_service: HealthService,
+    metrics_service: Arc<MetricsService>,
+    /// required to scrape `/metrics`, which is disabled without it
+    metrics_token: Option<String>,
+    /// only set when backed by a database
+    db_pool: Option<DatabasePool>,
+    /// whether cookies are marked `Secure`
+    secure_cookies: bool,
 }
 
 impl AppState {
-    pub fn new_with_pool(pool: Pool<Postgres>) -> Self {
-        let user_repo = Arc::new(SqlUserRepository::new(pool.clone())) as Arc<dyn UserRepository>;
-        let auth_service = DbAuthService::new(Arc::clone(&user_repo).into());
-        let todo_repo = Box::new(SqlTodoRepository::new(pool));
-        let todo_service = TodoService::new(todo_repo);
-        Self {
+    pub fn new_with_pool(
+        pool: DatabasePool,
+        metrics_service: Arc<MetricsService>,
+    ) -> Result<Self, AppStateError> {
+        let repos = DatabaseRepositories::new(&pool);
+        let user_cache = new_user_cache(Arc::clone(&repos.user))?;
+        let user_repo = match &user_cache {
+            Some(user_cache) => {
+                metrics_service.register_user_cache(Arc::clone(user_cache));
+                Arc::clone(user_cache) as Arc<dyn UserRepository>
+            }
+            None => repos.user,
+        };
+        let unit_of_work = new_unit_of_work(&pool, user_cache.clone());
+        let audit_service = Arc::new(AuditService::new(repos.audit));
+        let (session_service, jwt_keyring) = new_session_service(repos.session, &audit_service)?;
+        let auth_service = DbAuthService::new(
+            Arc::clone(&user_repo),
+            Arc::clone(&unit_of_work),
+            new_password_hashing_pool()?,
+            Arc::clone(&session_service),
+            Arc::clone(&audit_service),
+            Arc::clone(&metrics_service),
+        );
+        let todo_service = TodoService::new(
+            repos.todo,
+            Arc::clone(&repos.list),
+            Arc::clone(&unit_of_work),
+        );
+        let list_service = ListService::new(repos.list, Arc::clone(&unit_of_work));
+        let tag_service = TagService::new(repos.tag, Arc::clone(&unit_of_work));
+        let checklist_service = ChecklistService::new(repos.checklist, unit_of_work);
+        Ok(Self {
             auth_service: Box::new(auth_service),
             user_repository: user_repo,
+            session_service,
+            jwt_keyring,
             todo_service,
-        }
+            list_service,
+            tag_service,
+            checklist_service,
+            audit_service,
+            csp_report_service: CspReportService::new(CSP_REPORTS_LIMIT),
+            health_service: HealthService::new(repos.health, HEALTH_CHECK_TIMEOUT),
+            metrics_service,
+            metrics_token: get_metrics_token(),
+            db_pool: Some(pool),
+            secure_cookies: get_secure_cookies()?,
+        })
     }
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        let user_repo = Arc::new(InMemoryUserRepository::new()) as Arc<dyn UserRepository>;
-        let auth_service = DbAuthService::new(Arc::clone(&user_repo).into());
-        let todo_repo = Box::new(InMemoryTodoRepository::new());
-        let todo_service = TodoService::new(todo_repo);
-        Self {
+#[cfg(feature = "memory")]
+impl AppState {
+    /// Keeps everything in memory, so it is lost when the app stops.
+    pub fn new_in_memory(metrics_service: Arc<MetricsService>) -> Result<Self, AppStateError> {
+        let transaction_lock = TransactionLock::default();
+        let user_repo = Arc::new(InMemoryUserRepository::new(transaction_lock.clone()));
+        let list_repo = Arc::new(InMemoryListRepository::new(transaction_lock.clone()));
+        let todo_repo = Arc::new(InMemoryTodoRepository::new(
+            Arc::clone(&list_repo),
+            transaction_lock.clone(),
+        ));
+        let tag_repo = Arc::new(InMemoryTagRepository::new(
+            Arc::clone(&todo_repo),
+            transaction_lock.clone(),
+        ));
+        let checklist_repo = Arc::new(InMemoryChecklistRepository::new(
+            Arc::clone(&todo_repo),
+            transaction_lock.clone(),
+        ));
+        let audit_repo = Arc::new(InMemoryAuditRepository::new());
+        let unit_of_work = Arc::new(InMemoryUnitOfWork::new(
+            transaction_lock,
+            Arc::clone(&user_repo),
+            Arc::clone(&todo_repo),
+            Arc::clone(&list_repo),
+            Arc::clone(&tag_repo),
+            Arc::clone(&checklist_repo),
+            Arc::clone(&audit_repo),
+        ));
+        let audit_service = Arc::new(AuditService::new(audit_repo));
+        let session_store = Arc::new(InMemorySessionStore::new()) as Arc<dyn SessionStore>;
+        let (session_service, jwt_keyring) = new_session_service(session_store, &audit_service)?;
+        let auth_service = DbAuthService::new(
+            Arc::clone(&user_repo) as Arc<dyn UserRepository>,
+            Arc::clone(&unit_of_work) as Arc<dyn UnitOfWork>,
+            new_password_hashing_pool()?,
+            Arc::clone(&session_service),
+            Arc::clone(&audit_service),
+            Arc::clone(&metrics_service),
+        );
+        let todo_service = TodoService::new(
+            todo_repo,
+            Arc::clone(&list_repo) as Arc<dyn ListRepository>,
+            Arc::clone(&unit_of_work) as Arc<dyn UnitOfWork>,
+        );
+        let list_service =
+            ListService::new(list_repo, Arc::clone(&unit_of_work) as Arc<dyn UnitOfWork>);
+        let tag_service =
+            TagService::new(tag_repo, Arc::clone(&unit_of_work) as Arc<dyn UnitOfWork>);
+        let checklist_service = ChecklistService::new(checklist_repo, unit_of_work);
+        Ok(Self {
             auth_service: Box::new(auth_service),
             user_repository: user_repo,
+            session_service,
+            jwt_keyring,
             todo_service,
+            list_service,
+            tag_service,
+            checklist_service,
+            audit_service,
+            csp_report_service: CspReportService::new(CSP_REPORTS_LIMIT),
+            health_service: HealthService::new(
+                Arc::new(InMemoryHealthRepository),
+                HEALTH_CHECK_TIMEOUT,
+            ),
+            metrics_service,
+            metrics_token: get_metrics_token(),
+            db_pool: None,
+            secure_cookies: get_secure_cookies()?,
+        })
+    }
+}
+
+/// The repositories of the database backend `pool` connects to.
+struct DatabaseRepositories {
+    user: Arc<dyn UserRepository>,
+    todo: Arc<dyn TodoRepository>,
+    list: Arc<dyn ListRepository>,
+    tag: Arc<dyn TagRepository>,
+    checklist: Arc<dyn ChecklistRepository>,
+    audit: Arc<dyn AuditRepository>,
+    session: Arc<dyn SessionStore>,
+    health: Arc<dyn HealthRepository>,
+}
+
+impl DatabaseRepositories {
+    fn new(pool: &DatabasePool) -> Self {
+        match *pool {
+            #[cfg(feature = "postgres")]
+            DatabasePool::Postgres(ref pool) => Self {
+                user: Arc::new(SqlUserRepository::new(pool.clone())),
+                todo: Arc::new(SqlTodoRepository::new(pool.clone())),
+                list: Arc::new(SqlListRepository::new(pool.clone())),
+                tag: Arc::new(SqlTagRepository::new(pool.clone())),
+                checklist: Arc::new(SqlChecklistRepository::new(pool.clone())),
+                audit: Arc::new(SqlAuditRepository::new(pool.clone())),
+                session: Arc::new(SqlSessionStore::new(pool.clone())),
+                health: Arc::new(SqlHealthRepository::new(pool.clone())),
+            },
+            #[cfg(feature = "sqlite")]
+            DatabasePool::Sqlite(ref pool) => Self {
+                user: Arc::new(SqliteUserRepository::new(pool.clone())),
+                todo: Arc::new(SqliteTodoRepository::new(pool.clone())),
+                list: Arc::new(SqliteListRepository::new(pool.clone())),
+                tag: Arc::new(SqliteTagRepository::new(pool.clone())),
+                checklist: Arc::new(SqliteChecklistRepository::new(pool.clone())),
+                audit: Arc::new(SqliteAuditRepository::new(pool.clone())),
+                session: Arc::new(SqliteSessionStore::new(pool.clone())),
+                health: Arc::new(SqliteHealthRepository::new(pool.clone())),
+            },
         }
     }
 }
 
+/// Wraps the user repository in a lookup cache, unless disabled with `USER_CACHE_CAPACITY=0`.
+fn new_user_cache(
+    user_repo: Arc<dyn UserRepository>,
+) -> Result<Option<Arc<CachedUserRepository>>, ConfigError> {
+    Ok(match get_user_cache_capacity()? {
+        Some(capacity) => Some(Arc::new(CachedUserRepository::new(
+            user_repo,
+            capacity,
+            get_user_cache_ttl()?,
+        ))),
+        None => None,
+    })
+}
+
+/// Transactions over the repositories of the database backend `pool` connects to, evicting
+/// the users they change from `user_cache`.
+#[cfg_attr(
+    not(any(feature = "postgres", feature = "sqlite")),
+    allow(unused_variables)
+)]
+fn new_unit_of_work(
+    pool: &DatabasePool,
+    user_cache: Option<Arc<CachedUserRepository>>,
+) -> Arc<dyn UnitOfWork> {
+    match *pool {
+        #[cfg(feature = "postgres")]
+        DatabasePool::Postgres(ref pool) => Arc::new(SqlUnitOfWork::new(pool.clone(), user_cache)),
+        #[cfg(feature = "sqlite")]
+        DatabasePool::Sqlite(ref pool) => Arc::new(SqliteUnitOfWork::new(pool.clone(), user_cache)),
+    }
+}
+
+fn new_password_hashing_pool() -> Result<PasswordHashingPool, AppStateError> {
+    let hasher = Argon2PasswordHasher::from_env()?;
+    Ok(PasswordHashingPool::new(
+        Arc::new(hasher),
+        get_password_hash_max_concurrency()?,
+        get_password_hash_queue_timeout()?,
+    ))
+}
+
+/// The session service, and the JWT keyring it signs with in JWT mode
+type SessionServices = (Arc<dyn SessionService>, Option<Arc<JwtKeyring>>);
+
+/// Picks the session implementation for the configured `SESSION_MODE`, returning the JWT
+/// keyring in JWT mode. The session store is only used in server-side mode.
+fn new_session_service(
+    session_store: Arc<dyn SessionStore>,
+    audit_service: &Arc<AuditService>,
+) -> Result<SessionServices, AppStateError> {
+    Ok(match get_session_mode()? {
+        SessionMode::Jwt => {
+            let keyring = Arc::new(JwtKeyring::from_env()?);
+            let session_service = JwtSessionService::new(Arc::clone(&keyring));
+            (Arc::new(session_service), Some(keyring))
+        }
+        SessionMode::Server => {
+            let session_service = ServerSessionService::new(
+                session_store,
+                Arc::clone(audit_service),
+                get_session_idle_timeout()?,
+                get_session_max_age()?,
+            );
+            (Arc::new(session_service), None)
+        }
+    })
+}
+
+/// Removes expired sessions every [SESSION_CLEANUP_INTERVAL], starting right away.
+fn spawn_session_cleanup(session_service: Arc<dyn SessionService>) -> JoinHandle<()> {
+    actix_web::rt::spawn(async move {
+        let mut interval = tokio::time::interval(SESSION_CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            match session_service.delete_expired_sessions().await {
+                Ok(deleted) => tracing::debug!(deleted, "removed expired sessions"),
+                Err(e) => tracing::error!(error = %e, "failed to remove expired sessions"),
+            }
+        }
+    })
+}
+
+/// Claims of session JWTs, using the registered claim names so other services can verify them.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct TokenClaims {
+    #[serde(rename = "sub")]
     id: String,
+    #[serde(rename = "iat", with = "chrono::serde::ts_seconds")]
     issued: DateTime<Utc>,
+    #[serde(rename = "exp", with = "chrono::serde::ts_seconds")]
     expiration: DateTime<Utc>,
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
+    let cli = Cli::parse();
 
-    let pg_url = env::var("DATABASE_URL").expect("DATABASE_URL must be in env");
-    let pool: Pool<Postgres> = sqlx::postgres::PgPool::connect(&pg_url)
-        .await
-        .expect("Failed to initialize postgres database");
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            if let Err(e) = serve().await {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        command => {
+            init_cli_logging();
+            if let Err(e) = cli::run(command, cli.format).await {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Connects to the database of `DATABASE_URL` and applies its migrations, unless the data is
+/// kept in memory.
+async fn new_app_state(
+    settings: &DatabaseSettings,
+    metrics_service: Arc<MetricsService>,
+) -> Result<AppState, ServeError> {
+    #[cfg(feature = "memory")]
+    if settings.is_in_memory() {
+        tracing::warn!("keeping all data in memory, it will be lost when the app stops");
+        return Ok(AppState::new_in_memory(metrics_service)?);
+    }
+
+    // without a database backend in this build, connecting reports the supported URLs
+    let pool = utils::database::connect_with_retry(settings).await?;
+    pool.migrate().await?;
+
+    Ok(AppState::new_with_pool(pool, metrics_service)?)
+}
+
+async fn serve() -> Result<(), ServeError> {
+    let metrics_service = Arc::new(MetricsService::new());
+    init_logging(Arc::clone(&metrics_service))?;
 
-    sqlx::migrate!("./migrations")
-        .run(&pool)
-        .await
-        .expect("Failed to execute migrations");
+    let database_settings = DatabaseSettings::from_env()?;
+    let security_headers = SecurityHeaders::from_env()?;
+    let shutdown_timeout_secs = get_shutdown_timeout_secs()?;
+    let tls_settings = TlsSettings::from_env()?;
 
-    let app_state = web::Data::new(AppState::new_with_pool(pool));
+    let app_state = web::Data::new(new_app_state(&database_settings, metrics_service).await?);
+    let db_pool = app_state.db_pool.clone();
+    let session_cleanup = spawn_session_cleanup(Arc::clone(&app_state.session_service));
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
             .app_data(app_state.clone())
-            // TODO - figure out how to correctly order conflicting services with and without auth middleware
+            .wrap(security_headers.clone())
+            .wrap(HttpMetrics)
+            .wrap(RequestTracing)
             .service(Files::new("/static", "./static"))
             .service(index_redirect)
+            .service(jwks)
+            .service(healthz)
+            .service(readyz)
+            .service(metrics)
+            .service(csp_report_submit)
             .service(register_page)
             .service(register_submit)
             .service(login_page)
@@ -100,11 +503,87 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("/home")
                     .wrap(JwtSession)
+                    // pages under /home have private URLs, e.g. todo IDs
+                    .wrap(
+                        security_headers
+                            .clone()
+                            .referrer_policy(HeaderValue::from_static("no-referrer")),
+                    )
                     .service(todos_page)
-                    .service(create_todo_submit),
+                    .service(create_todo_submit)
+                    .service(delete_todo_submit)
+                    .service(complete_todo_submit)
+                    .service(move_todo_submit)
+                    .service(update_todo_details_submit)
+                    .service(move_todo_to_list_submit)
+                    .service(todo_page)
+                    .service(add_checklist_item_submit)
+                    .service(complete_checklist_item_submit)
+                    .service(move_checklist_item_submit)
+                    .service(delete_checklist_item_submit)
+                    .service(auto_complete_todo_submit)
+                    .service(create_list_submit)
+                    .service(rename_list_submit)
+                    .service(archive_list_submit)
+                    .service(delete_list_submit)
+                    .service(tags_page)
+                    .service(rename_tag_submit)
+                    .service(merge_tag_submit)
+                    .service(delete_tag_submit)
+                    .service(profile_page)
+                    .service(sessions_page)
+                    .service(revoke_session_submit)
+                    .service(admin_audit_page)
+                    .service(admin_csp_reports_page),
             )
     })
-    .bind(("0.0.0.0", 3000))?
-    .run()
-    .await
+    .shutdown_timeout(shutdown_timeout_secs);
+
+    // on SIGTERM the servers stop accepting connections, and return once in-flight requests
+    // are done or the shutdown timeout has passed
+    let served = match tls_settings {
+        Some(tls_settings) => {
+            let cert_resolver = Arc::new(ReloadingCertResolver::new(
+                tls_settings.cert_path,
+                tls_settings.key_path,
+            )?);
+            let reloader = cert_resolver.spawn_reloader();
+
+            let https_port = tls_settings.https_port;
+            tracing::info!(
+                port = https_port,
+                redirect_port = HTTP_PORT,
+                "serving HTTPS"
+            );
+            let https_server = server
+                .bind_rustls(("0.0.0.0", https_port), cert_resolver.server_config())?
+                .run();
+            let redirect_server = HttpServer::new(move || {
+                App::new().default_service(web::to(move |req: HttpRequest| async move {
+                    redirect_to_https(&req, https_port)
+                }))
+            })
+            .shutdown_timeout(shutdown_timeout_secs)
+            .bind(("0.0.0.0", HTTP_PORT))?
+            .run();
+
+            let served = futures_util::future::try_join(https_server, redirect_server)
+                .await
+                .map(|_| ());
+            reloader.abort();
+            served
+        }
+        None => {
+            tracing::info!(port = HTTP_PORT, "serving HTTP");
+            server.bind(("0.0.0.0", HTTP_PORT))?.run().await
+        }
+    };
+    session_cleanup.abort();
+
+    if let Some(pool) = db_pool {
+        tracing::info!("closing database connections");
+        pool.close().await;
+    }
+    tracing::info!("shut down");
+    Ok(served?)
 }
@@ -0,0 +1,86 @@
+//! HTTP metrics middleware. Records the latency of every request in the app's
+//! `MetricsService`, labelled with the method, matched route and status.
+//!
+//! Requests which don't match a route are labelled `unmatched`, so scanning for random
+//! paths can't create unbounded series.
+
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    time::Instant,
+};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web::Data,
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+
+use crate::AppState;
+
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+pub struct HttpMetrics;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for HttpMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = HttpMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HttpMetricsMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct HttpMetricsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for HttpMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let app_state = req
+            .app_data::<Data<AppState>>()
+            .expect("Fatal: could not access app data")
+            .clone();
+        let method = req.method().to_string();
+        let started = Instant::now();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await;
+            let (route, status) = match &res {
+                Ok(res) => (res.request().match_pattern(), res.status().as_u16()),
+                // the request isn't available with errors from inner middleware
+                Err(e) => (None, e.as_response_error().status_code().as_u16()),
+            };
+            app_state.metrics_service.observe_http_request(
+                &method,
+                route.as_deref().unwrap_or(UNMATCHED_ROUTE),
+                status,
+                started.elapsed(),
+            );
+            res
+        })
+    }
+}
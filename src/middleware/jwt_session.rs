@@ -1,9 +1,11 @@
-//! JWT Session middleware. Does the following:
-//! 1. extract the auth cookie from request (auth cookie should be set by POST /login)
-//! 2. verify the token
+//! Session middleware. Does the following:
+//! 1. extract the session cookie from request (session cookie should be set by POST /login)
+//! 2. resolve the session with the configured `SessionService` (a JWT or a server-side session)
 //! 3. find the user from the repository
-//! 4. add the UserEntity into the request extensions
-//! If steps 1-3 fail or are not found, then the response will redirect to /login
+//! 4. add the `AuthenticatedUser` into the request extensions
+//!
+//! If steps 1-3 fail or are not found, then the response will redirect to /login. GET requests
+//! pass their path as the `next` parameter, so the user is returned to it after logging in.
 
 // NOTE: combination of actix rules from:
 // * async calls: https://github.com/actix/examples/blob/344bcfce10647748444695d3aa302ba3bb241310/middleware/middleware/src/read_request_body.rs
@@ -18,16 +20,12 @@ use actix_web::{
     body::EitherBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
     http::header::LOCATION,
-    web::Data,
     Error, HttpMessage, HttpResponse,
 };
-use chrono::Utc;
 use futures_util::future::LocalBoxFuture;
-use jwt::VerifyWithKey;
 
-use crate::{
-    utils::global_auth::{get_jwt_signing_key, JWT_AUTH_COOKIE_NAME},
-    AppState, TokenClaims,
+use crate::utils::{
+    authenticated_user::AuthenticatedUser, redirect_target::login_redirect_location,
 };
 
 // There are two steps in middleware processing.
@@ -78,32 +76,9 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let service = self.service.clone();
         Box::pin(async move {
-            let token_cookie = match req.cookie(JWT_AUTH_COOKIE_NAME) {
-                Some(token) => token,
-                None => return redirect_to_login_middleware_response(req),
-            };
-            let jwt_token = token_cookie.value();
-
-            let key = get_jwt_signing_key();
-            let claims = match VerifyWithKey::<TokenClaims>::verify_with_key(jwt_token, &key) {
-                Ok(claims) => claims,
-                Err(_) => return redirect_to_login_middleware_response(req),
-            };
-
-            if claims.expiration < Utc::now() {
-                return redirect_to_login_middleware_response(req);
-            }
-
-            let app_state = req
-                .app_data::<Data<AppState>>()
-                .expect("Fatal: could not access app data");
-            let user = app_state
-                .user_repository
-                .get_user_by_id(&claims.id)
-                .await
-                .expect("Fatal: failed to contact database");
+            let authenticated = AuthenticatedUser::from_session_cookie(req.request()).await?;
 
-            let user = match user {
+            let user = match authenticated {
                 Some(val) => val,
                 None => return redirect_to_login_middleware_response(req),
             };
@@ -120,11 +95,11 @@ fn redirect_to_login_middleware_response<B: 'static>(
     req: ServiceRequest,
 ) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
     let response = HttpResponse::Found()
-        .insert_header((LOCATION, "/login"))
+        .insert_header((LOCATION, login_redirect_location(req.request())))
         .finish()
         // constructed responses map to "right" body
         .map_into_right_body();
 
     let (http_req, _) = req.into_parts();
-    return Ok(ServiceResponse::new(http_req, response));
+    Ok(ServiceResponse::new(http_req, response))
 }
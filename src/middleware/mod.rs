@@ -1 +1,4 @@
+pub mod http_metrics;
 pub mod jwt_session;
+pub mod request_tracing;
+pub mod security_headers;
@@ -0,0 +1,142 @@
+//! Request tracing middleware. Does the following:
+//! 1. take the request ID from a valid incoming `X-Request-Id` header, or generate one
+//! 2. run the request in a `request` span with the request ID, method and path, so the logs
+//!    of services and repositories can be traced back to the request
+//! 3. record the status, latency and signed in user on the span, and log the request
+//! 4. return the request ID in the `X-Request-Id` response header
+//!
+//! The request ID is also available to handlers through [current_request_id], e.g. to show
+//! it on error pages.
+
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    time::Instant,
+};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+use tracing::{field::Empty, Instrument};
+
+use crate::utils::{authenticated_user::AuthenticatedUser, random_id};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest incoming request ID which is accepted, longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// ID of the request currently being handled, `None` outside of [RequestTracing].
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+pub struct RequestTracing;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for RequestTracing
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestTracingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestTracingMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .filter(|id| is_valid_request_id(id))
+            .map_or_else(random_id, String::from);
+
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id,
+            method = %req.method(),
+            path = %req.path(),
+            status = Empty,
+            latency_ms = Empty,
+            user_id = Empty,
+        );
+
+        let started = Instant::now();
+        let fut = span.in_scope(|| self.service.call(req));
+
+        Box::pin(REQUEST_ID.scope(request_id.clone(), async move {
+            let res = fut.instrument(span.clone()).await;
+            span.record("latency_ms", started.elapsed().as_millis() as u64);
+
+            // errors from inner middleware are logged with their status and passed on, actix
+            // turns them into responses after this middleware so they don't get the header
+            let mut res = match res {
+                Ok(res) => res,
+                Err(e) => {
+                    let status = e.as_response_error().status_code();
+                    span.record("status", status.as_u16());
+                    span.in_scope(|| tracing::error!(error = %e, "request failed"));
+                    return Err(e);
+                }
+            };
+
+            let status = res.status();
+            span.record("status", status.as_u16());
+            if let Some(user) = res.request().extensions().get::<AuthenticatedUser>() {
+                span.record("user_id", user.id.as_str());
+            }
+            span.in_scope(|| {
+                if status.is_server_error() {
+                    tracing::error!("request failed");
+                } else {
+                    tracing::info!("request finished");
+                }
+            });
+
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                res.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
@@ -0,0 +1,249 @@
+//! Security headers middleware. Sets `Content-Security-Policy`, `X-Frame-Options`,
+//! `Referrer-Policy`, `Strict-Transport-Security`, `Permissions-Policy` and
+//! `X-Content-Type-Options` on every response.
+//!
+//! Headers already present on the response are left alone. As the middleware of a scope runs
+//! before the app-wide one on the way out, wrapping a scope with its own `SecurityHeaders`
+//! overrides the app-wide headers for that scope.
+//!
+//! In report-only mode the CSP is sent as `Content-Security-Policy-Report-Only`, with
+//! violations reported to [CSP_REPORT_PATH] instead of being blocked.
+
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{
+        HeaderMap, HeaderName, HeaderValue, InvalidHeaderValue, CONTENT_SECURITY_POLICY,
+        CONTENT_SECURITY_POLICY_REPORT_ONLY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY,
+        X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+    },
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+
+use crate::utils::global_auth::{parse_env, ConfigError, ConfigResult};
+
+/// Where browsers send CSP violation reports in report-only mode.
+pub const CSP_REPORT_PATH: &str = "/csp-reports";
+
+/// No scripts at all, as pages are plain HTML forms. The images are the logo and avatar in
+/// `nav.html`.
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; script-src 'none'; \
+    style-src 'self'; img-src 'self' https://tailwindui.com https://images.unsplash.com; \
+    form-action 'self'; frame-ancestors 'none'; base-uri 'none'";
+
+const PERMISSIONS_POLICY: HeaderName = HeaderName::from_static("permissions-policy");
+
+/// The header values are parsed when they're set, so invalid ones are found at startup rather
+/// than when a worker builds its middleware.
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    /// includes the report URI in report-only mode
+    content_security_policy: HeaderValue,
+    csp_report_only: bool,
+    frame_options: HeaderValue,
+    referrer_policy: HeaderValue,
+    /// `None` to not send HSTS
+    strict_transport_security: Option<HeaderValue>,
+    permissions_policy: HeaderValue,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self {
+            content_security_policy: HeaderValue::from_static(DEFAULT_CONTENT_SECURITY_POLICY),
+            csp_report_only: false,
+            frame_options: HeaderValue::from_static("DENY"),
+            referrer_policy: HeaderValue::from_static("strict-origin-when-cross-origin"),
+            strict_transport_security: Some(HeaderValue::from_static(
+                "max-age=63072000; includeSubDomains",
+            )),
+            permissions_policy: HeaderValue::from_static(
+                "camera=(), microphone=(), geolocation=(), payment=(), usb=()",
+            ),
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// The defaults, with overrides from env:
+    /// - `CONTENT_SECURITY_POLICY`: replaces [DEFAULT_CONTENT_SECURITY_POLICY]
+    /// - `CSP_REPORT_ONLY=true`: report violations instead of blocking them
+    pub fn from_env() -> ConfigResult<Self> {
+        let policy = std::env::var("CONTENT_SECURITY_POLICY")
+            .unwrap_or_else(|_| String::from(DEFAULT_CONTENT_SECURITY_POLICY));
+        let report_only = parse_env("CSP_REPORT_ONLY", "'true' or 'false'", false)?;
+        Self::default()
+            .content_security_policy(&policy, report_only)
+            .map_err(|_| ConfigError::Invalid {
+                name: String::from("CONTENT_SECURITY_POLICY"),
+                expected: "a valid header value",
+                value: policy,
+            })
+    }
+
+    /// Sets the CSP, only reporting violations to [CSP_REPORT_PATH] when `report_only`.
+    pub fn content_security_policy(
+        mut self,
+        policy: &str,
+        report_only: bool,
+    ) -> Result<Self, InvalidHeaderValue> {
+        self.content_security_policy = if report_only {
+            HeaderValue::from_str(&format!("{policy}; report-uri {CSP_REPORT_PATH}"))?
+        } else {
+            HeaderValue::from_str(policy)?
+        };
+        self.csp_report_only = report_only;
+        Ok(self)
+    }
+
+    pub fn frame_options(mut self, value: HeaderValue) -> Self {
+        self.frame_options = value;
+        self
+    }
+
+    pub fn referrer_policy(mut self, value: HeaderValue) -> Self {
+        self.referrer_policy = value;
+        self
+    }
+
+    pub fn strict_transport_security(mut self, value: Option<HeaderValue>) -> Self {
+        self.strict_transport_security = value;
+        self
+    }
+
+    pub fn permissions_policy(mut self, value: HeaderValue) -> Self {
+        self.permissions_policy = value;
+        self
+    }
+
+    fn header_values(&self) -> Vec<(HeaderName, HeaderValue)> {
+        let csp_header = if self.csp_report_only {
+            CONTENT_SECURITY_POLICY_REPORT_ONLY
+        } else {
+            CONTENT_SECURITY_POLICY
+        };
+
+        let mut headers = vec![
+            (csp_header, self.content_security_policy.clone()),
+            (X_FRAME_OPTIONS, self.frame_options.clone()),
+            (REFERRER_POLICY, self.referrer_policy.clone()),
+            (PERMISSIONS_POLICY, self.permissions_policy.clone()),
+            (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+        ];
+        if let Some(hsts) = &self.strict_transport_security {
+            headers.push((STRICT_TRANSPORT_SECURITY, hsts.clone()));
+        }
+        headers
+    }
+}
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for SecurityHeaders
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SecurityHeadersMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SecurityHeadersMiddleware {
+            service,
+            headers: Rc::new(self.header_values()),
+        }))
+    }
+}
+
+pub struct SecurityHeadersMiddleware<S> {
+    service: S,
+    headers: Rc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl<S, B> Service<ServiceRequest> for SecurityHeadersMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let headers = Rc::clone(&self.headers);
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let mut res = fut.await?;
+            insert_missing_headers(res.headers_mut(), &headers);
+            Ok(res)
+        })
+    }
+}
+
+fn insert_missing_headers(response_headers: &mut HeaderMap, headers: &[(HeaderName, HeaderValue)]) {
+    let has_csp = response_headers.contains_key(CONTENT_SECURITY_POLICY)
+        || response_headers.contains_key(CONTENT_SECURITY_POLICY_REPORT_ONLY);
+
+    for (name, value) in headers {
+        let is_csp =
+            *name == CONTENT_SECURITY_POLICY || *name == CONTENT_SECURITY_POLICY_REPORT_ONLY;
+        let present = if is_csp {
+            has_csp
+        } else {
+            response_headers.contains_key(name)
+        };
+        if !present {
+            response_headers.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{call_service, init_service, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn sets_the_headers() {
+        let headers = SecurityHeaders::default()
+            .content_security_policy("default-src 'self'", true)
+            .unwrap();
+        let app = init_service(
+            App::new()
+                .wrap(headers)
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::get().to_request()).await;
+        let header = |name| res.headers().get(name).map(|value| value.to_str().unwrap());
+        assert_eq!(
+            header(CONTENT_SECURITY_POLICY_REPORT_ONLY),
+            Some("default-src 'self'; report-uri /csp-reports")
+        );
+        assert_eq!(header(CONTENT_SECURITY_POLICY), None);
+        assert_eq!(header(X_FRAME_OPTIONS), Some("DENY"));
+        assert_eq!(header(X_CONTENT_TYPE_OPTIONS), Some("nosniff"));
+    }
+
+    #[test]
+    fn rejects_invalid_policies() {
+        assert!(SecurityHeaders::default()
+            .content_security_policy("default-src 'none'\n", false)
+            .is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use askama::Template;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+
+use crate::{
+    repositories::audit_repository::{AuditEventEntity, AuditEventFilter, AuditEventType},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        service_error_http_response::http_service_error_response,
+    },
+    AppState, TemplateToResponse,
+};
+
+use super::nav::Nav;
+
+#[derive(Template)]
+#[template(path = "admin_audit.html")]
+struct AdminAuditTemplate {
+    nav: Nav,
+    query: AuditQuery,
+    event_types: &'static [AuditEventType],
+    events: Vec<AuditEventEntity>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct AuditQuery {
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    event_type: String,
+    /// inclusive start date, `YYYY-MM-DD` as submitted by an `<input type="date">`
+    #[serde(default)]
+    since: String,
+    /// inclusive end date, `YYYY-MM-DD`
+    #[serde(default)]
+    until: String,
+}
+
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|v| !v.is_empty())
+}
+
+fn parse_day_start(value: &str) -> Result<DateTime<Utc>, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).expect("valid time").and_utc())
+        .map_err(|_| format!("Invalid date: {value}"))
+}
+
+#[get("/admin/audit")]
+async fn admin_audit_page(
+    web::Query(query): web::Query<AuditQuery>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    if !user.is_admin() {
+        return HttpResponse::Forbidden().body("Forbidden");
+    }
+
+    let mut filter = AuditEventFilter::default();
+    let mut error = None;
+
+    if let Some(username) = non_empty(&query.username) {
+        match state.user_repository.get_user_by_username(username).await {
+            Ok(Some(user)) => filter.actor_id = Some(user.id),
+            Ok(None) => error = Some(format!("No user named {username}")),
+            Err(e) => return http_service_error_response(Some(e.to_string())),
+        }
+    }
+    if let Some(event_type) = non_empty(&query.event_type) {
+        match event_type.parse() {
+            Ok(event_type) => filter.event_type = Some(event_type),
+            Err(e) => error = Some(e),
+        }
+    }
+    if let Some(since) = non_empty(&query.since) {
+        match parse_day_start(since) {
+            Ok(since) => filter.since = Some(since),
+            Err(e) => error = Some(e),
+        }
+    }
+    if let Some(until) = non_empty(&query.until) {
+        match parse_day_start(until) {
+            Ok(until) => filter.until = Some(until + chrono::Duration::days(1)),
+            Err(e) => error = Some(e),
+        }
+    }
+
+    let events = if error.is_some() {
+        vec![]
+    } else {
+        match state.audit_service.list_events(&filter).await {
+            Ok(events) => events,
+            Err(e) => return http_service_error_response(Some(e.to_string())),
+        }
+    };
+
+    AdminAuditTemplate {
+        nav: Nav::new(&user, "/home/admin/audit"),
+        query,
+        event_types: &AuditEventType::ALL,
+        events,
+        error,
+    }
+    .to_response()
+}
@@ -0,0 +1,51 @@
+use actix_web::{get, post, web, HttpResponse, Responder};
+use askama::Template;
+
+use crate::{
+    services::csp_report_service::CspReport, utils::authenticated_user::AuthenticatedUser,
+    AppState, TemplateToResponse,
+};
+
+use super::nav::Nav;
+
+/// Violation reports are small, anything bigger isn't a report
+const MAX_REPORT_BYTES: usize = 16 * 1024;
+
+#[post("/csp-reports")]
+async fn csp_report_submit(body: web::Bytes, state: web::Data<AppState>) -> impl Responder {
+    // browsers send `application/csp-report` or `application/reports+json`, so the body is
+    // parsed regardless of content type
+    if body.len() > MAX_REPORT_BYTES {
+        return HttpResponse::PayloadTooLarge().finish();
+    }
+    match serde_json::from_slice(&body) {
+        Ok(report) => {
+            state.csp_report_service.record(&report).await;
+            HttpResponse::NoContent().finish()
+        }
+        Err(_) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin_csp_reports.html")]
+struct AdminCspReportsTemplate {
+    nav: Nav,
+    reports: Vec<CspReport>,
+}
+
+#[get("/admin/csp-reports")]
+async fn admin_csp_reports_page(
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    if !user.is_admin() {
+        return HttpResponse::Forbidden().body("Forbidden");
+    }
+
+    AdminCspReportsTemplate {
+        nav: Nav::new(&user, "/home/admin/csp-reports"),
+        reports: state.csp_report_service.list_reports().await,
+    }
+    .to_response()
+}
@@ -0,0 +1,30 @@
+use actix_web::{get, http::header::CACHE_CONTROL, web, HttpResponse, Responder};
+
+use crate::{services::health_service::HealthReport, AppState};
+
+/// Liveness probe, up as long as the app is serving requests.
+#[get("/healthz")]
+async fn healthz(state: web::Data<AppState>) -> impl Responder {
+    health_response(state.health_service.liveness())
+}
+
+/// Readiness probe, checking the database is reachable and migrated. 503 when it isn't.
+#[get("/readyz")]
+async fn readyz(state: web::Data<AppState>) -> impl Responder {
+    let report = state.health_service.readiness().await;
+    if !report.is_up() {
+        tracing::warn!(?report, "not ready");
+    }
+    health_response(report)
+}
+
+fn health_response(report: HealthReport) -> HttpResponse {
+    let mut response = if report.is_up() {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    response
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .json(report)
+}
@@ -1,9 +1,16 @@
 use actix_web::{get, http::header::LOCATION, HttpResponse, Responder};
 
+use crate::utils::{
+    authenticated_user::AuthenticatedUser, redirect_target::DEFAULT_SIGNED_IN_PATH,
+};
+
 #[get("/")]
-async fn index_redirect() -> impl Responder {
-    // TODO - condition redirect based on auth
+async fn index_redirect(user: Option<AuthenticatedUser>) -> impl Responder {
+    let location = match user {
+        Some(_) => DEFAULT_SIGNED_IN_PATH,
+        None => "/login",
+    };
     HttpResponse::Found()
-        .append_header((LOCATION, "/login"))
+        .append_header((LOCATION, location))
         .body("")
 }
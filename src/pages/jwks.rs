@@ -0,0 +1,19 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::AppState;
+
+/// Public keys for verifying our session JWTs, for other internal services.
+/// Empty when sessions aren't JWTs or are only signed with symmetric keys.
+#[get("/.well-known/jwks.json")]
+async fn jwks(state: web::Data<AppState>) -> impl Responder {
+    let jwks = state
+        .jwt_keyring
+        .as_ref()
+        .map_or(JwkSet { keys: vec![] }, |keyring| keyring.jwks());
+
+    HttpResponse::Ok()
+        // allow verifiers to cache, but pick up rotations reasonably quickly
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(jwks)
+}
@@ -0,0 +1,110 @@
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::{
+    services::{audit_service::AuditContext, list_service::ListServiceError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        service_error_http_response::http_service_error_response,
+    },
+    AppState,
+};
+
+use super::todos::redirect_to;
+
+fn list_href(list_id: &str) -> String {
+    format!("/home/todos?list={list_id}")
+}
+
+fn list_error_response(e: ListServiceError) -> HttpResponse {
+    match e {
+        ListServiceError::InvalidName(message) => HttpResponse::BadRequest().body(message),
+        // the list was deleted in another tab
+        ListServiceError::ItemNotFound => redirect_to("/home/todos"),
+        e => http_service_error_response(Some(e.to_string())),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListNameFormData {
+    name: String,
+}
+
+#[post("/lists")]
+pub async fn create_list_submit(
+    web::Form(form): web::Form<ListNameFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    match state.list_service.create_list(&user.id, &form.name).await {
+        Ok(list_id) => redirect_to(&list_href(&list_id)),
+        Err(e) => list_error_response(e),
+    }
+}
+
+#[post("/lists/{id}/rename")]
+pub async fn rename_list_submit(
+    path: web::Path<String>,
+    web::Form(form): web::Form<ListNameFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let list_id = path.into_inner();
+
+    let res = state
+        .list_service
+        .rename_list(&user.id, &list_id, &form.name)
+        .await;
+
+    match res {
+        Ok(()) => redirect_to(&list_href(&list_id)),
+        Err(e) => list_error_response(e),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ArchiveListFormData {
+    archived: bool,
+}
+
+#[post("/lists/{id}/archive")]
+pub async fn archive_list_submit(
+    path: web::Path<String>,
+    web::Form(form): web::Form<ArchiveListFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let list_id = path.into_inner();
+
+    let res = state
+        .list_service
+        .set_list_archived(&user.id, &list_id, form.archived)
+        .await;
+
+    match res {
+        // an archived list is out of the way, so the user is taken back to their inbox
+        Ok(()) if form.archived => redirect_to("/home/todos"),
+        Ok(()) => redirect_to(&list_href(&list_id)),
+        Err(e) => list_error_response(e),
+    }
+}
+
+#[post("/lists/{id}/delete")]
+pub async fn delete_list_submit(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    ctx: AuditContext,
+) -> impl Responder {
+    let list_id = path.into_inner();
+
+    let res = state
+        .list_service
+        .delete_list(&user.id, &list_id, &ctx)
+        .await;
+
+    match res {
+        Ok(()) | Err(ListServiceError::ItemNotFound) => redirect_to("/home/todos"),
+        Err(e) => list_error_response(e),
+    }
+}
@@ -3,60 +3,95 @@ use askama::Template;
 use serde::Deserialize;
 
 use crate::{
-    services::auth_service::AuthServiceError, utils::global_auth::JWT_AUTH_COOKIE_NAME, AppState,
-    TemplateToResponse,
+    services::{audit_service::AuditContext, auth_service::AuthServiceError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        global_auth::SESSION_COOKIE_NAME,
+        redirect_target::safe_redirect_target,
+        service_error_http_response::{with_request_id, with_server_busy_status},
+    },
+    AppState, TemplateToResponse,
 };
 
 #[derive(Template)]
 #[template(path = "login.html")]
 struct LoginTemplate {
     error: Option<String>,
+    /// page to return to after logging in
+    next: Option<String>,
 }
 
-pub fn show_login_page(error: Option<&str>) -> HttpResponse {
+pub fn show_login_page(error: Option<&str>, next: Option<&str>) -> HttpResponse {
     LoginTemplate {
         error: error.map(String::from),
+        next: next.map(String::from),
     }
     .to_response()
 }
 
+#[derive(Deserialize, Debug)]
+pub struct LoginQuery {
+    next: Option<String>,
+}
+
 #[get("/login")]
-async fn login_page() -> impl Responder {
-    show_login_page(None)
+async fn login_page(
+    web::Query(query): web::Query<LoginQuery>,
+    user: Option<AuthenticatedUser>,
+) -> impl Responder {
+    if user.is_some() {
+        return HttpResponse::Found()
+            .append_header((LOCATION, safe_redirect_target(query.next.as_deref())))
+            .body("");
+    }
+    show_login_page(None, query.next.as_deref())
 }
 
 #[derive(Deserialize, Debug)]
 pub struct LoginFormData {
     username: String,
     password: String,
+    next: Option<String>,
 }
 
 #[post("/login")]
 pub async fn login_submit(
     web::Form(form): web::Form<LoginFormData>,
     state: web::Data<AppState>,
+    ctx: AuditContext,
 ) -> impl Responder {
     let res = state
         .auth_service
-        .authenticate_user(&form.username, &form.password)
+        .authenticate_user(&form.username, &form.password, &ctx)
         .await;
 
-    let access_token = match res {
+    let next = form.next.as_deref();
+    let session_token = match res {
         Ok(token) => token,
         Err(AuthServiceError::IncorrectPassword) => {
-            return show_login_page(Some("Incorrect username or password"))
+            return show_login_page(Some("Incorrect username or password"), next)
         }
         Err(AuthServiceError::UserDoesNotExists) => {
-            return show_login_page(Some("Incorrect username or password"))
+            return show_login_page(Some("Incorrect username or password"), next)
+        }
+        Err(AuthServiceError::ServerBusy) => {
+            return with_server_busy_status(show_login_page(
+                Some("The server is busy, please try again"),
+                next,
+            ))
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "failed to log in");
+            return show_login_page(Some(&with_request_id("Unknown error")), next);
         }
-        _ => return show_login_page(Some("Unknown error")),
     };
 
     HttpResponse::Found()
-        .append_header((LOCATION, format!("/home/todos")))
+        .append_header((LOCATION, safe_redirect_target(next)))
         .cookie(
-            Cookie::build(JWT_AUTH_COOKIE_NAME, access_token)
+            Cookie::build(SESSION_COOKIE_NAME, session_token)
                 .http_only(true)
+                .secure(state.secure_cookies)
                 .finish(),
         )
         .body("")
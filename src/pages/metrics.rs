@@ -0,0 +1,139 @@
+use actix_web::{get, http::header::WWW_AUTHENTICATE, web, HttpResponse, Responder};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use sha2::{Digest, Sha256};
+
+use crate::AppState;
+
+/// Prometheus metrics, for scrapers presenting `METRICS_TOKEN` as a bearer token. Not found
+/// when no token is configured.
+#[get("/metrics")]
+async fn metrics(auth: Option<BearerAuth>, state: web::Data<AppState>) -> impl Responder {
+    let token = match &state.metrics_token {
+        Some(token) => token,
+        None => return HttpResponse::NotFound().finish(),
+    };
+    if !auth.is_some_and(|auth| tokens_match(auth.token(), token)) {
+        return HttpResponse::Unauthorized()
+            .insert_header((WWW_AUTHENTICATE, "Bearer"))
+            .finish();
+    }
+
+    let metrics_service = &state.metrics_service;
+    if let Some(pool) = &state.db_pool {
+        metrics_service.set_db_pool_stats(pool);
+    }
+    match (
+        state.user_repository.count_users().await,
+        state.todo_service.count_todos().await,
+    ) {
+        (Ok(users), Ok(todos)) => metrics_service.set_totals(users, todos),
+        // the other metrics are still useful, e.g. to see why the database is failing
+        (Err(e), _) => tracing::warn!(error = %e, "failed to count users for metrics"),
+        (_, Err(e)) => tracing::warn!(error = %e, "failed to count todos for metrics"),
+    }
+
+    match metrics_service.render() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(prometheus::TEXT_FORMAT)
+            .body(body),
+        Err(e) => {
+            tracing::error!(error = %e, "failed to render metrics");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Compares digests, so the time taken doesn't reveal how much of the token was right.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    Sha256::digest(given.as_bytes()) == Sha256::digest(expected.as_bytes())
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{
+        http::{header::AUTHORIZATION, StatusCode},
+        test, App,
+    };
+
+    use crate::{
+        middleware::http_metrics::HttpMetrics, pages::login::login_submit,
+        services::metrics_service::MetricsService,
+    };
+
+    use super::*;
+
+    const TOKEN: &str = "scraper-token";
+
+    fn app_state() -> AppState {
+        // the keys the in-memory app is configured with, they don't matter for metrics
+        std::env::set_var("JWT_SECRET", "metrics-test-jwt-secret");
+        std::env::set_var("HASH_SECRET", "metrics-test-hash-secret");
+        AppState {
+            metrics_token: Some(String::from(TOKEN)),
+            ..AppState::new_in_memory(Arc::new(MetricsService::new())).unwrap()
+        }
+    }
+
+    #[actix_web::test]
+    async fn metrics_require_the_token() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state()))
+                .service(metrics),
+        )
+        .await;
+
+        for (auth, status) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some("Bearer wrong-token"), StatusCode::UNAUTHORIZED),
+            (Some("Basic c2NyYXBlcjp0b2tlbg=="), StatusCode::UNAUTHORIZED),
+            (Some("Bearer scraper-token"), StatusCode::OK),
+        ] {
+            let mut req = test::TestRequest::get().uri("/metrics");
+            if let Some(auth) = auth {
+                req = req.insert_header((AUTHORIZATION, auth));
+            }
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), status, "{auth:?}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn metrics_include_requests_and_logins() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state()))
+                .wrap(HttpMetrics)
+                .service(metrics)
+                .service(login_submit),
+        )
+        .await;
+
+        let login = test::TestRequest::post()
+            .uri("/login")
+            .set_form([("username", "nobody"), ("password", "password")])
+            .to_request();
+        let res = test::call_service(&app, login).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let scrape = test::TestRequest::get()
+            .uri("/metrics")
+            .insert_header((AUTHORIZATION, format!("Bearer {TOKEN}")))
+            .to_request();
+        let body = test::call_and_read_body(&app, scrape).await;
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            body.contains(
+                r#"http_request_duration_seconds_count{method="POST",route="/login",status="200"} 1"#
+            ),
+            "{body}"
+        );
+        assert!(
+            body.contains(r#"auth_attempts_total{operation="login",outcome="unknown_user"} 1"#),
+            "{body}"
+        );
+        assert!(body.contains("\nusers 0\n"), "{body}");
+    }
+}
@@ -1,4 +1,15 @@
+pub mod admin;
+pub mod csp_reports;
+pub mod health;
 pub mod index;
+pub mod jwks;
+pub mod lists;
 pub mod login;
+pub mod metrics;
+pub mod nav;
+pub mod profile;
 pub mod register;
+pub mod sessions;
+pub mod tags;
+pub mod todo;
 pub mod todos;
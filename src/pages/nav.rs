@@ -0,0 +1,50 @@
+use crate::utils::authenticated_user::AuthenticatedUser;
+
+pub struct NavLink {
+    pub href: &'static str,
+    pub label: &'static str,
+    pub active: bool,
+}
+
+/// Data for the shared `nav.html` include used by the pages under `/home`.
+pub struct Nav {
+    pub username: String,
+    pub links: Vec<NavLink>,
+    /// where on the current page the user is, e.g. the todo list they have open
+    pub breadcrumb: Option<String>,
+}
+
+impl Nav {
+    pub fn new(user: &AuthenticatedUser, current_href: &str) -> Self {
+        let mut links = vec![
+            ("/home/todos", "My Todos"),
+            ("/home/tags", "Tags"),
+            ("/home/profile", "Profile"),
+            ("/home/sessions", "Sessions"),
+        ];
+        if user.is_admin() {
+            links.push(("/home/admin/audit", "Audit Log"));
+            links.push(("/home/admin/csp-reports", "CSP Reports"));
+        }
+
+        Self {
+            username: user.username.clone(),
+            links: links
+                .into_iter()
+                .map(|(href, label)| NavLink {
+                    href,
+                    label,
+                    active: href == current_href,
+                })
+                .collect(),
+            breadcrumb: None,
+        }
+    }
+
+    pub fn with_breadcrumb(self, breadcrumb: &str) -> Self {
+        Self {
+            breadcrumb: Some(breadcrumb.to_owned()),
+            ..self
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use actix_web::{get, web, Responder};
+use askama::Template;
+
+use crate::{
+    repositories::audit_repository::AuditEventEntity,
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        service_error_http_response::http_service_error_response,
+    },
+    AppState, TemplateToResponse,
+};
+
+use super::nav::Nav;
+
+const RECENT_SECURITY_EVENTS_LIMIT: i64 = 20;
+
+#[derive(Template)]
+#[template(path = "profile.html")]
+struct ProfileTemplate<'a> {
+    nav: Nav,
+    user: &'a AuthenticatedUser,
+    events: Vec<AuditEventEntity>,
+}
+
+#[get("/profile")]
+async fn profile_page(state: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    let events = match state
+        .audit_service
+        .list_user_events(&user.id, RECENT_SECURITY_EVENTS_LIMIT)
+        .await
+    {
+        Ok(events) => events,
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    };
+
+    ProfileTemplate {
+        nav: Nav::new(&user, "/home/profile"),
+        user: &user,
+        events,
+    }
+    .to_response()
+}
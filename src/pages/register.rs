@@ -2,7 +2,16 @@ use actix_web::{get, http::header::LOCATION, post, web, HttpResponse, Responder}
 use askama::Template;
 use serde::Deserialize;
 
-use crate::{services::auth_service::AuthServiceError, AppState, TemplateToResponse};
+use crate::{
+    repositories::user_repository::UserRole,
+    services::{audit_service::AuditContext, auth_service::AuthServiceError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        redirect_target::DEFAULT_SIGNED_IN_PATH,
+        service_error_http_response::{with_request_id, with_server_busy_status},
+    },
+    AppState, TemplateToResponse,
+};
 
 #[derive(Template, Default)]
 #[template(path = "register.html")]
@@ -18,7 +27,12 @@ pub fn show_register_page(error: Option<&str>) -> HttpResponse {
 }
 
 #[get("/register")]
-async fn register_page() -> impl Responder {
+async fn register_page(user: Option<AuthenticatedUser>) -> impl Responder {
+    if user.is_some() {
+        return HttpResponse::Found()
+            .append_header((LOCATION, DEFAULT_SIGNED_IN_PATH))
+            .body("");
+    }
     show_register_page(None)
 }
 
@@ -32,16 +46,23 @@ pub struct RegisterFormData {
 pub async fn register_submit(
     web::Form(form): web::Form<RegisterFormData>,
     state: web::Data<AppState>,
+    ctx: AuditContext,
 ) -> impl Responder {
     let res = state
         .auth_service
-        .register_user(&form.username, &form.password)
+        .register_user(&form.username, &form.password, UserRole::User, &ctx)
         .await;
     match res {
         Ok(()) => HttpResponse::Found()
             .append_header((LOCATION, "/login"))
             .body(""),
         Err(AuthServiceError::UserAlreadyExists) => show_register_page(Some("User already exists")),
-        Err(_) => show_register_page(Some("Unknown error")),
+        Err(AuthServiceError::ServerBusy) => with_server_busy_status(show_register_page(Some(
+            "The server is busy, please try again",
+        ))),
+        Err(e) => {
+            tracing::error!(error = %e, "failed to register");
+            show_register_page(Some(&with_request_id("Unknown error")))
+        }
     }
 }
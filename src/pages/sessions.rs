@@ -0,0 +1,64 @@
+use actix_web::{get, http::header::LOCATION, post, web, HttpResponse, Responder};
+use askama::Template;
+
+use crate::{
+    repositories::session_store::SessionEntity,
+    services::{audit_service::AuditContext, session_service::SessionServiceError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        service_error_http_response::http_service_error_response,
+    },
+    AppState, TemplateToResponse,
+};
+
+use super::nav::Nav;
+
+#[derive(Template)]
+#[template(path = "sessions.html")]
+struct SessionsTemplate {
+    nav: Nav,
+    /// `None` when the configured session mode can't list sessions
+    sessions: Option<Vec<SessionEntity>>,
+    current_session_id: Option<String>,
+}
+
+#[get("/sessions")]
+async fn sessions_page(state: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    let sessions = match state.session_service.list_sessions(&user.id).await {
+        Ok(sessions) => Some(sessions),
+        Err(SessionServiceError::Unsupported) => None,
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    };
+
+    SessionsTemplate {
+        nav: Nav::new(&user, "/home/sessions"),
+        sessions,
+        current_session_id: user.session_id.clone(),
+    }
+    .to_response()
+}
+
+#[post("/sessions/{id}/revoke")]
+pub async fn revoke_session_submit(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    ctx: AuditContext,
+) -> impl Responder {
+    let session_id = path.into_inner();
+
+    let res = state
+        .session_service
+        .revoke_session(&user.id, &session_id, &ctx)
+        .await;
+
+    match res {
+        // an already revoked session (e.g. a resubmitted form) is not an error for the user
+        Ok(()) | Err(SessionServiceError::SessionNotFound) => {}
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    }
+
+    HttpResponse::Found()
+        .append_header((LOCATION, "/home/sessions"))
+        .body("")
+}
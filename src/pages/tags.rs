@@ -0,0 +1,135 @@
+use actix_web::{get, post, web, HttpResponse, Responder};
+use askama::Template;
+use serde::Deserialize;
+
+use crate::{
+    repositories::tag_repository::TagEntity,
+    services::tag_service::TagServiceError,
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        service_error_http_response::http_service_error_response,
+    },
+    AppState, TemplateToResponse,
+};
+
+use super::{nav::Nav, todos::redirect_to};
+
+#[derive(Template)]
+#[template(path = "tags.html")]
+struct TagsTemplate {
+    nav: Nav,
+    tags: Vec<TagEntity>,
+    error: Option<String>,
+}
+
+impl TagsTemplate {
+    /// The inbox filtered by the tag
+    fn todos_href(&self, tag: &TagEntity) -> String {
+        let query =
+            serde_urlencoded::to_string([("tags", &tag.name)]).expect("the query only has strings");
+        format!("/home/todos?{query}")
+    }
+
+    /// Tags the tag can be merged into
+    fn other_tags(&self, tag: &TagEntity) -> Vec<&TagEntity> {
+        self.tags
+            .iter()
+            .filter(|other| other.id != tag.id)
+            .collect()
+    }
+}
+
+async fn tags_page_response(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    error: Option<String>,
+) -> HttpResponse {
+    let tags = match state.tag_service.list_tags(&user.id).await {
+        Ok(tags) => tags,
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    };
+
+    TagsTemplate {
+        nav: Nav::new(user, "/home/tags"),
+        tags,
+        error,
+    }
+    .to_response()
+}
+
+/// Shows invalid and taken names on the page, and treats tags deleted in another tab as done
+async fn tag_result_response(
+    res: Result<(), TagServiceError>,
+    state: &AppState,
+    user: &AuthenticatedUser,
+) -> HttpResponse {
+    match res {
+        Ok(()) | Err(TagServiceError::ItemNotFound) => redirect_to("/home/tags"),
+        Err(e @ (TagServiceError::InvalidName(_) | TagServiceError::NameTaken)) => {
+            tags_page_response(state, user, Some(e.to_string())).await
+        }
+        Err(e) => http_service_error_response(Some(e.to_string())),
+    }
+}
+
+#[get("/tags")]
+async fn tags_page(state: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    tags_page_response(&state, &user, None).await
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RenameTagFormData {
+    name: String,
+}
+
+#[post("/tags/{id}/rename")]
+pub async fn rename_tag_submit(
+    path: web::Path<String>,
+    web::Form(form): web::Form<RenameTagFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let tag_id = path.into_inner();
+
+    let res = state
+        .tag_service
+        .rename_tag(&user.id, &tag_id, &form.name)
+        .await;
+
+    tag_result_response(res, &state, &user).await
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MergeTagFormData {
+    target_id: String,
+}
+
+#[post("/tags/{id}/merge")]
+pub async fn merge_tag_submit(
+    path: web::Path<String>,
+    web::Form(form): web::Form<MergeTagFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let tag_id = path.into_inner();
+
+    let res = state
+        .tag_service
+        .merge_tags(&user.id, &tag_id, &form.target_id)
+        .await;
+
+    tag_result_response(res, &state, &user).await
+}
+
+#[post("/tags/{id}/delete")]
+pub async fn delete_tag_submit(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let tag_id = path.into_inner();
+
+    let res = state.tag_service.delete_tag(&user.id, &tag_id).await;
+
+    tag_result_response(res, &state, &user).await
+}
@@ -0,0 +1,209 @@
+use std::slice;
+
+use actix_web::{get, post, web, HttpResponse, Responder};
+use askama::Template;
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{
+    repositories::{
+        checklist_repository::ChecklistItemEntity,
+        todo_repository::{MoveDirection, TodoEntity},
+    },
+    services::{checklist_service::ChecklistServiceError, todo_service::TodoServiceError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        service_error_http_response::http_service_error_response,
+    },
+    AppState, TemplateToResponse,
+};
+
+use super::{nav::Nav, todos::redirect_to};
+
+#[derive(Template)]
+#[template(path = "todo.html")]
+struct TodoTemplate {
+    nav: Nav,
+    todo: TodoEntity,
+    items: Vec<ChecklistItemEntity>,
+    error: Option<String>,
+}
+
+impl TodoTemplate {
+    /// The todos page of the todo's list
+    fn back_href(&self) -> String {
+        match &self.todo.list_id {
+            Some(list_id) => format!("/home/todos?list={list_id}"),
+            None => "/home/todos".to_owned(),
+        }
+    }
+
+    fn is_overdue(&self) -> bool {
+        self.todo.is_overdue(Utc::now())
+    }
+}
+
+fn todo_href(todo_id: &str) -> String {
+    format!("/home/todos/{todo_id}")
+}
+
+/// Renders the page of the todo and its checklist, redirecting to the todos page if the todo
+/// doesn't exist, e.g. when it was deleted in another tab
+async fn todo_page_response(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    todo_id: &str,
+    error: Option<String>,
+) -> HttpResponse {
+    let todo = match state.todo_service.get_todo(&user.id, todo_id).await {
+        Ok(todo) => todo,
+        Err(TodoServiceError::ItemNotFound) => return redirect_to("/home/todos"),
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    };
+    let items = match state
+        .checklist_service
+        .list_items(&user.id, slice::from_ref(&todo.id))
+        .await
+    {
+        Ok(items) => items,
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    };
+
+    TodoTemplate {
+        nav: Nav::new(user, "/home/todos").with_breadcrumb(&todo.name),
+        todo,
+        items,
+        error,
+    }
+    .to_response()
+}
+
+/// Shows invalid item names on the page, and otherwise goes back to it, where a todo deleted in
+/// another tab redirects on to the todos page
+async fn checklist_result_response<T>(
+    res: Result<T, ChecklistServiceError>,
+    state: &AppState,
+    user: &AuthenticatedUser,
+    todo_id: &str,
+) -> HttpResponse {
+    match res {
+        Ok(_) | Err(ChecklistServiceError::ItemNotFound) => redirect_to(&todo_href(todo_id)),
+        Err(ChecklistServiceError::InvalidName(e)) => {
+            todo_page_response(state, user, todo_id, Some(e)).await
+        }
+        Err(e) => http_service_error_response(Some(e.to_string())),
+    }
+}
+
+#[get("/todos/{id}")]
+async fn todo_page(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    todo_page_response(&state, &user, &path.into_inner(), None).await
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChecklistItemFormData {
+    name: String,
+}
+
+#[post("/todos/{id}/items")]
+pub async fn add_checklist_item_submit(
+    path: web::Path<String>,
+    web::Form(form): web::Form<ChecklistItemFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let todo_id = path.into_inner();
+
+    let res = state
+        .checklist_service
+        .add_item(&user.id, &todo_id, &form.name)
+        .await;
+
+    checklist_result_response(res, &state, &user, &todo_id).await
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CompleteChecklistItemFormData {
+    complete: bool,
+}
+
+#[post("/todos/{id}/items/{item_id}/complete")]
+pub async fn complete_checklist_item_submit(
+    path: web::Path<(String, String)>,
+    web::Form(form): web::Form<CompleteChecklistItemFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let (todo_id, item_id) = path.into_inner();
+
+    let res = state
+        .checklist_service
+        .set_item_complete(&user.id, &todo_id, &item_id, form.complete)
+        .await;
+
+    checklist_result_response(res, &state, &user, &todo_id).await
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MoveChecklistItemFormData {
+    direction: MoveDirection,
+}
+
+#[post("/todos/{id}/items/{item_id}/move")]
+pub async fn move_checklist_item_submit(
+    path: web::Path<(String, String)>,
+    web::Form(form): web::Form<MoveChecklistItemFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let (todo_id, item_id) = path.into_inner();
+
+    let res = state
+        .checklist_service
+        .move_item(&user.id, &todo_id, &item_id, form.direction)
+        .await;
+
+    checklist_result_response(res, &state, &user, &todo_id).await
+}
+
+#[post("/todos/{id}/items/{item_id}/delete")]
+pub async fn delete_checklist_item_submit(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let (todo_id, item_id) = path.into_inner();
+
+    let res = state
+        .checklist_service
+        .remove_item(&user.id, &todo_id, &item_id)
+        .await;
+
+    checklist_result_response(res, &state, &user, &todo_id).await
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AutoCompleteFormData {
+    auto_complete: bool,
+}
+
+#[post("/todos/{id}/auto-complete")]
+pub async fn auto_complete_todo_submit(
+    path: web::Path<String>,
+    web::Form(form): web::Form<AutoCompleteFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let todo_id = path.into_inner();
+
+    let res = state
+        .checklist_service
+        .set_auto_complete(&user.id, &todo_id, form.auto_complete)
+        .await;
+
+    checklist_result_response(res, &state, &user, &todo_id).await
+}
@@ -1,51 +1,577 @@
-use actix_web::{
-    get, post,
-    web::{self, ReqData},
-    HttpResponse, Responder,
-};
+use actix_web::{get, http::header::LOCATION, post, web, HttpResponse, Responder};
 use askama::Template;
-use serde::Deserialize;
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    repositories::{todo_repository::TodoEntity, user_repository::UserEntity},
+    repositories::{
+        list_repository::ListEntity,
+        todo_repository::{
+            MoveDirection, TodoDetails, TodoEntity, TodoFilter, TodoPriority, TodoSort, TodoView,
+        },
+    },
+    services::{audit_service::AuditContext, todo_service::TodoServiceError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        recurrence::{parse_weekdays, Recurrence, RecurrenceFrequency},
+        service_error_http_response::http_service_error_response,
+        todo_search::TodoSearch,
+    },
     AppState, TemplateToResponse,
 };
 
-#[derive(Template, Default)]
+use super::nav::Nav;
+
+#[derive(Template)]
 #[template(path = "todos.html")]
-struct TodosTemplate<'a> {
+struct TodosTemplate {
     todos: Vec<TodoEntity>,
-    username: &'a str,
+    nav: Nav,
+    query: TodosQuery,
+    filter: TodoFilter,
+    /// all of the user's lists, archived ones included
+    lists: Vec<ListEntity>,
+    /// the list shown, `None` for the inbox
+    list: Option<ListEntity>,
+    /// set when `todos` are the results of a search instead of the list
+    search: Option<TodoSearch>,
+    views: &'static [TodoView],
+    sorts: &'static [TodoSort],
+    priorities: &'static [TodoPriority],
+    frequencies: &'static [RecurrenceFrequency],
+    error: Option<String>,
+}
+
+impl TodosTemplate {
+    fn href(&self, view: &TodoView, sort: &TodoSort) -> String {
+        TodosQuery {
+            view: *view,
+            sort: *sort,
+            ..self.query.clone()
+        }
+        .href()
+    }
+
+    fn list_href(&self, list_id: &str) -> String {
+        TodosQuery {
+            list: Some(list_id.to_owned()),
+            q: String::new(),
+            ..self.query.clone()
+        }
+        .href()
+    }
+
+    fn inbox_href(&self) -> String {
+        TodosQuery {
+            list: None,
+            q: String::new(),
+            ..self.query.clone()
+        }
+        .href()
+    }
+
+    /// Back to the list the search was made from
+    fn clear_search_href(&self) -> String {
+        TodosQuery {
+            q: String::new(),
+            ..self.query.clone()
+        }
+        .href()
+    }
+
+    /// The page filtered by the tag as well as the tags it's already filtered by, or the search
+    /// narrowed down to the tag
+    fn tag_href(&self, tag: &str) -> String {
+        if self.search.is_some() {
+            let filter = match tag.contains(char::is_whitespace) {
+                true => format!("tag:\"{tag}\""),
+                false => format!("tag:{tag}"),
+            };
+            return TodosQuery {
+                q: format!("{} {filter}", self.query.q.trim()),
+                ..self.query.clone()
+            }
+            .href();
+        }
+        let mut tags = self.filter.tags.clone();
+        if !tags.iter().any(|other| other == tag) {
+            tags.push(tag.to_owned());
+        }
+        self.query.with_tags(&tags).href()
+    }
+
+    fn without_tag_href(&self, tag: &str) -> String {
+        let tags: Vec<_> = self
+            .filter
+            .tags
+            .iter()
+            .filter(|other| *other != tag)
+            .cloned()
+            .collect();
+        self.query.with_tags(&tags).href()
+    }
+
+    /// Query string of the forms on the page, so they redirect back to the same list and view
+    fn form_query(&self) -> String {
+        self.query.query_string()
+    }
+
+    fn is_current_list(&self, list_id: &str) -> bool {
+        self.query.list_id() == Some(list_id)
+    }
+
+    fn title(&self) -> &str {
+        match (&self.search, &self.list) {
+            (Some(_), _) => "Search",
+            (None, Some(list)) => &list.name,
+            (None, None) => "Inbox",
+        }
+    }
+
+    /// Name of the list a search result is in
+    fn list_name(&self, todo: &TodoEntity) -> &str {
+        match &todo.list_id {
+            Some(list_id) => self
+                .lists
+                .iter()
+                .find(|list| &list.id == list_id)
+                .map_or("", |list| &list.name),
+            None => "Inbox",
+        }
+    }
+
+    fn todo_list_href(&self, todo: &TodoEntity) -> String {
+        match &todo.list_id {
+            Some(list_id) => self.list_href(list_id),
+            None => self.inbox_href(),
+        }
+    }
+
+    fn has_archived_lists(&self) -> bool {
+        self.lists.iter().any(ListEntity::is_archived)
+    }
+
+    /// Lists a todo on this page can be moved to
+    fn other_lists(&self) -> Vec<&ListEntity> {
+        self.lists
+            .iter()
+            .filter(|list| !list.is_archived() && !self.is_current_list(&list.id))
+            .collect()
+    }
+
+    fn is_overdue(&self, todo: &TodoEntity) -> bool {
+        todo.is_overdue(self.filter.now)
+    }
+}
+
+/// Renders the todos page of the query's list, redirecting to the inbox if the list doesn't
+/// exist, e.g. when it was deleted in another tab
+async fn todos_page_response(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    query: TodosQuery,
+    error: Option<String>,
+) -> HttpResponse {
+    let lists = match state.list_service.list_lists(&user.id).await {
+        Ok(lists) => lists,
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    };
+    let list = match query.list_id() {
+        Some(list_id) => match lists.iter().find(|list| list.id == list_id) {
+            Some(list) => Some(list.clone()),
+            None => return redirect_to("/home/todos"),
+        },
+        None => None,
+    };
+
+    let filter = query.filter();
+    // an invalid search shows its error over the list instead
+    let (search, error) =
+        match non_empty(&query.q).map(|q| TodoSearch::parse(q, filter.now.date_naive())) {
+            Some(Ok(search)) => (Some(search), error),
+            Some(Err(e)) => (None, Some(e)),
+            None => (None, error),
+        };
+    let todos = match &search {
+        Some(search) => state.todo_service.search_todos(&user.id, search).await,
+        None => state.todo_service.list_todos(&user.id, &filter).await,
+    };
+    let todos = match todos {
+        Ok(todos) => todos,
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    };
+
+    let nav = Nav::new(user, "/home/todos");
+    let nav = match (&search, &list) {
+        (Some(_), _) => nav.with_breadcrumb("Search"),
+        (None, Some(list)) => nav.with_breadcrumb(&list.name),
+        (None, None) => nav.with_breadcrumb("Inbox"),
+    };
+
+    TodosTemplate {
+        todos,
+        nav,
+        query,
+        filter,
+        lists,
+        list,
+        search,
+        views: &TodoView::ALL,
+        sorts: &TodoSort::ALL,
+        priorities: &TodoPriority::ALL,
+        frequencies: &RecurrenceFrequency::ALL,
+        error,
+    }
+    .to_response()
+}
+
+pub(super) fn redirect_to(location: &str) -> HttpResponse {
+    HttpResponse::Found()
+        .append_header((LOCATION, location))
+        .body("")
 }
 
-pub fn show_todos_page(username: &str, todos: Vec<TodoEntity>) -> HttpResponse {
-    TodosTemplate { todos, username }.to_response()
+/// The list and view of the todos page, also passed to its forms so they redirect back to it
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TodosQuery {
+    /// `None` for the inbox
+    list: Option<String>,
+    #[serde(default)]
+    view: TodoView,
+    #[serde(default)]
+    sort: TodoSort,
+    /// names of the tags to filter by, separated by commas
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    tags: String,
+    /// a search of all of the user's todos, shown instead of the list
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    q: String,
+}
+
+impl TodosQuery {
+    fn list_id(&self) -> Option<&str> {
+        self.list.as_deref().filter(|list| !list.is_empty())
+    }
+
+    fn filter(&self) -> TodoFilter {
+        TodoFilter {
+            list_id: self.list_id().map(str::to_owned),
+            view: self.view,
+            sort: self.sort,
+            tags: parse_tags(&self.tags),
+            ..Default::default()
+        }
+    }
+
+    fn with_tags(&self, tags: &[String]) -> TodosQuery {
+        TodosQuery {
+            tags: tags.join(","),
+            ..self.clone()
+        }
+    }
+
+    fn query_string(&self) -> String {
+        let query = TodosQuery {
+            list: self.list_id().map(str::to_owned),
+            ..self.clone()
+        };
+        serde_urlencoded::to_string(query).expect("the query only has strings")
+    }
+
+    fn href(&self) -> String {
+        format!("/home/todos?{}", self.query_string())
+    }
 }
 
 #[get("/todos")]
-async fn todos_page(state: web::Data<AppState>, req_user: ReqData<UserEntity>) -> impl Responder {
-    let user_todos = state.todo_service.list_todos(&req_user.id).await.unwrap();
-    show_todos_page(&req_user.username, user_todos)
+async fn todos_page(
+    web::Query(query): web::Query<TodosQuery>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    todos_page_response(&state, &user, query, None).await
+}
+
+/// Due date, time, priority and tags as submitted by the `<input type="date">`,
+/// `<input type="time">`, `<select>` and tags input of the todos page
+#[derive(Deserialize, Debug)]
+pub struct TodoDetailsFormData {
+    #[serde(default)]
+    due_date: String,
+    #[serde(default)]
+    due_time: String,
+    #[serde(default)]
+    priority: String,
+    /// separated by commas
+    #[serde(default)]
+    tags: String,
+    /// a [RecurrenceFrequency], empty for todos which don't repeat
+    #[serde(default)]
+    repeat: String,
+    #[serde(default)]
+    repeat_interval: String,
+    /// the weekdays of weekly repeats, separated by commas, or the day of monthly ones
+    #[serde(default)]
+    repeat_on: String,
+}
+
+impl TodoDetailsFormData {
+    /// Empty inputs clear the due date and time, and a time without a date is dropped
+    fn parse(&self) -> Result<TodoDetails, String> {
+        let due_date = match non_empty(&self.due_date) {
+            Some(date) => Some(
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid date: {date}"))?,
+            ),
+            None => None,
+        };
+        let due_time = match non_empty(&self.due_time).filter(|_| due_date.is_some()) {
+            Some(time) => Some(
+                NaiveTime::parse_from_str(time, "%H:%M")
+                    .map_err(|_| format!("Invalid time: {time}"))?,
+            ),
+            None => None,
+        };
+        let priority = match non_empty(&self.priority) {
+            Some(priority) => priority.parse()?,
+            None => TodoPriority::default(),
+        };
+        let recurrence = match non_empty(&self.repeat) {
+            Some(frequency) => Some(self.parse_recurrence(frequency.parse()?, due_date)?),
+            None => None,
+        };
+
+        Ok(TodoDetails {
+            due_date,
+            due_time,
+            priority,
+            recurrence,
+        })
+    }
+
+    /// Without weekdays or a day, it repeats on those of the due date, like an `RRULE` does
+    fn parse_recurrence(
+        &self,
+        frequency: RecurrenceFrequency,
+        due_date: Option<NaiveDate>,
+    ) -> Result<Recurrence, String> {
+        let interval = match non_empty(&self.repeat_interval) {
+            Some(interval) => interval
+                .parse()
+                .map_err(|_| format!("Invalid repeat interval: {interval}"))?,
+            None => 1,
+        };
+        let on = non_empty(&self.repeat_on);
+        let weekdays = match (frequency, on) {
+            (RecurrenceFrequency::Weekly, Some(on)) => parse_weekdays(on)?,
+            (RecurrenceFrequency::Weekly, None) => {
+                due_date.map(|d| d.weekday()).into_iter().collect()
+            }
+            _ => vec![],
+        };
+        let day = match (frequency, on) {
+            (RecurrenceFrequency::Monthly, Some(on)) => on
+                .parse()
+                .map_err(|_| format!("Invalid day of the month: {on}"))?,
+            (RecurrenceFrequency::Monthly, None) => due_date.map_or(0, |d| d.day()),
+            _ => 0,
+        };
+
+        Recurrence::new(frequency, interval, weekdays, day)
+    }
+}
+
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|v| !v.is_empty())
+}
+
+/// Tag names separated by commas, skipping empty ones so stray commas don't matter
+fn parse_tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in value.split(',').filter_map(non_empty) {
+        if !tags.iter().any(|other| other == tag) {
+            tags.push(tag.to_owned());
+        }
+    }
+    tags
 }
 
 #[derive(Deserialize, Debug)]
 pub struct CreateTodoFormData {
     name: String,
+    #[serde(flatten)]
+    details: TodoDetailsFormData,
 }
 
 #[post("/todos")]
 pub async fn create_todo_submit(
+    web::Query(query): web::Query<TodosQuery>,
     web::Form(form): web::Form<CreateTodoFormData>,
     state: web::Data<AppState>,
-    req_user: ReqData<UserEntity>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let details = match form.details.parse() {
+        Ok(details) => details,
+        Err(e) => return todos_page_response(&state, &user, query, Some(e)).await,
+    };
+
+    let tags = parse_tags(&form.details.tags);
+
+    let res = state
+        .todo_service
+        .add_todo(&user.id, query.list_id(), &form.name, &details, &tags)
+        .await;
+
+    match res {
+        // when the list was deleted in another tab, the page redirects to the inbox
+        Ok(_) | Err(TodoServiceError::ItemNotFound) => {
+            todos_page_response(&state, &user, query, None).await
+        }
+        Err(TodoServiceError::InvalidTag(e)) => {
+            todos_page_response(&state, &user, query, Some(e)).await
+        }
+        Err(e) => http_service_error_response(Some(e.to_string())),
+    }
+}
+
+#[post("/todos/{id}/details")]
+pub async fn update_todo_details_submit(
+    path: web::Path<String>,
+    web::Query(query): web::Query<TodosQuery>,
+    web::Form(form): web::Form<TodoDetailsFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let todo_id = path.into_inner();
+
+    let details = match form.parse() {
+        Ok(details) => details,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    let tags = parse_tags(&form.tags);
+
+    let res = state
+        .todo_service
+        .update_todo_details(&user.id, &todo_id, &details, &tags)
+        .await;
+
+    match res {
+        Ok(()) | Err(TodoServiceError::ItemNotFound) => {}
+        Err(TodoServiceError::InvalidTag(e)) => return HttpResponse::BadRequest().body(e),
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    }
+
+    redirect_to(&query.href())
+}
+
+#[post("/todos/{id}/delete")]
+pub async fn delete_todo_submit(
+    path: web::Path<String>,
+    web::Query(query): web::Query<TodosQuery>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+    ctx: AuditContext,
+) -> impl Responder {
+    let todo_id = path.into_inner();
+
+    let res = state
+        .todo_service
+        .remove_todo(&user.id, &todo_id, &ctx)
+        .await;
+
+    match res {
+        // an already deleted todo (e.g. a resubmitted form) is not an error for the user
+        Ok(()) | Err(TodoServiceError::ItemNotFound) => {}
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    }
+
+    redirect_to(&query.href())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CompleteTodoFormData {
+    complete: bool,
+}
+
+#[post("/todos/{id}/complete")]
+pub async fn complete_todo_submit(
+    path: web::Path<String>,
+    web::Query(query): web::Query<TodosQuery>,
+    web::Form(form): web::Form<CompleteTodoFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let todo_id = path.into_inner();
+
+    let res = state
+        .todo_service
+        .set_todo_complete(&user.id, &todo_id, form.complete)
+        .await;
+
+    match res {
+        // the todo may have been deleted from another tab
+        Ok(()) | Err(TodoServiceError::ItemNotFound) => {}
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    }
+
+    redirect_to(&query.href())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MoveTodoFormData {
+    direction: MoveDirection,
+}
+
+#[post("/todos/{id}/move")]
+pub async fn move_todo_submit(
+    path: web::Path<String>,
+    web::Query(query): web::Query<TodosQuery>,
+    web::Form(form): web::Form<MoveTodoFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
 ) -> impl Responder {
-    state
+    let todo_id = path.into_inner();
+
+    let res = state
+        .todo_service
+        .move_todo(&user.id, &todo_id, form.direction)
+        .await;
+
+    match res {
+        Ok(()) | Err(TodoServiceError::ItemNotFound) => {}
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    }
+
+    redirect_to(&query.href())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MoveTodoToListFormData {
+    /// empty for the inbox
+    list_id: String,
+}
+
+#[post("/todos/{id}/list")]
+pub async fn move_todo_to_list_submit(
+    path: web::Path<String>,
+    web::Query(query): web::Query<TodosQuery>,
+    web::Form(form): web::Form<MoveTodoToListFormData>,
+    state: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let todo_id = path.into_inner();
+
+    let res = state
         .todo_service
-        .add_todo(&req_user.id, &form.name)
-        .await
-        .unwrap();
+        .move_todo_to_list(&user.id, &todo_id, non_empty(&form.list_id))
+        .await;
 
-    let todos = state.todo_service.list_todos(&req_user.id).await.unwrap();
+    match res {
+        // either the todo or the list is gone
+        Ok(()) | Err(TodoServiceError::ItemNotFound) => {}
+        Err(e) => return http_service_error_response(Some(e.to_string())),
+    }
 
-    show_todos_page(&req_user.username, todos)
+    redirect_to(&query.href())
 }
@@ -0,0 +1,117 @@
+use std::{fmt, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::RepositoryResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventType {
+    Login,
+    LoginFailed,
+    Registration,
+    PasswordChanged,
+    TodoDeleted,
+    ListDeleted,
+    RoleChanged,
+    UserDeleted,
+    SessionRevoked,
+}
+
+impl AuditEventType {
+    pub const ALL: [AuditEventType; 9] = [
+        AuditEventType::Login,
+        AuditEventType::LoginFailed,
+        AuditEventType::Registration,
+        AuditEventType::PasswordChanged,
+        AuditEventType::TodoDeleted,
+        AuditEventType::ListDeleted,
+        AuditEventType::RoleChanged,
+        AuditEventType::UserDeleted,
+        AuditEventType::SessionRevoked,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEventType::Login => "login",
+            AuditEventType::LoginFailed => "login_failed",
+            AuditEventType::Registration => "registration",
+            AuditEventType::PasswordChanged => "password_changed",
+            AuditEventType::TodoDeleted => "todo_deleted",
+            AuditEventType::ListDeleted => "list_deleted",
+            AuditEventType::RoleChanged => "role_changed",
+            AuditEventType::UserDeleted => "user_deleted",
+            AuditEventType::SessionRevoked => "session_revoked",
+        }
+    }
+}
+
+impl fmt::Display for AuditEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AuditEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuditEventType::ALL
+            .into_iter()
+            .find(|event_type| event_type.as_str() == s)
+            .ok_or_else(|| format!("unknown audit event type: {s}"))
+    }
+}
+
+/// An event to be appended to the audit log. The id and timestamp are assigned by the repository.
+#[derive(Debug, Clone)]
+pub struct NewAuditEvent {
+    pub actor_id: Option<String>,
+    pub event_type: AuditEventType,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditEventEntity {
+    pub id: String,
+    pub actor_id: Option<String>,
+    pub event_type: AuditEventType,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub payload: serde_json::Value,
+}
+
+/// Filter for listing audit events. `None` fields match everything.
+#[derive(Debug, Clone)]
+pub struct AuditEventFilter {
+    pub actor_id: Option<String>,
+    pub event_type: Option<AuditEventType>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: i64,
+}
+
+impl Default for AuditEventFilter {
+    fn default() -> Self {
+        Self {
+            actor_id: None,
+            event_type: None,
+            since: None,
+            until: None,
+            limit: 100,
+        }
+    }
+}
+
+/// Append-only store of security-relevant events. Events are returned newest first.
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    async fn append_event(&self, event: NewAuditEvent) -> RepositoryResult<String>;
+    async fn list_events(
+        &self,
+        filter: &AuditEventFilter,
+    ) -> RepositoryResult<Vec<AuditEventEntity>>;
+}
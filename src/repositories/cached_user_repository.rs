@@ -0,0 +1,377 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use lru::LruCache;
+use tracing::instrument;
+
+use super::{
+    user_repository::{UserEntity, UserRepository, UserRole},
+    RepositoryResult,
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// Caches [`UserRepository::get_user_by_id`] lookups of any backend in a bounded LRU,
+/// with entries expiring after `ttl`.
+///
+/// Writes made through this repository invalidate the user's entry. The TTL bounds how
+/// long changes made elsewhere (e.g. by another instance) can go unnoticed.
+pub struct CachedUserRepository {
+    inner: Arc<dyn UserRepository>,
+    /// never held across an await, so the stats can be read without one
+    users_by_id: Mutex<LruCache<String, CachedUser>>,
+    ttl: Duration,
+    /// bumped on every invalidation, so lookups racing with a write don't cache stale users
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct CachedUser {
+    user: UserEntity,
+    cached_at: Instant,
+}
+
+impl CachedUserRepository {
+    pub fn new(inner: Arc<dyn UserRepository>, capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self {
+            inner,
+            users_by_id: Mutex::new(LruCache::new(capacity)),
+            ttl,
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> UserCacheStats {
+        UserCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.lock_users().len(),
+        }
+    }
+
+    pub async fn invalidate(&self, id: &str) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.lock_users().pop(id);
+    }
+
+    fn lock_users(&self) -> MutexGuard<'_, LruCache<String, CachedUser>> {
+        self.users_by_id.lock().expect("user cache lock poisoned")
+    }
+
+    fn get_cached(&self, id: &str) -> Option<UserEntity> {
+        let mut users = self.lock_users();
+        match users.get(id) {
+            Some(cached) if cached.cached_at.elapsed() < self.ttl => Some(cached.user.clone()),
+            Some(_) => {
+                users.pop(id);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+#[async_trait]
+impl UserRepository for CachedUserRepository {
+    #[instrument(skip_all)]
+    async fn get_user_by_id(&self, id: &str) -> RepositoryResult<Option<UserEntity>> {
+        if let Some(user) = self.get_cached(id) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(user));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let user = self.inner.get_user_by_id(id).await?;
+
+        if let Some(user) = &user {
+            let mut users = self.lock_users();
+            if self.generation.load(Ordering::SeqCst) == generation {
+                users.put(
+                    id.to_owned(),
+                    CachedUser {
+                        user: user.clone(),
+                        cached_at: Instant::now(),
+                    },
+                );
+            }
+        }
+
+        Ok(user)
+    }
+
+    #[instrument(skip_all, fields(username = %username))]
+    async fn get_user_by_username(&self, username: &str) -> RepositoryResult<Option<UserEntity>> {
+        // only used when logging in, where the password hash must be current
+        self.inner.get_user_by_username(username).await
+    }
+
+    #[instrument(skip_all)]
+    async fn list_users(&self) -> RepositoryResult<Vec<UserEntity>> {
+        self.inner.list_users().await
+    }
+
+    #[instrument(skip_all, fields(username = %username))]
+    async fn create_user(&self, username: &str, pw_hash: &str) -> RepositoryResult<String> {
+        self.inner.create_user(username, pw_hash).await
+    }
+
+    #[instrument(skip_all)]
+    async fn update_password_hash(&self, id: &str, pw_hash: &str) -> RepositoryResult<()> {
+        let res = self.inner.update_password_hash(id, pw_hash).await;
+        self.invalidate(id).await;
+        res
+    }
+
+    #[instrument(skip_all)]
+    async fn update_user_role(&self, id: &str, role: UserRole) -> RepositoryResult<()> {
+        let res = self.inner.update_user_role(id, role).await;
+        self.invalidate(id).await;
+        res
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_user(&self, id: &str) -> RepositoryResult<()> {
+        let res = self.inner.delete_user(id).await;
+        self.invalidate(id).await;
+        res
+    }
+
+    #[instrument(skip_all)]
+    async fn count_users(&self) -> RepositoryResult<i64> {
+        self.inner.count_users().await
+    }
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use tokio::sync::Notify;
+
+    use crate::repositories::in_memory_user_repository::InMemoryUserRepository;
+
+    use super::*;
+
+    /// Counts the lookups which reach the backend, and can hold them up once the user is read
+    /// to race them with writes
+    #[derive(Default)]
+    struct CountingUserRepository {
+        users: InMemoryUserRepository,
+        lookups: AtomicU64,
+        hold_lookups: AtomicBool,
+        looked_up: Notify,
+        release: Notify,
+    }
+
+    #[async_trait]
+    impl UserRepository for CountingUserRepository {
+        async fn get_user_by_id(&self, id: &str) -> RepositoryResult<Option<UserEntity>> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            let user = self.users.get_user_by_id(id).await;
+            if self.hold_lookups.load(Ordering::SeqCst) {
+                self.looked_up.notify_one();
+                self.release.notified().await;
+            }
+            user
+        }
+
+        async fn get_user_by_username(
+            &self,
+            username: &str,
+        ) -> RepositoryResult<Option<UserEntity>> {
+            self.users.get_user_by_username(username).await
+        }
+
+        async fn list_users(&self) -> RepositoryResult<Vec<UserEntity>> {
+            self.users.list_users().await
+        }
+
+        async fn create_user(&self, username: &str, pw_hash: &str) -> RepositoryResult<String> {
+            self.users.create_user(username, pw_hash).await
+        }
+
+        async fn update_password_hash(&self, id: &str, pw_hash: &str) -> RepositoryResult<()> {
+            self.users.update_password_hash(id, pw_hash).await
+        }
+
+        async fn update_user_role(&self, id: &str, role: UserRole) -> RepositoryResult<()> {
+            self.users.update_user_role(id, role).await
+        }
+
+        async fn delete_user(&self, id: &str) -> RepositoryResult<()> {
+            self.users.delete_user(id).await
+        }
+
+        async fn count_users(&self) -> RepositoryResult<i64> {
+            self.users.count_users().await
+        }
+    }
+
+    fn cached(
+        capacity: usize,
+        ttl: Duration,
+    ) -> (Arc<CountingUserRepository>, Arc<CachedUserRepository>) {
+        let inner = Arc::new(CountingUserRepository::default());
+        let cache = Arc::new(CachedUserRepository::new(
+            Arc::clone(&inner) as Arc<dyn UserRepository>,
+            NonZeroUsize::new(capacity).unwrap(),
+            ttl,
+        ));
+        (inner, cache)
+    }
+
+    /// The fields of the user which can change
+    async fn lookup(repo: &dyn UserRepository, id: &str) -> Option<(String, UserRole)> {
+        let user = repo.get_user_by_id(id).await.unwrap();
+        user.map(|user| (user.pw_hash, user.role))
+    }
+
+    fn hits_and_misses(cache: &CachedUserRepository) -> (u64, u64, usize) {
+        let stats = cache.stats();
+        (stats.hits, stats.misses, stats.entries)
+    }
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn counts_hits_and_misses() {
+        let (inner, cache) = cached(10, TTL);
+        let id = cache.create_user("gina", "hash").await.unwrap();
+
+        let user = lookup(cache.as_ref(), &id).await;
+        assert_eq!(user, Some(("hash".to_owned(), UserRole::User)));
+        assert_eq!(lookup(cache.as_ref(), &id).await, user);
+        assert_eq!(lookup(cache.as_ref(), &id).await, user);
+        // unknown users aren't cached
+        assert_eq!(lookup(cache.as_ref(), "nope").await, None);
+        assert_eq!(lookup(cache.as_ref(), "nope").await, None);
+
+        assert_eq!(hits_and_misses(&cache), (2, 3, 1));
+        assert_eq!(inner.lookups.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn entries_expire_after_the_ttl() {
+        let (inner, cache) = cached(10, Duration::from_millis(200));
+        let id = cache.create_user("gina", "hash").await.unwrap();
+
+        lookup(cache.as_ref(), &id).await;
+        lookup(cache.as_ref(), &id).await;
+        assert_eq!(hits_and_misses(&cache), (1, 1, 1));
+
+        // a change made elsewhere, e.g. by another instance, is noticed once the entry expires
+        inner
+            .users
+            .update_user_role(&id, UserRole::Admin)
+            .await
+            .unwrap();
+        assert_eq!(
+            lookup(cache.as_ref(), &id).await,
+            Some(("hash".to_owned(), UserRole::User))
+        );
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(
+            lookup(cache.as_ref(), &id).await,
+            Some(("hash".to_owned(), UserRole::Admin))
+        );
+        assert_eq!(hits_and_misses(&cache), (2, 2, 1));
+    }
+
+    #[tokio::test]
+    async fn evicts_the_least_recently_used_user() {
+        let (inner, cache) = cached(2, TTL);
+        let mut ids = vec![];
+        for username in ["a", "b", "c"] {
+            ids.push(cache.create_user(username, "hash").await.unwrap());
+        }
+        let (a, b, c) = (&ids[0], &ids[1], &ids[2]);
+
+        lookup(cache.as_ref(), a).await;
+        lookup(cache.as_ref(), b).await;
+        // makes b the least recently used
+        lookup(cache.as_ref(), a).await;
+        lookup(cache.as_ref(), c).await;
+        assert_eq!(hits_and_misses(&cache), (1, 3, 2));
+
+        lookup(cache.as_ref(), a).await;
+        lookup(cache.as_ref(), c).await;
+        assert_eq!(hits_and_misses(&cache), (3, 3, 2));
+        lookup(cache.as_ref(), b).await;
+        assert_eq!(hits_and_misses(&cache), (3, 4, 2));
+        assert_eq!(inner.lookups.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn writes_invalidate_the_user() {
+        let (inner, cache) = cached(10, TTL);
+        let id = cache.create_user("gina", "hash").await.unwrap();
+
+        lookup(cache.as_ref(), &id).await;
+        cache.update_password_hash(&id, "new-hash").await.unwrap();
+        assert_eq!(hits_and_misses(&cache).2, 0);
+        assert_eq!(
+            lookup(cache.as_ref(), &id).await,
+            lookup(inner.as_ref(), &id).await
+        );
+        assert_eq!(
+            lookup(cache.as_ref(), &id).await,
+            Some(("new-hash".to_owned(), UserRole::User))
+        );
+
+        cache.update_user_role(&id, UserRole::Admin).await.unwrap();
+        assert_eq!(hits_and_misses(&cache).2, 0);
+        assert_eq!(
+            lookup(cache.as_ref(), &id).await,
+            Some(("new-hash".to_owned(), UserRole::Admin))
+        );
+
+        cache.delete_user(&id).await.unwrap();
+        assert_eq!(hits_and_misses(&cache).2, 0);
+        assert_eq!(lookup(cache.as_ref(), &id).await, None);
+        assert_eq!(lookup(inner.as_ref(), &id).await, None);
+    }
+
+    #[tokio::test]
+    async fn lookups_racing_with_an_invalidation_are_not_cached() {
+        let (inner, cache) = cached(10, TTL);
+        let id = cache.create_user("gina", "hash").await.unwrap();
+
+        inner.hold_lookups.store(true, Ordering::SeqCst);
+        let lookup_task = tokio::spawn({
+            let cache = Arc::clone(&cache);
+            let id = id.clone();
+            async move { lookup(cache.as_ref(), &id).await }
+        });
+        // the lookup has read the user, and the role changes before it's cached
+        inner.looked_up.notified().await;
+        cache.update_user_role(&id, UserRole::Admin).await.unwrap();
+        inner.release.notify_one();
+        assert_eq!(
+            lookup_task.await.unwrap(),
+            Some(("hash".to_owned(), UserRole::User))
+        );
+        inner.hold_lookups.store(false, Ordering::SeqCst);
+
+        assert_eq!(hits_and_misses(&cache).2, 0);
+        assert_eq!(
+            lookup(cache.as_ref(), &id).await,
+            Some(("hash".to_owned(), UserRole::Admin))
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use async_trait::async_trait;
+
+use super::{todo_repository::MoveDirection, RepositoryResult};
+
+/// A step of a todo, ticked off on the todo's page
+#[derive(Debug, Clone)]
+pub struct ChecklistItemEntity {
+    pub id: String,
+    pub todo_id: String,
+    pub name: String,
+    pub is_complete: bool,
+    /// where the item is in the todo's checklist, new items go last
+    pub position: i64,
+}
+
+/// The items of a checklist are only changed through their todo, so every method takes the todo
+/// along with the user it belongs to, and fails with `ItemNotFound` when they don't match.
+#[async_trait]
+pub trait ChecklistRepository: Send + Sync {
+    /// Adds the item to the end of the todo's checklist
+    async fn add_item(&self, user_id: &str, todo_id: &str, name: &str) -> RepositoryResult<String>;
+    /// Items of the todos in one go, by todo and then position
+    async fn list_items(
+        &self,
+        user_id: &str,
+        todo_ids: &[String],
+    ) -> RepositoryResult<Vec<ChecklistItemEntity>>;
+    async fn set_item_complete(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> RepositoryResult<()>;
+    /// Swaps the positions of the item and its neighbour, doing nothing at either end
+    async fn move_item(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> RepositoryResult<()>;
+    async fn remove_item(&self, user_id: &str, todo_id: &str, id: &str) -> RepositoryResult<()>;
+}
@@ -0,0 +1,71 @@
+use std::{collections::HashMap, fmt};
+
+use async_trait::async_trait;
+use sqlx::migrate::{AppliedMigration, Migrator};
+
+use super::RepositoryResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// applied, but the migration file has changed since
+    Changed,
+}
+
+impl MigrationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Changed => "changed",
+        }
+    }
+}
+
+impl fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A migration of this build and whether the database has it.
+#[derive(Debug, Clone)]
+pub struct MigrationEntity {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+#[async_trait]
+pub trait HealthRepository: Send + Sync {
+    /// Checks the database can be reached and answers queries
+    async fn ping(&self) -> RepositoryResult<()>;
+    /// The migrations of this build, oldest first
+    async fn list_migrations(&self) -> RepositoryResult<Vec<MigrationEntity>>;
+}
+
+/// Compares the migrations of `migrator` with those applied to the database, by checksum.
+pub fn migration_states(
+    migrator: &Migrator,
+    applied: Vec<AppliedMigration>,
+) -> Vec<MigrationEntity> {
+    let applied: HashMap<_, _> = applied
+        .into_iter()
+        .map(|migration| (migration.version, migration.checksum))
+        .collect();
+
+    migrator
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| MigrationEntity {
+            version: migration.version,
+            description: migration.description.to_string(),
+            state: match applied.get(&migration.version) {
+                None => MigrationState::Pending,
+                Some(checksum) if *checksum == migration.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::Changed,
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,99 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::utils::random_id;
+
+use super::{
+    audit_repository::{AuditEventEntity, AuditEventFilter, AuditRepository, NewAuditEvent},
+    RepositoryResult,
+};
+
+pub struct InMemoryAuditRepository {
+    events: Mutex<Vec<AuditEventEntity>>,
+}
+
+impl InMemoryAuditRepository {
+    pub fn new() -> Self {
+        Self {
+            events: Default::default(),
+        }
+    }
+
+    /// Removes the events, e.g. those appended in a transaction which is rolled back
+    pub fn remove_events(&self, ids: &HashSet<String>) {
+        self.events
+            .lock()
+            .unwrap()
+            .retain(|event| !ids.contains(&event.id));
+    }
+}
+
+impl Default for InMemoryAuditRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl AuditRepository for InMemoryAuditRepository {
+    async fn append_event(&self, event: NewAuditEvent) -> RepositoryResult<String> {
+        let id = random_id();
+
+        let entity = AuditEventEntity {
+            id: id.clone(),
+            actor_id: event.actor_id,
+            event_type: event.event_type,
+            ip: event.ip,
+            user_agent: event.user_agent,
+            created_at: Utc::now(),
+            payload: event.payload,
+        };
+
+        let mut events = self.events.lock().unwrap();
+        events.push(entity);
+
+        Ok(id)
+    }
+
+    async fn list_events(
+        &self,
+        filter: &AuditEventFilter,
+    ) -> RepositoryResult<Vec<AuditEventEntity>> {
+        let events = self.events.lock().unwrap();
+
+        // events are appended in chronological order, so iterate in reverse for newest first
+        let matching = events
+            .iter()
+            .rev()
+            .filter(|e| matches_filter(e, filter))
+            .take(filter.limit.max(0) as usize)
+            .cloned()
+            .collect();
+
+        Ok(matching)
+    }
+}
+
+fn matches_filter(event: &AuditEventEntity, filter: &AuditEventFilter) -> bool {
+    if filter.actor_id.is_some() && event.actor_id != filter.actor_id {
+        return false;
+    }
+    if let Some(event_type) = filter.event_type {
+        if event.event_type != event_type {
+            return false;
+        }
+    }
+    if let Some(since) = filter.since {
+        if event.created_at < since {
+            return false;
+        }
+    }
+    if let Some(until) = filter.until {
+        if event.created_at >= until {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+use crate::utils::random_id;
+
+use super::{
+    checklist_repository::{ChecklistItemEntity, ChecklistRepository},
+    in_memory_todo_repository::InMemoryTodoRepository,
+    in_memory_unit_of_work::TransactionLock,
+    todo_repository::{ChecklistProgress, MoveDirection},
+    RepositoryError, RepositoryResult,
+};
+
+/// The checklist items of each user, by ID. The progress of each todo's checklist is copied to
+/// the todo whenever it changes, so the todos are listed with it.
+pub struct InMemoryChecklistRepository {
+    items_by_user: Arc<Mutex<HashMap<String, HashMap<String, ChecklistItemEntity>>>>,
+    todos: Arc<InMemoryTodoRepository>,
+    transaction_lock: TransactionLock,
+}
+
+impl InMemoryChecklistRepository {
+    pub fn new(todos: Arc<InMemoryTodoRepository>, transaction_lock: TransactionLock) -> Self {
+        Self {
+            items_by_user: Default::default(),
+            todos,
+            transaction_lock,
+        }
+    }
+
+    /// Shares the data, for the transaction holding the lock to write through
+    pub fn in_transaction(&self) -> Self {
+        Self {
+            items_by_user: Arc::clone(&self.items_by_user),
+            todos: Arc::clone(&self.todos),
+            transaction_lock: self.transaction_lock.held(),
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, HashMap<String, ChecklistItemEntity>> {
+        self.items_by_user.lock().unwrap().clone()
+    }
+
+    pub fn restore(&self, snapshot: HashMap<String, HashMap<String, ChecklistItemEntity>>) {
+        *self.items_by_user.lock().unwrap() = snapshot;
+    }
+
+    /// The user's items, as long as they have the todo
+    fn user_items<'a>(
+        &self,
+        items_by_user: &'a mut HashMap<String, HashMap<String, ChecklistItemEntity>>,
+        user_id: &str,
+        todo_id: &str,
+    ) -> RepositoryResult<&'a mut HashMap<String, ChecklistItemEntity>> {
+        if !self.todos.contains(user_id, todo_id) {
+            return Err(RepositoryError::ItemNotFound);
+        }
+        Ok(items_by_user.entry(user_id.to_owned()).or_default())
+    }
+
+    fn update_progress(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        items: &HashMap<String, ChecklistItemEntity>,
+    ) {
+        let checklist = items.values().filter(|item| item.todo_id == todo_id).fold(
+            ChecklistProgress::default(),
+            |progress, item| ChecklistProgress {
+                done: progress.done + i64::from(item.is_complete),
+                total: progress.total + 1,
+            },
+        );
+        self.todos
+            .set_checklist_progress(user_id, todo_id, checklist);
+    }
+}
+
+/// The item, if it's on the todo's checklist
+fn find_item<'a>(
+    items: &'a mut HashMap<String, ChecklistItemEntity>,
+    todo_id: &str,
+    id: &str,
+) -> RepositoryResult<&'a mut ChecklistItemEntity> {
+    items
+        .get_mut(id)
+        .filter(|item| item.todo_id == todo_id)
+        .ok_or(RepositoryError::ItemNotFound)
+}
+
+#[async_trait]
+impl ChecklistRepository for InMemoryChecklistRepository {
+    async fn add_item(&self, user_id: &str, todo_id: &str, name: &str) -> RepositoryResult<String> {
+        let _lock = self.transaction_lock.write().await;
+        let mut items_by_user = self.items_by_user.lock().unwrap();
+        let items = self.user_items(&mut items_by_user, user_id, todo_id)?;
+
+        let id = random_id();
+        let position = items
+            .values()
+            .filter(|item| item.todo_id == todo_id)
+            .map(|item| item.position)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        items.insert(
+            id.clone(),
+            ChecklistItemEntity {
+                id: id.clone(),
+                todo_id: todo_id.to_owned(),
+                name: name.to_owned(),
+                is_complete: false,
+                position,
+            },
+        );
+        self.update_progress(user_id, todo_id, items);
+
+        Ok(id)
+    }
+
+    async fn list_items(
+        &self,
+        user_id: &str,
+        todo_ids: &[String],
+    ) -> RepositoryResult<Vec<ChecklistItemEntity>> {
+        let items_by_user = self.items_by_user.lock().unwrap();
+        let mut items: Vec<_> = items_by_user
+            .get(user_id)
+            .into_iter()
+            .flat_map(HashMap::values)
+            // items of deleted todos are left behind, but not listed
+            .filter(|item| todo_ids.contains(&item.todo_id))
+            .filter(|item| self.todos.contains(user_id, &item.todo_id))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| (&a.todo_id, a.position).cmp(&(&b.todo_id, b.position)));
+
+        Ok(items)
+    }
+
+    async fn set_item_complete(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut items_by_user = self.items_by_user.lock().unwrap();
+        let items = self.user_items(&mut items_by_user, user_id, todo_id)?;
+
+        find_item(items, todo_id, id)?.is_complete = complete;
+        self.update_progress(user_id, todo_id, items);
+
+        Ok(())
+    }
+
+    async fn move_item(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut items_by_user = self.items_by_user.lock().unwrap();
+        let items = self.user_items(&mut items_by_user, user_id, todo_id)?;
+
+        let position = find_item(items, todo_id, id)?.position;
+        let neighbour = items
+            .values()
+            .filter(|item| item.todo_id == todo_id)
+            .filter(|item| match direction {
+                MoveDirection::Up => item.position < position,
+                MoveDirection::Down => item.position > position,
+            })
+            .min_by_key(|item| match direction {
+                MoveDirection::Up => -item.position,
+                MoveDirection::Down => item.position,
+            })
+            .map(|item| (item.id.clone(), item.position));
+
+        if let Some((neighbour_id, neighbour_position)) = neighbour {
+            if let Some(item) = items.get_mut(&neighbour_id) {
+                item.position = position;
+            }
+            if let Some(item) = items.get_mut(id) {
+                item.position = neighbour_position;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn remove_item(&self, user_id: &str, todo_id: &str, id: &str) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut items_by_user = self.items_by_user.lock().unwrap();
+        let items = self.user_items(&mut items_by_user, user_id, todo_id)?;
+
+        find_item(items, todo_id, id)?;
+        items.remove(id);
+        self.update_progress(user_id, todo_id, items);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use super::{
+    health_repository::{HealthRepository, MigrationEntity},
+    RepositoryResult,
+};
+
+/// Always healthy, there is no database to check.
+pub struct InMemoryHealthRepository;
+
+#[async_trait]
+impl HealthRepository for InMemoryHealthRepository {
+    async fn ping(&self) -> RepositoryResult<()> {
+        Ok(())
+    }
+
+    async fn list_migrations(&self) -> RepositoryResult<Vec<MigrationEntity>> {
+        Ok(vec![])
+    }
+}
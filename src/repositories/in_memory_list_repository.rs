@@ -0,0 +1,147 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::utils::random_id;
+
+use super::{
+    in_memory_unit_of_work::TransactionLock,
+    list_repository::{ListEntity, ListRepository},
+    RepositoryError, RepositoryResult,
+};
+
+pub struct InMemoryListRepository {
+    lists_by_user: Arc<Mutex<HashMap<String, HashMap<String, ListEntity>>>>,
+    transaction_lock: TransactionLock,
+}
+
+impl InMemoryListRepository {
+    pub fn new(transaction_lock: TransactionLock) -> Self {
+        Self {
+            lists_by_user: Default::default(),
+            transaction_lock,
+        }
+    }
+
+    /// Shares the data, for the transaction holding the lock to write through
+    pub fn in_transaction(&self) -> Self {
+        Self {
+            lists_by_user: Arc::clone(&self.lists_by_user),
+            transaction_lock: self.transaction_lock.held(),
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, HashMap<String, ListEntity>> {
+        self.lists_by_user.lock().unwrap().clone()
+    }
+
+    pub fn restore(&self, snapshot: HashMap<String, HashMap<String, ListEntity>>) {
+        *self.lists_by_user.lock().unwrap() = snapshot;
+    }
+
+    pub fn archived_list_ids(&self, user_id: &str) -> HashSet<String> {
+        let lists_by_user = self.lists_by_user.lock().unwrap();
+        lists_by_user
+            .get(user_id)
+            .into_iter()
+            .flat_map(HashMap::values)
+            .filter(|list| list.is_archived())
+            .map(|list| list.id.clone())
+            .collect()
+    }
+}
+
+impl Default for InMemoryListRepository {
+    fn default() -> Self {
+        Self::new(TransactionLock::default())
+    }
+}
+
+#[async_trait]
+impl ListRepository for InMemoryListRepository {
+    async fn create_list(&self, user_id: &str, name: &str) -> RepositoryResult<String> {
+        let _lock = self.transaction_lock.write().await;
+        let mut lists_by_user = self.lists_by_user.lock().unwrap();
+
+        let id = random_id();
+        let new_list = ListEntity {
+            id: id.clone(),
+            name: name.to_owned(),
+            created_at: Utc::now(),
+            archived_at: None,
+        };
+        lists_by_user
+            .entry(user_id.to_owned())
+            .or_default()
+            .insert(id.clone(), new_list);
+
+        Ok(id)
+    }
+
+    async fn list_lists(&self, user_id: &str) -> RepositoryResult<Vec<ListEntity>> {
+        let lists_by_user = self.lists_by_user.lock().unwrap();
+        let mut lists: Vec<_> = lists_by_user
+            .get(user_id)
+            .map_or(vec![], |lists| lists.values().cloned().collect());
+        lists.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(lists)
+    }
+
+    async fn get_list(&self, user_id: &str, id: &str) -> RepositoryResult<Option<ListEntity>> {
+        let lists_by_user = self.lists_by_user.lock().unwrap();
+        Ok(lists_by_user
+            .get(user_id)
+            .and_then(|lists| lists.get(id))
+            .cloned())
+    }
+
+    async fn rename_list(&self, user_id: &str, id: &str, name: &str) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut lists_by_user = self.lists_by_user.lock().unwrap();
+        let list = lists_by_user
+            .get_mut(user_id)
+            .and_then(|lists| lists.get_mut(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        list.name = name.to_owned();
+
+        Ok(())
+    }
+
+    async fn set_list_archived(
+        &self,
+        user_id: &str,
+        id: &str,
+        archived: bool,
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut lists_by_user = self.lists_by_user.lock().unwrap();
+        let list = lists_by_user
+            .get_mut(user_id)
+            .and_then(|lists| lists.get_mut(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        list.archived_at = match archived {
+            true => Some(list.archived_at.unwrap_or_else(Utc::now)),
+            false => None,
+        };
+
+        Ok(())
+    }
+
+    async fn delete_list(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut lists_by_user = self.lists_by_user.lock().unwrap();
+
+        lists_by_user
+            .get_mut(user_id)
+            .and_then(|lists| lists.remove(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        Ok(())
+    }
+}
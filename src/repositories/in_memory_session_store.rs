@@ -0,0 +1,99 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+
+use crate::utils::random_id;
+
+use super::{
+    session_store::{NewSession, SessionEntity, SessionStore},
+    RepositoryError, RepositoryResult,
+};
+
+pub struct InMemorySessionStore {
+    sessions_by_token_hash: Mutex<HashMap<String, SessionEntity>>,
+}
+
+impl InMemorySessionStore {
+    pub fn new() -> Self {
+        Self {
+            sessions_by_token_hash: Default::default(),
+        }
+    }
+}
+
+impl Default for InMemorySessionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SessionStore for InMemorySessionStore {
+    async fn create_session(&self, session: NewSession) -> RepositoryResult<SessionEntity> {
+        let mut sessions = self.sessions_by_token_hash.lock().await;
+        if sessions.contains_key(&session.token_hash) {
+            return Err(RepositoryError::ItemAlreadyExists);
+        }
+
+        let now = Utc::now();
+        let entity = SessionEntity {
+            id: random_id(),
+            user_id: session.user_id,
+            device: session.device,
+            ip: session.ip,
+            created_at: now,
+            last_seen_at: now,
+        };
+        sessions.insert(session.token_hash, entity.clone());
+
+        Ok(entity)
+    }
+
+    async fn get_session_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> RepositoryResult<Option<SessionEntity>> {
+        let sessions = self.sessions_by_token_hash.lock().await;
+        Ok(sessions.get(token_hash).cloned())
+    }
+
+    async fn touch_session(&self, id: &str, last_seen_at: DateTime<Utc>) -> RepositoryResult<()> {
+        let mut sessions = self.sessions_by_token_hash.lock().await;
+        let session = sessions
+            .values_mut()
+            .find(|s| s.id == id)
+            .ok_or(RepositoryError::ItemNotFound)?;
+        session.last_seen_at = last_seen_at;
+        Ok(())
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> RepositoryResult<Vec<SessionEntity>> {
+        let sessions = self.sessions_by_token_hash.lock().await;
+        let mut user_sessions: Vec<_> = sessions
+            .values()
+            .filter(|s| s.user_id == user_id)
+            .cloned()
+            .collect();
+        user_sessions.sort_by_key(|s| Reverse(s.last_seen_at));
+        Ok(user_sessions)
+    }
+
+    async fn delete_session(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let mut sessions = self.sessions_by_token_hash.lock().await;
+        let before = sessions.len();
+        sessions.retain(|_, s| !(s.user_id == user_id && s.id == id));
+        if sessions.len() == before {
+            return Err(RepositoryError::ItemNotFound);
+        }
+        Ok(())
+    }
+
+    async fn delete_sessions_idle_since(&self, cutoff: DateTime<Utc>) -> RepositoryResult<u64> {
+        let mut sessions = self.sessions_by_token_hash.lock().await;
+        let before = sessions.len();
+        sessions.retain(|_, s| s.last_seen_at >= cutoff);
+        Ok((before - sessions.len()) as u64)
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+use async_trait::async_trait;
+
+use crate::utils::random_id;
+
+use super::{
+    in_memory_todo_repository::InMemoryTodoRepository,
+    in_memory_unit_of_work::TransactionLock,
+    tag_repository::{TagEntity, TagRepository},
+    RepositoryError, RepositoryResult,
+};
+
+/// The tags of each user, by ID. Which todos have a tag is kept on the todos, by name, so
+/// changes to a tag are made to the todos too.
+pub struct InMemoryTagRepository {
+    tags_by_user: Arc<Mutex<HashMap<String, HashMap<String, String>>>>,
+    todos: Arc<InMemoryTodoRepository>,
+    transaction_lock: TransactionLock,
+}
+
+impl InMemoryTagRepository {
+    pub fn new(todos: Arc<InMemoryTodoRepository>, transaction_lock: TransactionLock) -> Self {
+        Self {
+            tags_by_user: Default::default(),
+            todos,
+            transaction_lock,
+        }
+    }
+
+    /// Shares the data, for the transaction holding the lock to write through
+    pub fn in_transaction(&self) -> Self {
+        Self {
+            tags_by_user: Arc::clone(&self.tags_by_user),
+            todos: Arc::clone(&self.todos),
+            transaction_lock: self.transaction_lock.held(),
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, HashMap<String, String>> {
+        self.tags_by_user.lock().unwrap().clone()
+    }
+
+    pub fn restore(&self, snapshot: HashMap<String, HashMap<String, String>>) {
+        *self.tags_by_user.lock().unwrap() = snapshot;
+    }
+}
+
+#[async_trait]
+impl TagRepository for InMemoryTagRepository {
+    async fn set_todo_tags(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        names: &[String],
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut tags_by_user = self.tags_by_user.lock().unwrap();
+        self.todos.set_tags(user_id, todo_id, names)?;
+
+        let tags = tags_by_user.entry(user_id.to_owned()).or_default();
+        for name in names {
+            if !tags.values().any(|tag| tag == name) {
+                tags.insert(random_id(), name.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list_tags(&self, user_id: &str) -> RepositoryResult<Vec<TagEntity>> {
+        let tags_by_user = self.tags_by_user.lock().unwrap();
+        let counts = self.todos.tag_counts(user_id);
+
+        let mut tags: Vec<_> = tags_by_user
+            .get(user_id)
+            .into_iter()
+            .flatten()
+            .map(|(id, name)| TagEntity {
+                id: id.clone(),
+                name: name.clone(),
+                todo_count: counts.get(name).copied().unwrap_or(0),
+            })
+            .collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(tags)
+    }
+
+    async fn rename_tag(&self, user_id: &str, id: &str, name: &str) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut tags_by_user = self.tags_by_user.lock().unwrap();
+        let tags = tags_by_user
+            .get_mut(user_id)
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        if tags
+            .iter()
+            .any(|(other_id, other)| other_id != id && other == name)
+        {
+            return Err(RepositoryError::ItemAlreadyExists);
+        }
+        let tag = tags.get_mut(id).ok_or(RepositoryError::ItemNotFound)?;
+
+        let old_name = std::mem::replace(tag, name.to_owned());
+        self.todos.update_tags(user_id, |tags| {
+            for tag in tags.iter_mut().filter(|tag| **tag == old_name) {
+                *tag = name.to_owned();
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn merge_tags(
+        &self,
+        user_id: &str,
+        source_id: &str,
+        target_id: &str,
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut tags_by_user = self.tags_by_user.lock().unwrap();
+        let tags = tags_by_user
+            .get_mut(user_id)
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        let target = tags
+            .get(target_id)
+            .cloned()
+            .ok_or(RepositoryError::ItemNotFound)?;
+        let source = tags
+            .remove(source_id)
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        self.todos.update_tags(user_id, |tags| {
+            for tag in tags.iter_mut().filter(|tag| **tag == source) {
+                *tag = target.clone();
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn delete_tag(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut tags_by_user = self.tags_by_user.lock().unwrap();
+        let name = tags_by_user
+            .get_mut(user_id)
+            .and_then(|tags| tags.remove(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        self.todos
+            .update_tags(user_id, |tags| tags.retain(|tag| *tag != name));
+
+        Ok(())
+    }
+}
@@ -1,63 +1,425 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
-use tokio::sync::Mutex;
+use chrono::Utc;
 
-use crate::utils::random_id;
+use crate::utils::{random_id, todo_search::TodoSearch};
 
 use super::{
-    todo_repository::{TodoEntity, TodoRepository},
-    RepositoryResult,
+    in_memory_list_repository::InMemoryListRepository,
+    in_memory_unit_of_work::TransactionLock,
+    todo_repository::{
+        ChecklistProgress, MoveDirection, TodoDetails, TodoEntity, TodoFilter, TodoRepository,
+        TodoSort, TodoView,
+    },
+    RepositoryError, RepositoryResult,
 };
 
 pub struct InMemoryTodoRepository {
-    todos_by_user: Mutex<HashMap<String, HashMap<String, TodoEntity>>>,
+    todos_by_user: Arc<Mutex<HashMap<String, HashMap<String, TodoEntity>>>>,
+    /// to leave the todos of archived lists out of searches
+    lists: Arc<InMemoryListRepository>,
+    transaction_lock: TransactionLock,
 }
 
 impl InMemoryTodoRepository {
-    pub fn new() -> Self {
+    pub fn new(lists: Arc<InMemoryListRepository>, transaction_lock: TransactionLock) -> Self {
         Self {
             todos_by_user: Default::default(),
+            lists,
+            transaction_lock,
+        }
+    }
+
+    /// Shares the data, for the transaction holding the lock to write through
+    pub fn in_transaction(&self) -> Self {
+        Self {
+            todos_by_user: Arc::clone(&self.todos_by_user),
+            lists: Arc::clone(&self.lists),
+            transaction_lock: self.transaction_lock.held(),
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, HashMap<String, TodoEntity>> {
+        self.todos_by_user.lock().unwrap().clone()
+    }
+
+    pub fn restore(&self, snapshot: HashMap<String, HashMap<String, TodoEntity>>) {
+        *self.todos_by_user.lock().unwrap() = snapshot;
+    }
+
+    /// Replaces the tags of the todo, which the in memory backend keeps on the todo itself
+    pub fn set_tags(&self, user_id: &str, id: &str, tags: &[String]) -> RepositoryResult<()> {
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        let todo = todos_by_user
+            .get_mut(user_id)
+            .and_then(|todos| todos.get_mut(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        todo.tags = tags.to_vec();
+        todo.tags.sort();
+        todo.tags.dedup();
+
+        Ok(())
+    }
+
+    /// Runs `update` on the tags of every todo of the user, e.g. to rename a tag
+    pub fn update_tags(&self, user_id: &str, mut update: impl FnMut(&mut Vec<String>)) {
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        for todo in todos_by_user
+            .get_mut(user_id)
+            .into_iter()
+            .flat_map(HashMap::values_mut)
+        {
+            update(&mut todo.tags);
+            todo.tags.sort();
+            todo.tags.dedup();
+        }
+    }
+
+    /// Whether the user has the todo
+    pub fn contains(&self, user_id: &str, id: &str) -> bool {
+        let todos_by_user = self.todos_by_user.lock().unwrap();
+        todos_by_user
+            .get(user_id)
+            .is_some_and(|todos| todos.contains_key(id))
+    }
+
+    /// Updates the checklist progress of the todo, which the checklist repository keeps current
+    pub fn set_checklist_progress(&self, user_id: &str, id: &str, checklist: ChecklistProgress) {
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        if let Some(todo) = todos_by_user
+            .get_mut(user_id)
+            .and_then(|todos| todos.get_mut(id))
+        {
+            todo.checklist = checklist;
+        }
+    }
+
+    /// How many of the user's todos have each tag
+    pub fn tag_counts(&self, user_id: &str) -> HashMap<String, i64> {
+        let todos_by_user = self.todos_by_user.lock().unwrap();
+        let mut counts = HashMap::new();
+        for todo in todos_by_user
+            .get(user_id)
+            .into_iter()
+            .flat_map(HashMap::values)
+        {
+            for tag in &todo.tags {
+                *counts.entry(tag.clone()).or_default() += 1;
+            }
         }
+        counts
+    }
+}
+
+/// The todos in position order, same as the SQL repositories
+fn sorted(todos: &HashMap<String, TodoEntity>) -> Vec<&TodoEntity> {
+    let mut todos: Vec<_> = todos.values().collect();
+    todos.sort_by_key(|todo| (todo.position, todo.created_at, &todo.id));
+    todos
+}
+
+/// The position after the last todo of the list
+fn next_position(todos: &HashMap<String, TodoEntity>, list_id: Option<&str>) -> i64 {
+    todos
+        .values()
+        .filter(|todo| todo.list_id.as_deref() == list_id)
+        .map(|todo| todo.position)
+        .max()
+        .unwrap_or(0)
+        + 1
+}
+
+fn in_view(todo: &TodoEntity, filter: &TodoFilter) -> bool {
+    if todo.list_id != filter.list_id || !filter.tags.iter().all(|tag| todo.tags.contains(tag)) {
+        return false;
+    }
+    let today = filter.now.date_naive();
+    match filter.view {
+        TodoView::All => true,
+        TodoView::Today => todo.due_date == Some(today),
+        TodoView::Upcoming => todo.due_date.is_some_and(|date| date > today),
+        TodoView::Overdue => todo.is_overdue(filter.now),
+        TodoView::NoDate => todo.due_date.is_none(),
     }
 }
 
 #[async_trait]
 impl TodoRepository for InMemoryTodoRepository {
-    async fn add_todo(&self, user_id: &str, name: &str) -> RepositoryResult<String> {
-        let mut todos_by_user = self.todos_by_user.lock().await;
+    async fn add_todo(
+        &self,
+        user_id: &str,
+        list_id: Option<&str>,
+        name: &str,
+        details: &TodoDetails,
+    ) -> RepositoryResult<String> {
+        let _lock = self.transaction_lock.write().await;
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        let todos = todos_by_user.entry(user_id.to_owned()).or_default();
 
         let id = random_id();
+        let now = Utc::now();
         let new_todo = TodoEntity {
             id: id.clone(),
+            list_id: list_id.map(str::to_owned),
             name: name.to_owned(),
             is_complete: false,
+            created_at: now,
+            updated_at: now,
+            completed_at: None,
+            position: next_position(todos, list_id),
+            due_date: details.due_date,
+            due_time: details.due_time,
+            priority: details.priority,
+            tags: vec![],
+            checklist: ChecklistProgress::default(),
+            auto_complete: false,
+            recurrence: details.recurrence.clone(),
         };
+        todos.insert(id.clone(), new_todo);
 
-        if let Some(todos) = todos_by_user.get_mut(user_id) {
-            todos.insert(id.clone(), new_todo);
-        } else {
-            let new_map = HashMap::from([(id.clone(), new_todo)]);
-            todos_by_user.insert(user_id.to_owned(), new_map);
+        Ok(id)
+    }
+
+    async fn list_todos(
+        &self,
+        user_id: &str,
+        filter: &TodoFilter,
+    ) -> RepositoryResult<Vec<TodoEntity>> {
+        let todos_by_user = self.todos_by_user.lock().unwrap();
+        let mut todos: Vec<_> = todos_by_user.get(user_id).map_or(vec![], |todos| {
+            sorted(todos)
+                .into_iter()
+                .filter(|todo| in_view(todo, filter))
+                .cloned()
+                .collect()
+        });
+
+        // stable sorts, so todos due at the same time stay in position order. missing dates and
+        // times go last, like NULLS LAST.
+        let due = |todo: &TodoEntity| {
+            (
+                todo.due_date.is_none(),
+                todo.due_date,
+                todo.due_time.is_none(),
+                todo.due_time,
+            )
+        };
+        match filter.sort {
+            TodoSort::Position => {}
+            TodoSort::DueDate => todos.sort_by_key(due),
+            TodoSort::Priority => todos.sort_by_key(|todo| (Reverse(todo.priority), due(todo))),
         }
 
-        Ok(id)
+        Ok(todos)
+    }
+
+    async fn search_todos(
+        &self,
+        user_id: &str,
+        search: &TodoSearch,
+    ) -> RepositoryResult<Vec<TodoEntity>> {
+        let archived = self.lists.archived_list_ids(user_id);
+        let todos_by_user = self.todos_by_user.lock().unwrap();
+        let mut matches: Vec<_> = todos_by_user
+            .get(user_id)
+            .into_iter()
+            .flat_map(HashMap::values)
+            .filter(|todo| {
+                todo.list_id
+                    .as_ref()
+                    .is_none_or(|list_id| !archived.contains(list_id))
+                    && search.is_complete.is_none_or(|c| todo.is_complete == c)
+                    && search.tags.iter().all(|tag| todo.tags.contains(tag))
+                    && search
+                        .due_from
+                        .is_none_or(|from| todo.due_date >= Some(from))
+                    && search
+                        .due_until
+                        .is_none_or(|until| todo.due_date.is_some_and(|date| date <= until))
+            })
+            .filter_map(|todo| Some((search.rank_name(&todo.name)?, todo.clone())))
+            .collect();
+        matches.sort_by(|(a_rank, a), (b_rank, b)| {
+            let key = |todo: &TodoEntity| {
+                (
+                    todo.is_complete,
+                    todo.due_date.is_none(),
+                    todo.due_date,
+                    todo.created_at,
+                    todo.id.clone(),
+                )
+            };
+            b_rank.cmp(a_rank).then_with(|| key(a).cmp(&key(b)))
+        });
+
+        Ok(matches.into_iter().map(|(_, todo)| todo).collect())
+    }
+
+    async fn get_todo(&self, user_id: &str, id: &str) -> RepositoryResult<Option<TodoEntity>> {
+        let todos_by_user = self.todos_by_user.lock().unwrap();
+        Ok(todos_by_user
+            .get(user_id)
+            .and_then(|todos| todos.get(id))
+            .cloned())
     }
 
-    async fn list_todos(&self, user_id: &str) -> RepositoryResult<Vec<TodoEntity>> {
-        let todos_by_user = self.todos_by_user.lock().await;
-        Ok(todos_by_user.get(user_id).map_or(vec![], |entries| {
-            entries.into_iter().map(|i| i.1.clone()).collect()
-        }))
+    async fn update_todo_details(
+        &self,
+        user_id: &str,
+        id: &str,
+        details: &TodoDetails,
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        let todo = todos_by_user
+            .get_mut(user_id)
+            .and_then(|todos| todos.get_mut(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        todo.due_date = details.due_date;
+        todo.due_time = details.due_time;
+        todo.priority = details.priority;
+        todo.recurrence = details.recurrence.clone();
+        todo.updated_at = Utc::now();
+
+        Ok(())
     }
 
     async fn remove_todo(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
-        let mut todos_by_user = self.todos_by_user.lock().await;
+        let _lock = self.transaction_lock.write().await;
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
 
-        if let Some(todos) = todos_by_user.get_mut(user_id) {
-            todos.remove(id);
-        };
+        todos_by_user
+            .get_mut(user_id)
+            .and_then(|todos| todos.remove(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        Ok(())
+    }
+
+    async fn set_todo_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> RepositoryResult<bool> {
+        let _lock = self.transaction_lock.write().await;
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        let todo = todos_by_user
+            .get_mut(user_id)
+            .and_then(|todos| todos.get_mut(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+        if todo.is_complete == complete {
+            return Ok(false);
+        }
+
+        let now = Utc::now();
+        todo.is_complete = complete;
+        todo.completed_at = complete.then_some(now);
+        todo.updated_at = now;
+
+        Ok(true)
+    }
+
+    async fn set_todo_auto_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        auto_complete: bool,
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        let todo = todos_by_user
+            .get_mut(user_id)
+            .and_then(|todos| todos.get_mut(id))
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        todo.auto_complete = auto_complete;
+        todo.updated_at = Utc::now();
+
+        Ok(())
+    }
+
+    async fn move_todo(
+        &self,
+        user_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        let todos = todos_by_user
+            .get_mut(user_id)
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        let target = todos.get(id).ok_or(RepositoryError::ItemNotFound)?.clone();
+        let mut candidates = sorted(todos)
+            .into_iter()
+            .filter(|todo| todo.list_id == target.list_id)
+            .filter(|todo| match direction {
+                MoveDirection::Up => todo.position < target.position,
+                MoveDirection::Down => todo.position > target.position,
+            });
+        let neighbour = match direction {
+            MoveDirection::Up => candidates.next_back(),
+            MoveDirection::Down => candidates.next(),
+        }
+        .map(|todo| (todo.id.clone(), todo.position));
+
+        if let Some((neighbour_id, neighbour_position)) = neighbour {
+            if let Some(todo) = todos.get_mut(&neighbour_id) {
+                todo.position = target.position;
+            }
+            if let Some(todo) = todos.get_mut(id) {
+                todo.position = neighbour_position;
+            }
+        }
 
         Ok(())
     }
+
+    async fn move_todo_to_list(
+        &self,
+        user_id: &str,
+        id: &str,
+        list_id: Option<&str>,
+    ) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        let todos = todos_by_user
+            .get_mut(user_id)
+            .ok_or(RepositoryError::ItemNotFound)?;
+
+        let position = next_position(todos, list_id);
+        let todo = todos.get_mut(id).ok_or(RepositoryError::ItemNotFound)?;
+        todo.list_id = list_id.map(str::to_owned);
+        todo.position = position;
+        todo.updated_at = Utc::now();
+
+        Ok(())
+    }
+
+    async fn remove_list_todos(&self, user_id: &str, list_id: &str) -> RepositoryResult<u64> {
+        let _lock = self.transaction_lock.write().await;
+        let mut todos_by_user = self.todos_by_user.lock().unwrap();
+        match todos_by_user.get_mut(user_id) {
+            Some(todos) => {
+                let count = todos.len();
+                todos.retain(|_, todo| todo.list_id.as_deref() != Some(list_id));
+                Ok((count - todos.len()) as u64)
+            }
+            None => Ok(0),
+        }
+    }
+
+    async fn count_todos(&self) -> RepositoryResult<i64> {
+        let todos_by_user = self.todos_by_user.lock().unwrap();
+        Ok(todos_by_user.values().map(HashMap::len).sum::<usize>() as i64)
+    }
 }
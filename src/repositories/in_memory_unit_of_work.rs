@@ -0,0 +1,354 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use tokio::sync::{Mutex, MutexGuard, OwnedMutexGuard};
+
+use super::{
+    audit_repository::{AuditEventEntity, AuditEventFilter, AuditRepository, NewAuditEvent},
+    checklist_repository::{ChecklistItemEntity, ChecklistRepository},
+    in_memory_audit_repository::InMemoryAuditRepository,
+    in_memory_checklist_repository::InMemoryChecklistRepository,
+    in_memory_list_repository::InMemoryListRepository,
+    in_memory_tag_repository::InMemoryTagRepository,
+    in_memory_todo_repository::InMemoryTodoRepository,
+    in_memory_user_repository::InMemoryUserRepository,
+    list_repository::{ListEntity, ListRepository},
+    tag_repository::TagRepository,
+    todo_repository::{TodoEntity, TodoRepository},
+    unit_of_work::{RepositoryTransaction, UnitOfWork},
+    user_repository::{UserEntity, UserRepository},
+    RepositoryResult,
+};
+
+/// Transactions over the in memory repositories. They run one at a time and write straight to
+/// the repositories, which are put back the way they were at `begin` on rollback. Other requests
+/// can see changes before they're committed, which is fine for a backend that's only for trying
+/// the app out, but wait for the transaction to finish before writing (see [TransactionLock]),
+/// so rolling back doesn't undo their changes. Audit events are the exception, only the ones
+/// appended in the transaction are removed, as events are recorded all the time, e.g. on login,
+/// and waiting for a transaction to record one would be pointless.
+pub struct InMemoryUnitOfWork {
+    users: Arc<InMemoryUserRepository>,
+    todos: Arc<InMemoryTodoRepository>,
+    lists: Arc<InMemoryListRepository>,
+    tags: Arc<InMemoryTagRepository>,
+    checklists: Arc<InMemoryChecklistRepository>,
+    audit: Arc<InMemoryAuditRepository>,
+    transaction_lock: TransactionLock,
+}
+
+impl InMemoryUnitOfWork {
+    /// The repositories must share `transaction_lock` with the unit of work.
+    pub fn new(
+        transaction_lock: TransactionLock,
+        users: Arc<InMemoryUserRepository>,
+        todos: Arc<InMemoryTodoRepository>,
+        lists: Arc<InMemoryListRepository>,
+        tags: Arc<InMemoryTagRepository>,
+        checklists: Arc<InMemoryChecklistRepository>,
+        audit: Arc<InMemoryAuditRepository>,
+    ) -> Self {
+        Self {
+            users,
+            todos,
+            lists,
+            tags,
+            checklists,
+            audit,
+            transaction_lock,
+        }
+    }
+}
+
+/// Held by a transaction from `begin` until it's committed or rolled back, and by each write
+/// made outside of it, so those wait for the transaction instead of being undone by its
+/// rollback. The repositories a transaction writes through have the lock [held](Self::held).
+#[derive(Clone, Default)]
+pub struct TransactionLock {
+    lock: Arc<Mutex<()>>,
+    held: bool,
+}
+
+impl TransactionLock {
+    /// Waits for the open transaction to finish, unless this is the transaction's own lock.
+    pub async fn write(&self) -> Option<MutexGuard<'_, ()>> {
+        if self.held {
+            return None;
+        }
+        Some(self.lock.lock().await)
+    }
+
+    /// The lock of a repository written to by the transaction holding it
+    pub fn held(&self) -> Self {
+        Self {
+            lock: Arc::clone(&self.lock),
+            held: true,
+        }
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for InMemoryUnitOfWork {
+    async fn begin(&self) -> RepositoryResult<Box<dyn RepositoryTransaction>> {
+        let guard = Arc::clone(&self.transaction_lock.lock).lock_owned().await;
+
+        let snapshot = Snapshot {
+            users: self.users.snapshot(),
+            todos: self.todos.snapshot(),
+            lists: self.lists.snapshot(),
+            tags: self.tags.snapshot(),
+            checklists: self.checklists.snapshot(),
+        };
+
+        Ok(Box::new(InMemoryTransaction {
+            users: self.users.in_transaction(),
+            todos: self.todos.in_transaction(),
+            lists: self.lists.in_transaction(),
+            tags: self.tags.in_transaction(),
+            checklists: self.checklists.in_transaction(),
+            audit: TransactionAuditRepository {
+                inner: Arc::clone(&self.audit),
+                appended_ids: Default::default(),
+            },
+            snapshot: Some(snapshot),
+            _guard: guard,
+        }))
+    }
+}
+
+struct Snapshot {
+    users: HashMap<String, UserEntity>,
+    todos: HashMap<String, HashMap<String, TodoEntity>>,
+    lists: HashMap<String, HashMap<String, ListEntity>>,
+    tags: HashMap<String, HashMap<String, String>>,
+    checklists: HashMap<String, HashMap<String, ChecklistItemEntity>>,
+}
+
+/// Audit repository of a transaction, keeping track of the events it appends so only those
+/// are removed on rollback
+struct TransactionAuditRepository {
+    inner: Arc<InMemoryAuditRepository>,
+    appended_ids: std::sync::Mutex<HashSet<String>>,
+}
+
+#[async_trait]
+impl AuditRepository for TransactionAuditRepository {
+    async fn append_event(&self, event: NewAuditEvent) -> RepositoryResult<String> {
+        let id = self.inner.append_event(event).await?;
+        self.appended_ids.lock().unwrap().insert(id.clone());
+        Ok(id)
+    }
+
+    async fn list_events(
+        &self,
+        filter: &AuditEventFilter,
+    ) -> RepositoryResult<Vec<AuditEventEntity>> {
+        self.inner.list_events(filter).await
+    }
+}
+
+struct InMemoryTransaction {
+    users: InMemoryUserRepository,
+    todos: InMemoryTodoRepository,
+    lists: InMemoryListRepository,
+    tags: InMemoryTagRepository,
+    checklists: InMemoryChecklistRepository,
+    audit: TransactionAuditRepository,
+    /// taken once committed, otherwise restored when dropped
+    snapshot: Option<Snapshot>,
+    _guard: OwnedMutexGuard<()>,
+}
+
+#[async_trait]
+impl RepositoryTransaction for InMemoryTransaction {
+    fn users(&self) -> &dyn UserRepository {
+        &self.users
+    }
+
+    fn todos(&self) -> &dyn TodoRepository {
+        &self.todos
+    }
+
+    fn lists(&self) -> &dyn ListRepository {
+        &self.lists
+    }
+
+    fn tags(&self) -> &dyn TagRepository {
+        &self.tags
+    }
+
+    fn checklists(&self) -> &dyn ChecklistRepository {
+        &self.checklists
+    }
+
+    fn audit(&self) -> &dyn AuditRepository {
+        &self.audit
+    }
+
+    async fn commit(mut self: Box<Self>) -> RepositoryResult<()> {
+        self.snapshot = None;
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> RepositoryResult<()> {
+        Ok(())
+    }
+}
+
+impl Drop for InMemoryTransaction {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.users.restore(snapshot.users);
+            self.todos.restore(snapshot.todos);
+            self.lists.restore(snapshot.lists);
+            self.tags.restore(snapshot.tags);
+            self.checklists.restore(snapshot.checklists);
+            let appended_ids = std::mem::take(&mut *self.audit.appended_ids.lock().unwrap());
+            self.audit.inner.remove_events(&appended_ids);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::repositories::audit_repository::AuditEventType;
+
+    use super::*;
+
+    struct Store {
+        unit_of_work: InMemoryUnitOfWork,
+        users: Arc<InMemoryUserRepository>,
+        audit: Arc<InMemoryAuditRepository>,
+    }
+
+    fn store() -> Store {
+        let transaction_lock = TransactionLock::default();
+        let users = Arc::new(InMemoryUserRepository::new(transaction_lock.clone()));
+        let lists = Arc::new(InMemoryListRepository::new(transaction_lock.clone()));
+        let todos = Arc::new(InMemoryTodoRepository::new(
+            Arc::clone(&lists),
+            transaction_lock.clone(),
+        ));
+        let tags = Arc::new(InMemoryTagRepository::new(
+            Arc::clone(&todos),
+            transaction_lock.clone(),
+        ));
+        let checklists = Arc::new(InMemoryChecklistRepository::new(
+            Arc::clone(&todos),
+            transaction_lock.clone(),
+        ));
+        let audit = Arc::new(InMemoryAuditRepository::new());
+        let unit_of_work = InMemoryUnitOfWork::new(
+            transaction_lock,
+            Arc::clone(&users),
+            todos,
+            lists,
+            tags,
+            checklists,
+            Arc::clone(&audit),
+        );
+        Store {
+            unit_of_work,
+            users,
+            audit,
+        }
+    }
+
+    fn event(event_type: AuditEventType) -> NewAuditEvent {
+        NewAuditEvent {
+            actor_id: None,
+            event_type,
+            ip: None,
+            user_agent: None,
+            payload: json!({}),
+        }
+    }
+
+    async fn event_types(audit: &InMemoryAuditRepository) -> Vec<AuditEventType> {
+        let events = audit.list_events(&Default::default()).await.unwrap();
+        events.into_iter().map(|event| event.event_type).collect()
+    }
+
+    #[tokio::test]
+    async fn rollback_keeps_events_appended_outside_of_the_transaction() {
+        let Store {
+            unit_of_work,
+            audit,
+            ..
+        } = store();
+        audit
+            .append_event(event(AuditEventType::Login))
+            .await
+            .unwrap();
+
+        let tx = unit_of_work.begin().await.unwrap();
+        tx.audit()
+            .append_event(event(AuditEventType::Registration))
+            .await
+            .unwrap();
+        // e.g. a failed login recorded by another request while the transaction is open
+        audit
+            .append_event(event(AuditEventType::LoginFailed))
+            .await
+            .unwrap();
+        tx.rollback().await.unwrap();
+
+        assert_eq!(
+            event_types(&audit).await,
+            [AuditEventType::LoginFailed, AuditEventType::Login]
+        );
+    }
+
+    #[tokio::test]
+    async fn commit_keeps_events_appended_in_the_transaction() {
+        let Store {
+            unit_of_work,
+            audit,
+            ..
+        } = store();
+
+        let tx = unit_of_work.begin().await.unwrap();
+        tx.audit()
+            .append_event(event(AuditEventType::Registration))
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        assert_eq!(event_types(&audit).await, [AuditEventType::Registration]);
+    }
+
+    #[tokio::test]
+    async fn rollback_keeps_writes_waiting_for_the_transaction() {
+        let Store {
+            unit_of_work,
+            users,
+            ..
+        } = store();
+
+        let tx = unit_of_work.begin().await.unwrap();
+        tx.users().create_user("rolled back", "hash").await.unwrap();
+        // another request, writing outside of the transaction
+        let write = tokio::spawn({
+            let users = Arc::clone(&users);
+            async move { users.create_user("kept", "hash").await.unwrap() }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!write.is_finished());
+        tx.rollback().await.unwrap();
+        write.await.unwrap();
+
+        let usernames: Vec<_> = users
+            .list_users()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|user| user.username)
+            .collect();
+        assert_eq!(usernames, ["kept"]);
+    }
+}
@@ -1,55 +1,118 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
-use tokio::sync::Mutex;
 
 use crate::utils::random_id;
 
 use super::{
-    user_repository::{UserEntity, UserRepository},
-    RepositoryResult,
+    in_memory_unit_of_work::TransactionLock,
+    user_repository::{UserEntity, UserRepository, UserRole},
+    RepositoryError, RepositoryResult,
 };
 
 pub struct InMemoryUserRepository {
-    users_by_id: Mutex<HashMap<String, UserEntity>>,
+    users_by_id: Arc<Mutex<HashMap<String, UserEntity>>>,
+    transaction_lock: TransactionLock,
 }
 
 impl InMemoryUserRepository {
-    pub fn new() -> Self {
+    pub fn new(transaction_lock: TransactionLock) -> Self {
         Self {
             users_by_id: Default::default(),
+            transaction_lock,
+        }
+    }
+
+    /// Shares the data, for the transaction holding the lock to write through
+    pub fn in_transaction(&self) -> Self {
+        Self {
+            users_by_id: Arc::clone(&self.users_by_id),
+            transaction_lock: self.transaction_lock.held(),
         }
     }
+
+    pub fn snapshot(&self) -> HashMap<String, UserEntity> {
+        self.users_by_id.lock().unwrap().clone()
+    }
+
+    pub fn restore(&self, snapshot: HashMap<String, UserEntity>) {
+        *self.users_by_id.lock().unwrap() = snapshot;
+    }
+}
+
+impl Default for InMemoryUserRepository {
+    fn default() -> Self {
+        Self::new(TransactionLock::default())
+    }
 }
 
 #[async_trait]
 impl UserRepository for InMemoryUserRepository {
     async fn get_user_by_id(&self, id: &str) -> RepositoryResult<Option<UserEntity>> {
-        let users = self.users_by_id.lock().await;
+        let users = self.users_by_id.lock().unwrap();
         let user = users.get(id).cloned();
         Ok(user)
     }
 
     async fn get_user_by_username(&self, username: &str) -> RepositoryResult<Option<UserEntity>> {
-        let users = self.users_by_id.lock().await;
+        let users = self.users_by_id.lock().unwrap();
         let user = users
             .iter()
             .find_map(|(_, user)| (user.username == username).then(|| user.clone()));
         Ok(user)
     }
 
+    async fn list_users(&self) -> RepositoryResult<Vec<UserEntity>> {
+        let users = self.users_by_id.lock().unwrap();
+        let mut users: Vec<_> = users.values().cloned().collect();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        Ok(users)
+    }
+
     async fn create_user(&self, username: &str, pw_hash: &str) -> RepositoryResult<String> {
+        let _lock = self.transaction_lock.write().await;
         let id = random_id();
 
         let entity = UserEntity {
             id: id.clone(),
             username: username.to_owned(),
             pw_hash: pw_hash.to_owned(),
+            role: UserRole::User,
         };
 
-        let mut users = self.users_by_id.lock().await;
+        let mut users = self.users_by_id.lock().unwrap();
         users.insert(id.clone(), entity);
 
         Ok(id)
     }
+
+    async fn update_password_hash(&self, id: &str, pw_hash: &str) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut users = self.users_by_id.lock().unwrap();
+        let user = users.get_mut(id).ok_or(RepositoryError::ItemNotFound)?;
+        user.pw_hash = pw_hash.to_owned();
+        Ok(())
+    }
+
+    async fn update_user_role(&self, id: &str, role: UserRole) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut users = self.users_by_id.lock().unwrap();
+        let user = users.get_mut(id).ok_or(RepositoryError::ItemNotFound)?;
+        user.role = role;
+        Ok(())
+    }
+
+    async fn delete_user(&self, id: &str) -> RepositoryResult<()> {
+        let _lock = self.transaction_lock.write().await;
+        let mut users = self.users_by_id.lock().unwrap();
+        users.remove(id).ok_or(RepositoryError::ItemNotFound)?;
+        Ok(())
+    }
+
+    async fn count_users(&self) -> RepositoryResult<i64> {
+        Ok(self.users_by_id.lock().unwrap().len() as i64)
+    }
 }
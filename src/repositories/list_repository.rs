@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::RepositoryResult;
+
+/// A named list (project) of a user's todos. Todos without a list are in the user's inbox.
+#[derive(Debug, Clone)]
+pub struct ListEntity {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    /// archived lists are hidden from the todos page, but keep their todos
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+impl ListEntity {
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+}
+
+#[async_trait]
+pub trait ListRepository: Send + Sync {
+    async fn create_list(&self, user_id: &str, name: &str) -> RepositoryResult<String>;
+    /// Lists of the user in the order they were created, archived ones included
+    async fn list_lists(&self, user_id: &str) -> RepositoryResult<Vec<ListEntity>>;
+    async fn get_list(&self, user_id: &str, id: &str) -> RepositoryResult<Option<ListEntity>>;
+    async fn rename_list(&self, user_id: &str, id: &str, name: &str) -> RepositoryResult<()>;
+    /// Archives the list or brings it back, keeping when it was first archived
+    async fn set_list_archived(
+        &self,
+        user_id: &str,
+        id: &str,
+        archived: bool,
+    ) -> RepositoryResult<()>;
+    /// Deletes the list. Its todos are removed with `TodoRepository::remove_list_todos` in the
+    /// same transaction, as only the SQL backends delete them along with the list.
+    async fn delete_list(&self, user_id: &str, id: &str) -> RepositoryResult<()>;
+}
@@ -1,13 +1,84 @@
+pub mod audit_repository;
+pub mod cached_user_repository;
+pub mod checklist_repository;
+pub mod health_repository;
+#[cfg(feature = "memory")]
+pub mod in_memory_audit_repository;
+#[cfg(feature = "memory")]
+pub mod in_memory_checklist_repository;
+#[cfg(feature = "memory")]
+pub mod in_memory_health_repository;
+#[cfg(feature = "memory")]
+pub mod in_memory_list_repository;
+#[cfg(feature = "memory")]
+pub mod in_memory_session_store;
+#[cfg(feature = "memory")]
+pub mod in_memory_tag_repository;
+#[cfg(feature = "memory")]
 pub mod in_memory_todo_repository;
+#[cfg(feature = "memory")]
+pub mod in_memory_unit_of_work;
+#[cfg(feature = "memory")]
 pub mod in_memory_user_repository;
+pub mod list_repository;
+#[cfg(test)]
+pub mod repository_tests;
+pub mod session_store;
+#[cfg(feature = "postgres")]
+pub mod sql_audit_repository;
+#[cfg(feature = "postgres")]
+pub mod sql_checklist_repository;
+#[cfg(feature = "postgres")]
+pub mod sql_health_repository;
+#[cfg(feature = "postgres")]
+pub mod sql_list_repository;
+#[cfg(feature = "postgres")]
+pub mod sql_session_store;
+#[cfg(feature = "postgres")]
+pub mod sql_tag_repository;
+#[cfg(feature = "postgres")]
 pub mod sql_todo_repository;
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub mod sql_transaction;
+#[cfg(feature = "postgres")]
+pub mod sql_unit_of_work;
+#[cfg(feature = "postgres")]
 pub mod sql_user_repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_audit_repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_checklist_repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_health_repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_list_repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_session_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_tag_repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_todo_repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_unit_of_work;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_user_repository;
 pub mod sqlx_error_mapper;
+pub mod tag_repository;
 pub mod todo_repository;
+pub mod unit_of_work;
 pub mod user_repository;
 
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+use sqlx::migrate::Migrator;
 use thiserror::Error;
 
+/// Migrations of the postgres database, applied at startup
+#[cfg(feature = "postgres")]
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+/// Migrations of the SQLite database, applied at startup
+#[cfg(feature = "sqlite")]
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
 #[derive(Error, Debug)]
 pub enum RepositoryError {
     #[error("already exists")]
@@ -0,0 +1,995 @@
+//! Tests of the repository traits, shared by the backends so they keep behaving the same. They
+//! run against the in memory backend and SQLite in memory, and against postgres when
+//! `DATABASE_URL` names a postgres server, as each test creates a database of its own on it.
+//! Tests of the services build on the same backends.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde_json::json;
+
+use crate::utils::todo_search::TodoSearch;
+
+use super::{
+    audit_repository::{AuditEventFilter, AuditEventType, AuditRepository, NewAuditEvent},
+    checklist_repository::ChecklistRepository,
+    list_repository::{ListEntity, ListRepository},
+    tag_repository::TagRepository,
+    todo_repository::{
+        ChecklistProgress, MoveDirection, TodoDetails, TodoEntity, TodoFilter, TodoPriority,
+        TodoRepository, TodoSort, TodoView,
+    },
+    unit_of_work::{RepositoryTransaction, UnitOfWork},
+    user_repository::{UserRepository, UserRole},
+    RepositoryError,
+};
+
+/// Runs each test against every backend compiled in
+macro_rules! repository_tests {
+    ($($test:ident),* $(,)?) => {
+        #[cfg(feature = "memory")]
+        mod in_memory {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    super::$test(super::in_memory()).await;
+                }
+            )*
+        }
+
+        #[cfg(feature = "sqlite")]
+        mod sqlite {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    super::$test(super::sqlite().await).await;
+                }
+            )*
+        }
+
+        #[cfg(feature = "postgres")]
+        mod postgres {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    // passes without a server to run against
+                    if let Some(database) = super::PostgresTestDatabase::create().await {
+                        super::$test(database.repositories()).await;
+                        database.drop().await;
+                    }
+                }
+            )*
+        }
+    };
+}
+
+repository_tests!(
+    users,
+    audit,
+    todo_order,
+    due_dates,
+    lists,
+    tags,
+    checklists,
+    recurrence,
+    search,
+    rollback_leaves_no_trace,
+    dropped_transaction_leaves_no_trace,
+    commit_keeps_changes,
+    concurrent_completions
+);
+
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub todos: Arc<dyn TodoRepository>,
+    pub lists: Arc<dyn ListRepository>,
+    pub tags: Arc<dyn TagRepository>,
+    pub checklists: Arc<dyn ChecklistRepository>,
+    pub audit: Arc<dyn AuditRepository>,
+    pub unit_of_work: Arc<dyn UnitOfWork>,
+}
+
+#[cfg(feature = "memory")]
+pub fn in_memory() -> Repositories {
+    use super::{
+        in_memory_audit_repository::InMemoryAuditRepository,
+        in_memory_checklist_repository::InMemoryChecklistRepository,
+        in_memory_list_repository::InMemoryListRepository,
+        in_memory_tag_repository::InMemoryTagRepository,
+        in_memory_todo_repository::InMemoryTodoRepository,
+        in_memory_unit_of_work::{InMemoryUnitOfWork, TransactionLock},
+        in_memory_user_repository::InMemoryUserRepository,
+    };
+
+    let transaction_lock = TransactionLock::default();
+    let users = Arc::new(InMemoryUserRepository::new(transaction_lock.clone()));
+    let lists = Arc::new(InMemoryListRepository::new(transaction_lock.clone()));
+    let todos = Arc::new(InMemoryTodoRepository::new(
+        Arc::clone(&lists),
+        transaction_lock.clone(),
+    ));
+    let tags = Arc::new(InMemoryTagRepository::new(
+        Arc::clone(&todos),
+        transaction_lock.clone(),
+    ));
+    let checklists = Arc::new(InMemoryChecklistRepository::new(
+        Arc::clone(&todos),
+        transaction_lock.clone(),
+    ));
+    let audit = Arc::new(InMemoryAuditRepository::new());
+    let unit_of_work = Arc::new(InMemoryUnitOfWork::new(
+        transaction_lock,
+        Arc::clone(&users),
+        Arc::clone(&todos),
+        Arc::clone(&lists),
+        Arc::clone(&tags),
+        Arc::clone(&checklists),
+        Arc::clone(&audit),
+    ));
+    Repositories {
+        users,
+        todos,
+        lists,
+        tags,
+        checklists,
+        audit,
+        unit_of_work,
+    }
+}
+
+/// A migrated SQLite database in memory. Every connection would open a database of its own, so
+/// the pool keeps a single one open.
+#[cfg(feature = "sqlite")]
+pub async fn sqlite() -> Repositories {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::{
+        sqlite_audit_repository::SqliteAuditRepository,
+        sqlite_checklist_repository::SqliteChecklistRepository,
+        sqlite_list_repository::SqliteListRepository, sqlite_tag_repository::SqliteTagRepository,
+        sqlite_todo_repository::SqliteTodoRepository, sqlite_unit_of_work::SqliteUnitOfWork,
+        sqlite_user_repository::SqliteUserRepository, SQLITE_MIGRATOR,
+    };
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    SQLITE_MIGRATOR.run(&pool).await.unwrap();
+    Repositories {
+        users: Arc::new(SqliteUserRepository::new(pool.clone())),
+        todos: Arc::new(SqliteTodoRepository::new(pool.clone())),
+        lists: Arc::new(SqliteListRepository::new(pool.clone())),
+        tags: Arc::new(SqliteTagRepository::new(pool.clone())),
+        checklists: Arc::new(SqliteChecklistRepository::new(pool.clone())),
+        audit: Arc::new(SqliteAuditRepository::new(pool.clone())),
+        unit_of_work: Arc::new(SqliteUnitOfWork::new(pool, None)),
+    }
+}
+
+/// A migrated database of its own on the postgres server of `DATABASE_URL`, dropped once the
+/// test has passed. Failed tests leave it behind to be looked into.
+#[cfg(feature = "postgres")]
+pub struct PostgresTestDatabase {
+    url: String,
+    name: String,
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "postgres")]
+impl PostgresTestDatabase {
+    /// `None` unless `DATABASE_URL` is a postgres URL
+    pub async fn create() -> Option<Self> {
+        use std::str::FromStr;
+
+        use sqlx::{postgres::PgConnectOptions, Connection, Executor, PgConnection, PgPool};
+
+        let url = std::env::var("DATABASE_URL")
+            .ok()
+            .filter(|url| url.starts_with("postgres:") || url.starts_with("postgresql:"))?;
+        let name = format!("test_{}", crate::utils::random_id().replace('-', ""));
+
+        let mut connection = PgConnection::connect(&url).await.unwrap();
+        connection
+            .execute(format!(r#"CREATE DATABASE "{name}""#).as_str())
+            .await
+            .unwrap();
+        connection.close().await.unwrap();
+
+        let options = PgConnectOptions::from_str(&url).unwrap().database(&name);
+        let pool = PgPool::connect_with(options).await.unwrap();
+        super::MIGRATOR.run(&pool).await.unwrap();
+        Some(Self { url, name, pool })
+    }
+
+    pub fn repositories(&self) -> Repositories {
+        use super::{
+            sql_audit_repository::SqlAuditRepository,
+            sql_checklist_repository::SqlChecklistRepository,
+            sql_list_repository::SqlListRepository, sql_tag_repository::SqlTagRepository,
+            sql_todo_repository::SqlTodoRepository, sql_unit_of_work::SqlUnitOfWork,
+            sql_user_repository::SqlUserRepository,
+        };
+
+        let pool = &self.pool;
+        Repositories {
+            users: Arc::new(SqlUserRepository::new(pool.clone())),
+            todos: Arc::new(SqlTodoRepository::new(pool.clone())),
+            lists: Arc::new(SqlListRepository::new(pool.clone())),
+            tags: Arc::new(SqlTagRepository::new(pool.clone())),
+            checklists: Arc::new(SqlChecklistRepository::new(pool.clone())),
+            audit: Arc::new(SqlAuditRepository::new(pool.clone())),
+            unit_of_work: Arc::new(SqlUnitOfWork::new(pool.clone(), None)),
+        }
+    }
+
+    pub async fn drop(self) {
+        use sqlx::{Connection, Executor, PgConnection};
+
+        self.pool.close().await;
+        let mut connection = PgConnection::connect(&self.url).await.unwrap();
+        // connections of dropped transactions may still be closing
+        connection
+            .execute(format!(r#"DROP DATABASE "{}" WITH (FORCE)"#, self.name).as_str())
+            .await
+            .unwrap();
+    }
+}
+
+/// What the tests take as the current time, a Thursday
+pub fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2030, 1, 10, 12, 0, 0).unwrap()
+}
+
+pub fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn due(due_date: NaiveDate, priority: TodoPriority) -> TodoDetails {
+    TodoDetails {
+        due_date: Some(due_date),
+        priority,
+        ..Default::default()
+    }
+}
+
+fn filter(list_id: Option<&str>, view: TodoView, sort: TodoSort) -> TodoFilter {
+    TodoFilter {
+        list_id: list_id.map(str::to_owned),
+        view,
+        sort,
+        tags: vec![],
+        now: now(),
+    }
+}
+
+async fn list_names(repos: &Repositories, user_id: &str, filter: &TodoFilter) -> Vec<String> {
+    let todos = repos.todos.list_todos(user_id, filter).await.unwrap();
+    todos.into_iter().map(|todo| todo.name).collect()
+}
+
+async fn inbox(repos: &Repositories, user_id: &str) -> Vec<String> {
+    list_names(
+        repos,
+        user_id,
+        &filter(None, TodoView::All, TodoSort::Position),
+    )
+    .await
+}
+
+pub async fn add(repos: &Repositories, user_id: &str, name: &str) -> String {
+    repos
+        .todos
+        .add_todo(user_id, None, name, &Default::default())
+        .await
+        .unwrap()
+}
+
+async fn get(repos: &Repositories, user_id: &str, id: &str) -> TodoEntity {
+    repos.todos.get_todo(user_id, id).await.unwrap().unwrap()
+}
+
+async fn users(repos: Repositories) {
+    let bob = repos.users.create_user("bob", "hash-b").await.unwrap();
+    let ann = repos.users.create_user("ann", "hash-a").await.unwrap();
+
+    let user = repos.users.get_user_by_id(&bob).await.unwrap().unwrap();
+    assert_eq!(
+        (user.username.as_str(), user.pw_hash.as_str(), user.role),
+        ("bob", "hash-b", UserRole::User)
+    );
+    let user = repos.users.get_user_by_username("ann").await.unwrap();
+    assert_eq!(user.map(|user| user.id), Some(ann.clone()));
+    assert!(repos
+        .users
+        .get_user_by_username("cat")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(repos.users.get_user_by_id("nope").await.unwrap().is_none());
+
+    let users = repos.users.list_users().await.unwrap();
+    let usernames: Vec<_> = users.iter().map(|user| user.username.as_str()).collect();
+    assert_eq!(usernames, ["ann", "bob"]);
+
+    repos
+        .users
+        .update_password_hash(&bob, "hash-b2")
+        .await
+        .unwrap();
+    repos
+        .users
+        .update_user_role(&bob, UserRole::Admin)
+        .await
+        .unwrap();
+    let user = repos.users.get_user_by_id(&bob).await.unwrap().unwrap();
+    assert_eq!(user.pw_hash, "hash-b2");
+    assert!(user.is_admin());
+    assert_eq!(repos.users.count_users().await.unwrap(), 2);
+
+    repos.users.delete_user(&bob).await.unwrap();
+    assert!(repos.users.get_user_by_id(&bob).await.unwrap().is_none());
+    assert_eq!(repos.users.count_users().await.unwrap(), 1);
+}
+
+async fn todo_order(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    let other = repos.users.create_user("hank", "hash").await.unwrap();
+    let todos = &repos.todos;
+    let a = add(&repos, &user, "a").await;
+    let b = add(&repos, &user, "b").await;
+    add(&repos, &user, "c").await;
+    let d = add(&repos, &user, "d").await;
+    add(&repos, &other, "x").await;
+    assert_eq!(inbox(&repos, &user).await, ["a", "b", "c", "d"]);
+
+    todos.move_todo(&user, &b, MoveDirection::Up).await.unwrap();
+    // moving past either end changes nothing
+    todos.move_todo(&user, &b, MoveDirection::Up).await.unwrap();
+    todos
+        .move_todo(&user, &d, MoveDirection::Down)
+        .await
+        .unwrap();
+    assert_eq!(inbox(&repos, &user).await, ["b", "a", "c", "d"]);
+
+    assert!(todos.set_todo_complete(&user, &d, true).await.unwrap());
+    let todo = get(&repos, &user, &d).await;
+    assert!(todo.completed_at.is_some());
+    assert!(todo.updated_at >= todo.created_at);
+    // completing it again changes nothing
+    assert!(!todos.set_todo_complete(&user, &d, true).await.unwrap());
+    let completed_again = get(&repos, &user, &d).await;
+    assert!(completed_again.is_complete);
+    assert_eq!(completed_again.completed_at, todo.completed_at);
+    assert_eq!(completed_again.updated_at, todo.updated_at);
+    assert!(matches!(
+        todos.set_todo_complete(&other, &d, true).await,
+        Err(RepositoryError::ItemNotFound)
+    ));
+    assert!(todos.set_todo_complete(&user, &d, false).await.unwrap());
+    let todo = get(&repos, &user, &d).await;
+    assert!(!todo.is_complete);
+    assert!(todo.completed_at.is_none());
+
+    // other users' todos can't be seen or changed
+    assert!(todos.get_todo(&other, &a).await.unwrap().is_none());
+    assert_eq!(inbox(&repos, &other).await, ["x"]);
+    let _ = todos.remove_todo(&other, &a).await;
+    let _ = todos.move_todo(&other, &a, MoveDirection::Up).await;
+    assert_eq!(inbox(&repos, &user).await, ["b", "a", "c", "d"]);
+
+    todos.remove_todo(&user, &a).await.unwrap();
+    assert!(todos.get_todo(&user, &a).await.unwrap().is_none());
+    assert_eq!(inbox(&repos, &user).await, ["b", "c", "d"]);
+    assert_eq!(todos.count_todos().await.unwrap(), 4);
+}
+
+async fn due_dates(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    let todos = &repos.todos;
+    let a = todos
+        .add_todo(&user, None, "a", &due(date(2030, 1, 12), TodoPriority::Low))
+        .await
+        .unwrap();
+    let b_details = TodoDetails {
+        priority: TodoPriority::High,
+        ..Default::default()
+    };
+    todos.add_todo(&user, None, "b", &b_details).await.unwrap();
+    let c_details = TodoDetails {
+        due_time: NaiveTime::from_hms_opt(9, 0, 0),
+        ..due(date(2030, 1, 10), TodoPriority::Normal)
+    };
+    todos.add_todo(&user, None, "c", &c_details).await.unwrap();
+    let d = todos
+        .add_todo(
+            &user,
+            None,
+            "d",
+            &due(date(2030, 1, 8), TodoPriority::Normal),
+        )
+        .await
+        .unwrap();
+
+    let view = |view| filter(None, view, TodoSort::Position);
+    let sort = |sort| filter(None, TodoView::All, sort);
+    assert_eq!(
+        list_names(&repos, &user, &sort(TodoSort::DueDate)).await,
+        ["d", "c", "a", "b"]
+    );
+    assert_eq!(
+        list_names(&repos, &user, &sort(TodoSort::Priority)).await,
+        ["b", "d", "c", "a"]
+    );
+    assert_eq!(
+        list_names(&repos, &user, &view(TodoView::Today)).await,
+        ["c"]
+    );
+    assert_eq!(
+        list_names(&repos, &user, &view(TodoView::Upcoming)).await,
+        ["a"]
+    );
+    assert_eq!(
+        list_names(&repos, &user, &view(TodoView::Overdue)).await,
+        ["c", "d"]
+    );
+    assert_eq!(
+        list_names(&repos, &user, &view(TodoView::NoDate)).await,
+        ["b"]
+    );
+
+    // completed todos aren't overdue
+    todos.set_todo_complete(&user, &d, true).await.unwrap();
+    assert_eq!(
+        list_names(&repos, &user, &view(TodoView::Overdue)).await,
+        ["c"]
+    );
+
+    let details = due(date(2030, 1, 14), TodoPriority::High);
+    todos
+        .update_todo_details(&user, &a, &details)
+        .await
+        .unwrap();
+    let todo = get(&repos, &user, &a).await;
+    assert_eq!(
+        (todo.due_date, todo.due_time, todo.priority),
+        (details.due_date, None, TodoPriority::High)
+    );
+}
+
+async fn lists(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    let other = repos.users.create_user("hank", "hash").await.unwrap();
+    let lists = &repos.lists;
+    let work = lists.create_list(&user, "Work").await.unwrap();
+    let home = lists.create_list(&user, "Home").await.unwrap();
+    lists.rename_list(&user, &home, "House").await.unwrap();
+    let names = |lists: Vec<ListEntity>| -> Vec<String> {
+        lists.into_iter().map(|list| list.name).collect()
+    };
+    assert_eq!(
+        names(lists.list_lists(&user).await.unwrap()),
+        ["Work", "House"]
+    );
+    assert!(lists.list_lists(&other).await.unwrap().is_empty());
+    assert!(lists.get_list(&other, &work).await.unwrap().is_none());
+
+    lists.set_list_archived(&user, &work, true).await.unwrap();
+    let archived_at = lists
+        .get_list(&user, &work)
+        .await
+        .unwrap()
+        .unwrap()
+        .archived_at;
+    assert!(archived_at.is_some());
+    lists.set_list_archived(&user, &work, true).await.unwrap();
+    let list = lists.get_list(&user, &work).await.unwrap().unwrap();
+    assert_eq!(list.archived_at, archived_at);
+    lists.set_list_archived(&user, &work, false).await.unwrap();
+    let list = lists.get_list(&user, &work).await.unwrap().unwrap();
+    assert!(!list.is_archived());
+
+    for name in ["one", "two"] {
+        repos
+            .todos
+            .add_todo(&user, Some(&work), name, &Default::default())
+            .await
+            .unwrap();
+    }
+    let moved = add(&repos, &user, "moved").await;
+    add(&repos, &user, "inbox").await;
+    repos
+        .todos
+        .move_todo_to_list(&user, &moved, Some(&work))
+        .await
+        .unwrap();
+    let in_work = filter(Some(&work), TodoView::All, TodoSort::Position);
+    assert_eq!(
+        list_names(&repos, &user, &in_work).await,
+        ["one", "two", "moved"]
+    );
+    assert_eq!(inbox(&repos, &user).await, ["inbox"]);
+
+    let removed = repos.todos.remove_list_todos(&user, &work).await.unwrap();
+    assert_eq!(removed, 3);
+    lists.delete_list(&user, &work).await.unwrap();
+    assert!(lists.get_list(&user, &work).await.unwrap().is_none());
+    assert_eq!(names(lists.list_lists(&user).await.unwrap()), ["House"]);
+    assert_eq!(inbox(&repos, &user).await, ["inbox"]);
+}
+
+async fn set_tags(repos: &Repositories, user_id: &str, todo_id: &str, names: &[&str]) {
+    let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    repos
+        .tags
+        .set_todo_tags(user_id, todo_id, &names)
+        .await
+        .unwrap();
+}
+
+async fn tag_counts(repos: &Repositories, user_id: &str) -> Vec<(String, i64)> {
+    let tags = repos.tags.list_tags(user_id).await.unwrap();
+    tags.into_iter()
+        .map(|tag| (tag.name, tag.todo_count))
+        .collect()
+}
+
+async fn tag_id(repos: &Repositories, user_id: &str, name: &str) -> String {
+    let tags = repos.tags.list_tags(user_id).await.unwrap();
+    tags.into_iter().find(|tag| tag.name == name).unwrap().id
+}
+
+fn tagged(names: &[&str]) -> TodoFilter {
+    TodoFilter {
+        tags: names.iter().map(|name| name.to_string()).collect(),
+        ..filter(None, TodoView::All, TodoSort::Position)
+    }
+}
+
+fn count(name: &str, todo_count: i64) -> (String, i64) {
+    (name.to_owned(), todo_count)
+}
+
+async fn tags(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    let other = repos.users.create_user("hank", "hash").await.unwrap();
+    let t1 = add(&repos, &user, "t1").await;
+    let t2 = add(&repos, &user, "t2").await;
+    let t3 = add(&repos, &user, "t3").await;
+    set_tags(&repos, &user, &t1, &["work", "urgent"]).await;
+    set_tags(&repos, &user, &t2, &["home", "urgent"]).await;
+    set_tags(&repos, &user, &t3, &["home"]).await;
+
+    assert_eq!(
+        tag_counts(&repos, &user).await,
+        [count("home", 2), count("urgent", 2), count("work", 1)]
+    );
+    assert_eq!(get(&repos, &user, &t1).await.tags, ["urgent", "work"]);
+    assert_eq!(
+        list_names(&repos, &user, &tagged(&["urgent"])).await,
+        ["t1", "t2"]
+    );
+    assert_eq!(
+        list_names(&repos, &user, &tagged(&["home", "urgent"])).await,
+        ["t2"]
+    );
+    assert!(tag_counts(&repos, &other).await.is_empty());
+
+    // tags no todo has anymore are kept
+    set_tags(&repos, &user, &t3, &[]).await;
+    assert!(get(&repos, &user, &t3).await.tags.is_empty());
+    assert_eq!(tag_counts(&repos, &user).await[0], count("home", 1));
+
+    let work = tag_id(&repos, &user, "work").await;
+    assert!(matches!(
+        repos.tags.rename_tag(&user, &work, "home").await,
+        Err(RepositoryError::ItemAlreadyExists)
+    ));
+    repos.tags.rename_tag(&user, &work, "job").await.unwrap();
+    assert_eq!(get(&repos, &user, &t1).await.tags, ["job", "urgent"]);
+
+    let home = tag_id(&repos, &user, "home").await;
+    let urgent = tag_id(&repos, &user, "urgent").await;
+    repos.tags.merge_tags(&user, &urgent, &home).await.unwrap();
+    assert_eq!(get(&repos, &user, &t1).await.tags, ["home", "job"]);
+    assert_eq!(get(&repos, &user, &t2).await.tags, ["home"]);
+    assert_eq!(
+        tag_counts(&repos, &user).await,
+        [count("home", 2), count("job", 1)]
+    );
+
+    repos.tags.delete_tag(&user, &home).await.unwrap();
+    assert_eq!(get(&repos, &user, &t1).await.tags, ["job"]);
+    assert!(get(&repos, &user, &t2).await.tags.is_empty());
+    assert_eq!(tag_counts(&repos, &user).await, [count("job", 1)]);
+}
+
+async fn checklists(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    let other = repos.users.create_user("hank", "hash").await.unwrap();
+    let checklists = &repos.checklists;
+    let todo = add(&repos, &user, "todo").await;
+    let other_todo = add(&repos, &other, "other").await;
+    let mut ids = vec![];
+    for name in ["one", "two", "three"] {
+        ids.push(checklists.add_item(&user, &todo, name).await.unwrap());
+    }
+    checklists
+        .add_item(&other, &other_todo, "theirs")
+        .await
+        .unwrap();
+    let items = || async {
+        let items = checklists
+            .list_items(&user, &[todo.clone(), other_todo.clone()])
+            .await
+            .unwrap();
+        items
+            .into_iter()
+            .map(|item| (item.name, item.is_complete))
+            .collect::<Vec<_>>()
+    };
+    let item = |name: &str, is_complete| (name.to_owned(), is_complete);
+
+    assert_eq!(
+        items().await,
+        [item("one", false), item("two", false), item("three", false)]
+    );
+
+    checklists
+        .set_item_complete(&user, &todo, &ids[1], true)
+        .await
+        .unwrap();
+    assert_eq!(
+        get(&repos, &user, &todo).await.checklist,
+        ChecklistProgress { done: 1, total: 3 }
+    );
+
+    checklists
+        .move_item(&user, &todo, &ids[2], MoveDirection::Up)
+        .await
+        .unwrap();
+    checklists
+        .move_item(&user, &todo, &ids[0], MoveDirection::Up)
+        .await
+        .unwrap();
+    assert_eq!(
+        items().await,
+        [item("one", false), item("three", false), item("two", true)]
+    );
+
+    checklists.remove_item(&user, &todo, &ids[0]).await.unwrap();
+    assert_eq!(items().await, [item("three", false), item("two", true)]);
+
+    // items are only changed through a todo of their user
+    assert!(matches!(
+        checklists.add_item(&other, &todo, "nope").await,
+        Err(RepositoryError::ItemNotFound)
+    ));
+    assert!(matches!(
+        checklists
+            .set_item_complete(&user, &other_todo, &ids[1], false)
+            .await,
+        Err(RepositoryError::ItemNotFound)
+    ));
+    assert!(matches!(
+        checklists.remove_item(&other, &todo, &ids[1]).await,
+        Err(RepositoryError::ItemNotFound)
+    ));
+    assert_eq!(items().await, [item("three", false), item("two", true)]);
+}
+
+async fn recurrence(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    let todos = &repos.todos;
+    let details = TodoDetails {
+        recurrence: Some("FREQ=WEEKLY;BYDAY=MO,TH".parse().unwrap()),
+        ..due(date(2030, 1, 14), TodoPriority::Normal)
+    };
+    let todo = todos
+        .add_todo(&user, None, "standup", &details)
+        .await
+        .unwrap();
+    assert_eq!(
+        get(&repos, &user, &todo).await.recurrence,
+        details.recurrence
+    );
+
+    let details = TodoDetails {
+        recurrence: Some("FREQ=MONTHLY;INTERVAL=3;BYMONTHDAY=14".parse().unwrap()),
+        ..details
+    };
+    todos
+        .update_todo_details(&user, &todo, &details)
+        .await
+        .unwrap();
+    assert_eq!(
+        get(&repos, &user, &todo).await.recurrence,
+        details.recurrence
+    );
+
+    todos
+        .update_todo_details(&user, &todo, &Default::default())
+        .await
+        .unwrap();
+    assert!(get(&repos, &user, &todo).await.recurrence.is_none());
+}
+
+async fn search(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    let other = repos.users.create_user("hank", "hash").await.unwrap();
+    let todos = &repos.todos;
+    let groceries = repos.lists.create_list(&user, "Groceries").await.unwrap();
+    let archived = repos.lists.create_list(&user, "Old").await.unwrap();
+    let buy_milk = todos
+        .add_todo(
+            &user,
+            None,
+            "Buy milk",
+            &due(date(2030, 1, 12), TodoPriority::Normal),
+        )
+        .await
+        .unwrap();
+    add(&repos, &user, "Milkshake recipe").await;
+    let oat_milk = todos
+        .add_todo(
+            &user,
+            Some(&groceries),
+            "Oat milk & bread",
+            &due(date(2030, 1, 10), TodoPriority::Normal),
+        )
+        .await
+        .unwrap();
+    todos
+        .add_todo(&user, Some(&archived), "milk the cow", &Default::default())
+        .await
+        .unwrap();
+    let taxes = todos
+        .add_todo(
+            &user,
+            None,
+            "Taxes 2030",
+            &due(date(2030, 12, 31), TodoPriority::Normal),
+        )
+        .await
+        .unwrap();
+    add(&repos, &other, "milk").await;
+    repos
+        .tags
+        .set_todo_tags(&user, &buy_milk, &["home".to_owned()])
+        .await
+        .unwrap();
+    repos
+        .tags
+        .set_todo_tags(&user, &taxes, &["work".to_owned()])
+        .await
+        .unwrap();
+    repos
+        .lists
+        .set_list_archived(&user, &archived, true)
+        .await
+        .unwrap();
+    todos
+        .set_todo_complete(&user, &buy_milk, true)
+        .await
+        .unwrap();
+
+    // backends rank matches differently, so only which todos match is compared
+    let search = |query: &'static str| {
+        let todos = Arc::clone(todos);
+        let user = user.clone();
+        async move {
+            let search = TodoSearch::parse(query, now().date_naive()).unwrap();
+            let found = todos.search_todos(&user, &search).await.unwrap();
+            let mut names: Vec<_> = found.into_iter().map(|todo| todo.name).collect();
+            names.sort();
+            names
+        }
+    };
+
+    let milk = ["Buy milk", "Milkshake recipe", "Oat milk & bread"];
+    assert_eq!(search("milk").await, milk);
+    assert_eq!(search("MIL").await, milk);
+    assert_eq!(search("oat mil").await, ["Oat milk & bread"]);
+    assert_eq!(
+        search("milk is:open").await,
+        ["Milkshake recipe", "Oat milk & bread"]
+    );
+    assert_eq!(search("is:done").await, ["Buy milk"]);
+    assert_eq!(search("tag:home").await, ["Buy milk"]);
+    assert_eq!(search("due:<2030-01-11").await, ["Oat milk & bread"]);
+    assert_eq!(search("due:>=2030-01-12").await, ["Buy milk", "Taxes 2030"]);
+    assert_eq!(search("due:today").await, ["Oat milk & bread"]);
+    assert_eq!(search("2030").await, ["Taxes 2030"]);
+    assert!(search("cow").await.is_empty());
+    assert!(search("zzz").await.is_empty());
+
+    todos.remove_todo(&user, &oat_milk).await.unwrap();
+    assert!(search("oat").await.is_empty());
+
+    repos
+        .lists
+        .set_list_archived(&user, &archived, false)
+        .await
+        .unwrap();
+    assert_eq!(search("cow").await, ["milk the cow"]);
+}
+
+async fn audit_events(
+    repos: &Repositories,
+    filter: &AuditEventFilter,
+) -> Vec<(String, AuditEventType)> {
+    let events = repos.audit.list_events(filter).await.unwrap();
+    events
+        .into_iter()
+        .map(|event| (event.actor_id.unwrap(), event.event_type))
+        .collect()
+}
+
+async fn audit(repos: Repositories) {
+    for (actor_id, event_type) in [
+        ("gina", AuditEventType::Registration),
+        ("gina", AuditEventType::Login),
+        ("hank", AuditEventType::Login),
+    ] {
+        let event = NewAuditEvent {
+            actor_id: Some(actor_id.to_owned()),
+            event_type,
+            ip: Some("127.0.0.1".to_owned()),
+            user_agent: None,
+            payload: json!({}),
+        };
+        repos.audit.append_event(event).await.unwrap();
+        // events are ordered by when they were appended
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+    }
+    let event = |actor_id: &str, event_type| (actor_id.to_owned(), event_type);
+
+    assert_eq!(
+        audit_events(&repos, &Default::default()).await,
+        [
+            event("hank", AuditEventType::Login),
+            event("gina", AuditEventType::Login),
+            event("gina", AuditEventType::Registration),
+        ]
+    );
+    let gina = AuditEventFilter {
+        actor_id: Some("gina".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(
+        audit_events(&repos, &gina).await,
+        [
+            event("gina", AuditEventType::Login),
+            event("gina", AuditEventType::Registration),
+        ]
+    );
+    let last_login = AuditEventFilter {
+        event_type: Some(AuditEventType::Login),
+        limit: 1,
+        ..Default::default()
+    };
+    assert_eq!(
+        audit_events(&repos, &last_login).await,
+        [event("hank", AuditEventType::Login)]
+    );
+}
+
+/// What a transaction could change, to check it's the same after rolling back
+async fn stored(repos: &Repositories, user_id: &str) -> (Vec<String>, Vec<String>, usize) {
+    let users = repos.users.list_users().await.unwrap();
+    let events = repos.audit.list_events(&Default::default()).await.unwrap();
+    (
+        users.into_iter().map(|user| user.username).collect(),
+        inbox(repos, user_id).await,
+        events.len(),
+    )
+}
+
+/// Writes a user, a todo and an audit event in the transaction
+async fn write_in_transaction(tx: &dyn RepositoryTransaction, user_id: &str) {
+    let new_user = tx.users().create_user("hank", "hash").await.unwrap();
+    tx.todos()
+        .add_todo(user_id, None, "uncommitted", &Default::default())
+        .await
+        .unwrap();
+    tx.todos()
+        .add_todo(&new_user, None, "theirs", &Default::default())
+        .await
+        .unwrap();
+    tx.audit()
+        .append_event(NewAuditEvent {
+            actor_id: Some(new_user),
+            event_type: AuditEventType::Registration,
+            ip: None,
+            user_agent: None,
+            payload: json!({}),
+        })
+        .await
+        .unwrap();
+}
+
+async fn rollback_leaves_no_trace(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    add(&repos, &user, "committed").await;
+    let before = stored(&repos, &user).await;
+
+    let tx = repos.unit_of_work.begin().await.unwrap();
+    write_in_transaction(tx.as_ref(), &user).await;
+    tx.rollback().await.unwrap();
+
+    assert_eq!(stored(&repos, &user).await, before);
+    assert_eq!(repos.todos.count_todos().await.unwrap(), 1);
+}
+
+async fn dropped_transaction_leaves_no_trace(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    add(&repos, &user, "committed").await;
+    let before = stored(&repos, &user).await;
+
+    // as when a service returns early with `?` before committing
+    let failed: Result<(), RepositoryError> = async {
+        let tx = repos.unit_of_work.begin().await?;
+        write_in_transaction(tx.as_ref(), &user).await;
+        Err(RepositoryError::ItemNotFound)?;
+        tx.commit().await
+    }
+    .await;
+    assert!(failed.is_err());
+
+    assert_eq!(stored(&repos, &user).await, before);
+    assert_eq!(repos.todos.count_todos().await.unwrap(), 1);
+}
+
+async fn commit_keeps_changes(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+
+    let tx = repos.unit_of_work.begin().await.unwrap();
+    write_in_transaction(tx.as_ref(), &user).await;
+    tx.commit().await.unwrap();
+
+    assert_eq!(
+        stored(&repos, &user).await,
+        (
+            vec!["gina".to_owned(), "hank".to_owned()],
+            vec!["uncommitted".to_owned()],
+            1
+        )
+    );
+    assert_eq!(repos.todos.count_todos().await.unwrap(), 2);
+}
+
+/// Of two transactions completing a todo at once, only the first sees it change
+async fn concurrent_completions(repos: Repositories) {
+    let user = repos.users.create_user("gina", "hash").await.unwrap();
+    let id = add(&repos, &user, "a").await;
+
+    let tx = repos.unit_of_work.begin().await.unwrap();
+    assert!(tx
+        .todos()
+        .set_todo_complete(&user, &id, true)
+        .await
+        .unwrap());
+    let concurrent = tokio::spawn({
+        let unit_of_work = Arc::clone(&repos.unit_of_work);
+        let (user, id) = (user.clone(), id.clone());
+        async move {
+            let tx = unit_of_work.begin().await.unwrap();
+            let changed = tx
+                .todos()
+                .set_todo_complete(&user, &id, true)
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+            changed
+        }
+    });
+    // the other transaction waits for this one to commit
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    tx.commit().await.unwrap();
+
+    assert!(!concurrent.await.unwrap());
+    assert!(get(&repos, &user, &id).await.is_complete);
+}
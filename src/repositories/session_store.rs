@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::RepositoryResult;
+
+/// A session to be stored. The session token itself is never stored, only its hash.
+#[derive(Debug, Clone)]
+pub struct NewSession {
+    pub token_hash: String,
+    pub user_id: String,
+    pub device: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionEntity {
+    pub id: String,
+    pub user_id: String,
+    pub device: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn create_session(&self, session: NewSession) -> RepositoryResult<SessionEntity>;
+    async fn get_session_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> RepositoryResult<Option<SessionEntity>>;
+    async fn touch_session(&self, id: &str, last_seen_at: DateTime<Utc>) -> RepositoryResult<()>;
+    /// Sessions of the user, most recently seen first.
+    async fn list_user_sessions(&self, user_id: &str) -> RepositoryResult<Vec<SessionEntity>>;
+    async fn delete_session(&self, user_id: &str, id: &str) -> RepositoryResult<()>;
+    /// Removes sessions last seen before `cutoff`, returning how many were removed.
+    async fn delete_sessions_idle_since(&self, cutoff: DateTime<Utc>) -> RepositoryResult<u64>;
+}
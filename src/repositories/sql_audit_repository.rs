@@ -0,0 +1,116 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+use tracing::instrument;
+
+use crate::utils::random_id;
+
+use super::{
+    audit_repository::{
+        AuditEventEntity, AuditEventFilter, AuditEventType, AuditRepository, NewAuditEvent,
+    },
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    RepositoryError, RepositoryResult,
+};
+
+pub struct SqlAuditRepository {
+    executor: SqlExecutor<Postgres>,
+}
+
+impl SqlAuditRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Postgres>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AuditEventRow {
+    pub id: String,
+    pub actor_id: Option<String>,
+    pub event_type: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub payload: serde_json::Value,
+}
+
+#[async_trait]
+impl AuditRepository for SqlAuditRepository {
+    #[instrument(skip_all)]
+    async fn append_event(&self, event: NewAuditEvent) -> RepositoryResult<String> {
+        let id = random_id();
+
+        let query = sqlx::query!(
+            "INSERT INTO AuditEvents (id, actor_id, event_type, ip, user_agent, created_at, payload) 
+            VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            id,
+            event.actor_id,
+            event.event_type.as_str(),
+            event.ip,
+            event.user_agent,
+            Utc::now(),
+            event.payload
+        );
+
+        query.execute(&mut *self.executor.acquire().await?).await?;
+
+        Ok(id)
+    }
+
+    #[instrument(skip_all)]
+    async fn list_events(
+        &self,
+        filter: &AuditEventFilter,
+    ) -> RepositoryResult<Vec<AuditEventEntity>> {
+        let query = sqlx::query_as!(
+            AuditEventRow,
+            "SELECT * FROM AuditEvents 
+            WHERE ($1::varchar IS NULL OR actor_id = $1) 
+            AND ($2::varchar IS NULL OR event_type = $2) 
+            AND ($3::timestamptz IS NULL OR created_at >= $3) 
+            AND ($4::timestamptz IS NULL OR created_at < $4) 
+            ORDER BY created_at DESC 
+            LIMIT $5",
+            filter.actor_id,
+            filter.event_type.map(|t| t.as_str()),
+            filter.since,
+            filter.until,
+            filter.limit
+        );
+
+        let events = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        events.into_iter().map(TryFrom::try_from).collect()
+    }
+}
+
+impl TryFrom<AuditEventRow> for AuditEventEntity {
+    type Error = RepositoryError;
+
+    fn try_from(value: AuditEventRow) -> Result<Self, Self::Error> {
+        let event_type = value
+            .event_type
+            .parse::<AuditEventType>()
+            .map_err(|e| RepositoryError::UnknownError { info: Some(e) })?;
+
+        Ok(AuditEventEntity {
+            id: value.id,
+            actor_id: value.actor_id,
+            event_type,
+            ip: value.ip,
+            user_agent: value.user_agent,
+            created_at: value.created_at,
+            payload: value.payload,
+        })
+    }
+}
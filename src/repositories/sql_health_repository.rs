@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use sqlx::{migrate::Migrate, Pool, Postgres};
+use tracing::instrument;
+
+use super::{
+    health_repository::{migration_states, HealthRepository, MigrationEntity},
+    RepositoryError, RepositoryResult, MIGRATOR,
+};
+
+pub struct SqlHealthRepository {
+    pool: Pool<Postgres>,
+}
+
+impl SqlHealthRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl HealthRepository for SqlHealthRepository {
+    #[instrument(skip_all)]
+    async fn ping(&self) -> RepositoryResult<()> {
+        sqlx::query!("SELECT 1 AS one")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn list_migrations(&self) -> RepositoryResult<Vec<MigrationEntity>> {
+        // sqlx only creates its bookkeeping table on the first migration run
+        let is_migrated = sqlx::query_scalar!(
+            r#"SELECT to_regclass('_sqlx_migrations') IS NOT NULL AS "is_migrated!""#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let applied = if is_migrated {
+            let mut conn = self.pool.acquire().await?;
+            conn.list_applied_migrations()
+                .await
+                .map_err(|e| RepositoryError::UnknownError {
+                    info: Some(e.to_string()),
+                })?
+        } else {
+            vec![]
+        };
+
+        Ok(migration_states(&MIGRATOR, applied))
+    }
+}
@@ -0,0 +1,206 @@
+use async_trait::async_trait;
+use sqlx::{Pool, Postgres};
+use tracing::instrument;
+
+use crate::utils::random_id;
+
+use super::{
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    tag_repository::{TagEntity, TagRepository},
+    RepositoryError, RepositoryResult,
+};
+
+pub struct SqlTagRepository {
+    executor: SqlExecutor<Postgres>,
+}
+
+impl SqlTagRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Postgres>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TagRow {
+    pub id: String,
+    pub name: String,
+    pub todo_count: i64,
+}
+
+#[async_trait]
+impl TagRepository for SqlTagRepository {
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_todo_tags(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        names: &[String],
+    ) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM Todos WHERE id=$1 AND user_id=$2) AS "exists!""#,
+            todo_id,
+            user_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if !exists {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        for name in names {
+            sqlx::query!(
+                "INSERT INTO Tags (id, user_id, name) VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, name) DO NOTHING",
+                random_id(),
+                user_id,
+                name
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        sqlx::query!("DELETE FROM TodoTags WHERE todo_id=$1", todo_id)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query!(
+            "INSERT INTO TodoTags (todo_id, tag_id)
+            SELECT $1, id FROM Tags WHERE user_id=$2 AND name=ANY($3)",
+            todo_id,
+            user_id,
+            names
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn list_tags(&self, user_id: &str) -> RepositoryResult<Vec<TagEntity>> {
+        let query = sqlx::query_as!(
+            TagRow,
+            r#"SELECT Tags.id, Tags.name, COUNT(TodoTags.todo_id) AS "todo_count!"
+            FROM Tags LEFT JOIN TodoTags ON TodoTags.tag_id=Tags.id
+            WHERE Tags.user_id=$1
+            GROUP BY Tags.id, Tags.name
+            ORDER BY Tags.name"#,
+            user_id
+        );
+
+        let tags = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(tags.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn rename_tag(&self, user_id: &str, id: &str, name: &str) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        let taken = sqlx::query_scalar!(
+            r#"SELECT EXISTS (
+                SELECT 1 FROM Tags WHERE user_id=$1 AND name=$2 AND id<>$3
+            ) AS "taken!""#,
+            user_id,
+            name,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if taken {
+            return Err(RepositoryError::ItemAlreadyExists);
+        }
+
+        let result = sqlx::query!(
+            "UPDATE Tags SET name=$3 WHERE id=$1 AND user_id=$2",
+            id,
+            user_id,
+            name
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn merge_tags(
+        &self,
+        user_id: &str,
+        source_id: &str,
+        target_id: &str,
+    ) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        let found = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM Tags WHERE user_id=$1 AND id IN ($2, $3)"#,
+            user_id,
+            source_id,
+            target_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if found != 2 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        sqlx::query!(
+            "INSERT INTO TodoTags (todo_id, tag_id)
+            SELECT todo_id, $2 FROM TodoTags WHERE tag_id=$1
+            ON CONFLICT DO NOTHING",
+            source_id,
+            target_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM Tags WHERE id=$1 AND user_id=$2",
+            source_id,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn delete_tag(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query!("DELETE FROM Tags WHERE id=$1 AND user_id=$2", id, user_id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<TagRow> for TagEntity {
+    fn from(value: TagRow) -> Self {
+        TagEntity {
+            id: value.id,
+            name: value.name,
+            todo_count: value.todo_count,
+        }
+    }
+}
@@ -1,61 +1,432 @@
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use sqlx::{Pool, Postgres};
+use tracing::instrument;
 
-use crate::utils::random_id;
+use crate::utils::{random_id, todo_search::TodoSearch};
 
 use super::{
-    todo_repository::{TodoEntity, TodoRepository},
-    RepositoryResult,
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    todo_repository::{
+        ChecklistProgress, MoveDirection, TodoDetails, TodoEntity, TodoFilter, TodoPriority,
+        TodoRepository,
+    },
+    RepositoryError, RepositoryResult,
 };
 
 pub struct SqlTodoRepository {
-    pool: Pool<Postgres>,
+    executor: SqlExecutor<Postgres>,
 }
 
 impl SqlTodoRepository {
     pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Postgres>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
     }
 }
 
 #[derive(Debug)]
 struct TodoRow {
     pub id: String,
-    #[allow(unused)]
-    pub user_id: String,
+    pub list_id: Option<String>,
     pub name: String,
     pub complete: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub position: i64,
+    pub due_date: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
+    pub priority: i16,
+    pub tags: Vec<String>,
+    pub checklist_done: i64,
+    pub checklist_total: i64,
+    pub auto_complete: bool,
+    pub recurrence: Option<String>,
 }
 
 #[async_trait]
 impl TodoRepository for SqlTodoRepository {
-    async fn add_todo(&self, user_id: &str, name: &str) -> RepositoryResult<String> {
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn add_todo(
+        &self,
+        user_id: &str,
+        list_id: Option<&str>,
+        name: &str,
+        details: &TodoDetails,
+    ) -> RepositoryResult<String> {
         let id = random_id();
 
         let query = sqlx::query!(
-            "INSERT INTO Todos (id, user_id, name, complete) 
-            VALUES ($1, $2, $3, $4)",
+            "INSERT INTO Todos (
+                id, user_id, list_id, name, complete, created_at, updated_at, position,
+                due_date, due_time, priority, recurrence
+            )
+            VALUES ($1, $2, $8, $3, false, $4, $4, (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM Todos
+                WHERE user_id=$2::varchar AND list_id IS NOT DISTINCT FROM $8::varchar
+            ), $5, $6, $7, $9)",
             id,
             user_id,
             name,
-            false
+            Utc::now(),
+            details.due_date,
+            details.due_time,
+            details.priority.as_i16(),
+            list_id,
+            details.recurrence.as_ref().map(ToString::to_string)
         );
 
-        query.execute(&self.pool).await?;
+        query.execute(&mut *self.executor.acquire().await?).await?;
 
         Ok(id)
     }
 
-    async fn list_todos(&self, user_id: &str) -> RepositoryResult<Vec<TodoEntity>> {
-        let query = sqlx::query_as!(TodoRow, "SELECT * FROM Todos WHERE user_id=$1", user_id);
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn list_todos(
+        &self,
+        user_id: &str,
+        filter: &TodoFilter,
+    ) -> RepositoryResult<Vec<TodoEntity>> {
+        // the CASEs of other views and sorts are constant, so they don't filter or order
+        let query = sqlx::query_as!(
+            TodoRow,
+            r#"SELECT id, list_id, name, complete, created_at, updated_at, completed_at, position,
+                due_date, due_time, priority,
+                ARRAY(
+                    SELECT Tags.name FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                    WHERE TodoTags.todo_id=Todos.id
+                    ORDER BY Tags.name
+                ) AS "tags!",
+                (
+                    SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id AND complete
+                ) AS "checklist_done!",
+                (SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id) AS "checklist_total!",
+                auto_complete, recurrence
+            FROM Todos
+            WHERE user_id=$1 AND list_id IS NOT DISTINCT FROM $6 AND (
+                SELECT COUNT(*) FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                WHERE TodoTags.todo_id=Todos.id AND Tags.name=ANY($7)
+            ) = cardinality($7) AND CASE $2
+                WHEN 'today' THEN due_date=$3
+                WHEN 'upcoming' THEN due_date>$3
+                WHEN 'overdue' THEN NOT complete AND (due_date<$3 OR (due_date=$3 AND due_time<$4))
+                WHEN 'no_date' THEN due_date IS NULL
+                ELSE true
+            END
+            ORDER BY
+                CASE WHEN $5='priority' THEN priority END DESC,
+                CASE WHEN $5 IN ('priority', 'due_date') THEN due_date END NULLS LAST,
+                CASE WHEN $5 IN ('priority', 'due_date') THEN due_time END NULLS LAST,
+                position, created_at, id"#,
+            user_id,
+            filter.view.as_str(),
+            filter.now.date_naive(),
+            filter.now.time(),
+            filter.sort.as_str(),
+            filter.list_id.as_deref(),
+            &filter.tags
+        );
+
+        let todos = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(todos.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn search_todos(
+        &self,
+        user_id: &str,
+        search: &TodoSearch,
+    ) -> RepositoryResult<Vec<TodoEntity>> {
+        // words only have letters and digits, so they can't be tsquery syntax
+        let tsquery = search
+            .words
+            .iter()
+            .map(|word| format!("{word}:*"))
+            .collect::<Vec<_>>()
+            .join(" & ");
+
+        let query = sqlx::query_as!(
+            TodoRow,
+            r#"SELECT id, list_id, name, complete, created_at, updated_at, completed_at, position,
+                due_date, due_time, priority,
+                ARRAY(
+                    SELECT Tags.name FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                    WHERE TodoTags.todo_id=Todos.id
+                    ORDER BY Tags.name
+                ) AS "tags!",
+                (
+                    SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id AND complete
+                ) AS "checklist_done!",
+                (SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id) AS "checklist_total!",
+                auto_complete, recurrence
+            FROM Todos
+            WHERE user_id=$1
+                AND (list_id IS NULL OR list_id IN (SELECT id FROM Lists WHERE archived_at IS NULL))
+                AND ($2::text='' OR search @@ to_tsquery('simple', $2::text))
+                AND ($3::boolean IS NULL OR complete=$3)
+                AND (
+                    SELECT COUNT(*) FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                    WHERE TodoTags.todo_id=Todos.id AND Tags.name=ANY($4)
+                ) = cardinality($4)
+                AND ($5::date IS NULL OR due_date>=$5)
+                AND ($6::date IS NULL OR due_date<=$6)
+            ORDER BY
+                CASE WHEN $2::text='' THEN 0 ELSE ts_rank(search, to_tsquery('simple', $2::text)) END DESC,
+                complete, due_date NULLS LAST, created_at, id"#,
+            user_id,
+            tsquery,
+            search.is_complete,
+            &search.tags,
+            search.due_from,
+            search.due_until
+        );
+
+        let todos = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(todos.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn get_todo(&self, user_id: &str, id: &str) -> RepositoryResult<Option<TodoEntity>> {
+        let query = sqlx::query_as!(
+            TodoRow,
+            r#"SELECT id, list_id, name, complete, created_at, updated_at, completed_at, position,
+                due_date, due_time, priority,
+                ARRAY(
+                    SELECT Tags.name FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                    WHERE TodoTags.todo_id=Todos.id
+                    ORDER BY Tags.name
+                ) AS "tags!",
+                (
+                    SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id AND complete
+                ) AS "checklist_done!",
+                (SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id) AS "checklist_total!",
+                auto_complete, recurrence
+            FROM Todos
+            WHERE id=$1 AND user_id=$2"#,
+            id,
+            user_id
+        );
+
+        let todo = query
+            .fetch_optional(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(todo.map(From::from))
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn update_todo_details(
+        &self,
+        user_id: &str,
+        id: &str,
+        details: &TodoDetails,
+    ) -> RepositoryResult<()> {
+        let query = sqlx::query!(
+            "UPDATE Todos SET due_date=$3, due_time=$4, priority=$5, recurrence=$7, updated_at=$6
+            WHERE id=$1 AND user_id=$2",
+            id,
+            user_id,
+            details.due_date,
+            details.due_time,
+            details.priority.as_i16(),
+            Utc::now(),
+            details.recurrence.as_ref().map(ToString::to_string)
+        );
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn remove_todo(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query!("DELETE FROM Todos WHERE id=$1 AND user_id=$2", id, user_id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_todo_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> RepositoryResult<bool> {
+        let query = sqlx::query!(
+            "UPDATE Todos SET
+                complete=$3,
+                completed_at=CASE WHEN $3 THEN COALESCE(completed_at, $4) END,
+                updated_at=$4
+            WHERE id=$1 AND user_id=$2 AND complete<>$3",
+            id,
+            user_id,
+            complete,
+            Utc::now()
+        );
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() > 0 {
+            return Ok(true);
+        }
+        // already complete or not, unless there's no such todo
+        match self.get_todo(user_id, id).await? {
+            Some(_) => Ok(false),
+            None => Err(RepositoryError::ItemNotFound),
+        }
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_todo_auto_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        auto_complete: bool,
+    ) -> RepositoryResult<()> {
+        let query = sqlx::query!(
+            "UPDATE Todos SET auto_complete=$3, updated_at=$4 WHERE id=$1 AND user_id=$2",
+            id,
+            user_id,
+            auto_complete,
+            Utc::now()
+        );
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn move_todo(
+        &self,
+        user_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        // swaps with the closest todo above or below in one statement, so it's atomic
+        let query = sqlx::query!(
+            "WITH target AS (
+                SELECT id, list_id, position FROM Todos WHERE id=$1 AND user_id=$2
+            ),
+            neighbour AS (
+                SELECT Todos.id, Todos.position FROM Todos, target
+                WHERE Todos.user_id=$2
+                    AND Todos.list_id IS NOT DISTINCT FROM target.list_id
+                    AND CASE WHEN $3
+                    THEN Todos.position < target.position
+                    ELSE Todos.position > target.position
+                END
+                ORDER BY CASE WHEN $3 THEN -Todos.position ELSE Todos.position END
+                LIMIT 1
+            )
+            UPDATE Todos SET position=CASE
+                WHEN Todos.id=target.id THEN neighbour.position
+                ELSE target.position
+            END
+            FROM target, neighbour
+            WHERE Todos.id IN (target.id, neighbour.id)",
+            id,
+            user_id,
+            direction == MoveDirection::Up
+        );
+
+        let result = query.execute(&mut *conn).await?;
+
+        if result.rows_affected() == 0 {
+            // either the todo doesn't exist or it's already at the end of the list
+            let exists = sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT 1 FROM Todos WHERE id=$1 AND user_id=$2) AS "exists!""#,
+                id,
+                user_id
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            if !exists {
+                return Err(RepositoryError::ItemNotFound);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn move_todo_to_list(
+        &self,
+        user_id: &str,
+        id: &str,
+        list_id: Option<&str>,
+    ) -> RepositoryResult<()> {
+        let query = sqlx::query!(
+            "UPDATE Todos SET list_id=$3, updated_at=$4, position=(
+                SELECT COALESCE(MAX(position), 0) + 1 FROM Todos
+                WHERE user_id=$2::varchar AND list_id IS NOT DISTINCT FROM $3::varchar
+            )
+            WHERE id=$1 AND user_id=$2",
+            id,
+            user_id,
+            list_id,
+            Utc::now()
+        );
 
-        let users = query.fetch_all(&self.pool).await?;
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
 
-        Ok(users.into_iter().map(From::from).collect())
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn remove_list_todos(&self, user_id: &str, list_id: &str) -> RepositoryResult<u64> {
+        let query = sqlx::query!(
+            "DELETE FROM Todos WHERE user_id=$1 AND list_id=$2",
+            user_id,
+            list_id
+        );
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        Ok(result.rows_affected())
     }
 
-    async fn remove_todo(&self, _user_id: &str, _id: &str) -> RepositoryResult<()> {
-        todo!()
+    #[instrument(skip_all)]
+    async fn count_todos(&self) -> RepositoryResult<i64> {
+        let query = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM Todos"#);
+
+        Ok(query
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await?)
     }
 }
 
@@ -63,8 +434,24 @@ impl From<TodoRow> for TodoEntity {
     fn from(value: TodoRow) -> Self {
         TodoEntity {
             id: value.id,
+            list_id: value.list_id,
             name: value.name,
             is_complete: value.complete,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            completed_at: value.completed_at,
+            position: value.position,
+            due_date: value.due_date,
+            due_time: value.due_time,
+            priority: TodoPriority::from_i16(value.priority),
+            tags: value.tags,
+            checklist: ChecklistProgress {
+                done: value.checklist_done,
+                total: value.checklist_total,
+            },
+            auto_complete: value.auto_complete,
+            // rules are only written by `Recurrence`, so they always parse
+            recurrence: value.recurrence.and_then(|rule| rule.parse().ok()),
         }
     }
 }
@@ -0,0 +1,139 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use sqlx::{pool::PoolConnection, Database, Pool, Transaction};
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::{
+    audit_repository::AuditRepository,
+    checklist_repository::ChecklistRepository,
+    list_repository::ListRepository,
+    tag_repository::TagRepository,
+    todo_repository::TodoRepository,
+    unit_of_work::{RepositoryTransaction, TransactionUserRepository},
+    user_repository::UserRepository,
+    RepositoryError, RepositoryResult,
+};
+
+/// Transaction shared by the repositories of a unit of work, taken once it's committed or
+/// rolled back
+pub type SharedTransaction<DB> = Arc<Mutex<Option<Transaction<'static, DB>>>>;
+
+/// Where a SQL repository runs its queries: straight on the pool, or in the transaction of a
+/// unit of work.
+pub enum SqlExecutor<DB: Database> {
+    Pool(Pool<DB>),
+    Transaction(SharedTransaction<DB>),
+}
+
+impl<DB: Database> SqlExecutor<DB> {
+    pub async fn acquire(&self) -> RepositoryResult<SqlConnection<'_, DB>> {
+        match self {
+            SqlExecutor::Pool(pool) => Ok(SqlConnection::Pooled(pool.acquire().await?)),
+            SqlExecutor::Transaction(transaction) => {
+                let transaction = transaction.lock().await;
+                if transaction.is_none() {
+                    return Err(RepositoryError::UnknownError {
+                        info: Some("transaction is already finished".to_owned()),
+                    });
+                }
+                Ok(SqlConnection::Transaction(transaction))
+            }
+        }
+    }
+}
+
+/// Connection acquired from a [`SqlExecutor`], to pass to queries as `&mut *conn`
+pub enum SqlConnection<'a, DB: Database> {
+    Pooled(PoolConnection<DB>),
+    Transaction(MutexGuard<'a, Option<Transaction<'static, DB>>>),
+}
+
+impl<DB: Database> Deref for SqlConnection<'_, DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            SqlConnection::Pooled(conn) => conn,
+            SqlConnection::Transaction(transaction) => {
+                transaction.as_ref().expect("checked when acquired")
+            }
+        }
+    }
+}
+
+impl<DB: Database> DerefMut for SqlConnection<'_, DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            SqlConnection::Pooled(conn) => conn,
+            SqlConnection::Transaction(transaction) => {
+                transaction.as_mut().expect("checked when acquired")
+            }
+        }
+    }
+}
+
+/// Repositories of either SQL backend sharing a database transaction. sqlx rolls the
+/// transaction back when it's dropped uncommitted.
+pub struct SqlRepositoryTransaction<DB: Database> {
+    pub transaction: SharedTransaction<DB>,
+    pub users: TransactionUserRepository,
+    pub todos: Box<dyn TodoRepository>,
+    pub lists: Box<dyn ListRepository>,
+    pub tags: Box<dyn TagRepository>,
+    pub checklists: Box<dyn ChecklistRepository>,
+    pub audit: Box<dyn AuditRepository>,
+}
+
+impl<DB: Database> SqlRepositoryTransaction<DB> {
+    async fn take(&self) -> RepositoryResult<Transaction<'static, DB>> {
+        self.transaction
+            .lock()
+            .await
+            .take()
+            .ok_or(RepositoryError::UnknownError {
+                info: Some("transaction is already finished".to_owned()),
+            })
+    }
+}
+
+#[async_trait]
+impl<DB: Database> RepositoryTransaction for SqlRepositoryTransaction<DB> {
+    fn users(&self) -> &dyn UserRepository {
+        &self.users
+    }
+
+    fn todos(&self) -> &dyn TodoRepository {
+        self.todos.as_ref()
+    }
+
+    fn lists(&self) -> &dyn ListRepository {
+        self.lists.as_ref()
+    }
+
+    fn tags(&self) -> &dyn TagRepository {
+        self.tags.as_ref()
+    }
+
+    fn checklists(&self) -> &dyn ChecklistRepository {
+        self.checklists.as_ref()
+    }
+
+    fn audit(&self) -> &dyn AuditRepository {
+        self.audit.as_ref()
+    }
+
+    async fn commit(self: Box<Self>) -> RepositoryResult<()> {
+        self.take().await?.commit().await?;
+        self.users.invalidate_cache().await;
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> RepositoryResult<()> {
+        self.take().await?.rollback().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::{Pool, Postgres};
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use super::{
+    cached_user_repository::CachedUserRepository,
+    sql_audit_repository::SqlAuditRepository,
+    sql_checklist_repository::SqlChecklistRepository,
+    sql_list_repository::SqlListRepository,
+    sql_tag_repository::SqlTagRepository,
+    sql_todo_repository::SqlTodoRepository,
+    sql_transaction::{SharedTransaction, SqlRepositoryTransaction},
+    sql_user_repository::SqlUserRepository,
+    unit_of_work::{RepositoryTransaction, TransactionUserRepository, UnitOfWork},
+    RepositoryResult,
+};
+
+pub struct SqlUnitOfWork {
+    pool: Pool<Postgres>,
+    user_cache: Option<Arc<CachedUserRepository>>,
+}
+
+impl SqlUnitOfWork {
+    pub fn new(pool: Pool<Postgres>, user_cache: Option<Arc<CachedUserRepository>>) -> Self {
+        Self { pool, user_cache }
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for SqlUnitOfWork {
+    #[instrument(skip_all)]
+    async fn begin(&self) -> RepositoryResult<Box<dyn RepositoryTransaction>> {
+        let transaction: SharedTransaction<Postgres> =
+            Arc::new(Mutex::new(Some(self.pool.begin().await?)));
+
+        Ok(Box::new(SqlRepositoryTransaction {
+            users: TransactionUserRepository::new(
+                Arc::new(SqlUserRepository::in_transaction(Arc::clone(&transaction))),
+                self.user_cache.clone(),
+            ),
+            todos: Box::new(SqlTodoRepository::in_transaction(Arc::clone(&transaction))),
+            lists: Box::new(SqlListRepository::in_transaction(Arc::clone(&transaction))),
+            tags: Box::new(SqlTagRepository::in_transaction(Arc::clone(&transaction))),
+            checklists: Box::new(SqlChecklistRepository::in_transaction(Arc::clone(
+                &transaction,
+            ))),
+            audit: Box::new(SqlAuditRepository::in_transaction(Arc::clone(&transaction))),
+            transaction,
+        }))
+    }
+}
@@ -1,20 +1,30 @@
 use async_trait::async_trait;
 use sqlx::{FromRow, Pool, Postgres};
+use tracing::instrument;
 
 use crate::utils::random_id;
 
 use super::{
-    user_repository::{UserEntity, UserRepository},
-    RepositoryResult,
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    user_repository::{UserEntity, UserRepository, UserRole},
+    RepositoryError, RepositoryResult,
 };
 
 pub struct SqlUserRepository {
-    pool: Pool<Postgres>,
+    executor: SqlExecutor<Postgres>,
 }
 
 impl SqlUserRepository {
     pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Postgres>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
     }
 }
 
@@ -23,26 +33,45 @@ pub struct UserRow {
     id: String,
     username: String,
     password_hash: String,
+    role: String,
 }
 
 #[async_trait]
 impl UserRepository for SqlUserRepository {
+    #[instrument(skip_all)]
     async fn get_user_by_id(&self, id: &str) -> RepositoryResult<Option<UserEntity>> {
         let query = sqlx::query_as!(UserRow, "SELECT * FROM Users WHERE id=$1", id);
 
-        let user = query.fetch_optional(&self.pool).await?;
+        let user = query
+            .fetch_optional(&mut *self.executor.acquire().await?)
+            .await?;
 
         Ok(user.map(From::from))
     }
 
+    #[instrument(skip_all, fields(username = %username))]
     async fn get_user_by_username(&self, username: &str) -> RepositoryResult<Option<UserEntity>> {
         let query = sqlx::query_as!(UserRow, "SELECT * FROM Users WHERE username=$1", username);
 
-        let user = query.fetch_optional(&self.pool).await?;
+        let user = query
+            .fetch_optional(&mut *self.executor.acquire().await?)
+            .await?;
 
         Ok(user.map(From::from))
     }
 
+    #[instrument(skip_all)]
+    async fn list_users(&self) -> RepositoryResult<Vec<UserEntity>> {
+        let query = sqlx::query_as!(UserRow, "SELECT * FROM Users ORDER BY username");
+
+        let users = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(users.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(username = %username))]
     async fn create_user(&self, username: &str, pw_hash: &str) -> RepositoryResult<String> {
         let id = random_id();
 
@@ -54,10 +83,55 @@ impl UserRepository for SqlUserRepository {
             pw_hash
         );
 
-        query.execute(&self.pool).await?;
+        query.execute(&mut *self.executor.acquire().await?).await?;
 
         Ok(id)
     }
+
+    #[instrument(skip_all)]
+    async fn update_password_hash(&self, id: &str, pw_hash: &str) -> RepositoryResult<()> {
+        let query = sqlx::query!("UPDATE Users SET password_hash=$2 WHERE id=$1", id, pw_hash);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn update_user_role(&self, id: &str, role: UserRole) -> RepositoryResult<()> {
+        let query = sqlx::query!("UPDATE Users SET role=$2 WHERE id=$1", id, role.as_str());
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_user(&self, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query!("DELETE FROM Users WHERE id=$1", id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn count_users(&self) -> RepositoryResult<i64> {
+        let query = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM Users"#);
+
+        Ok(query
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await?)
+    }
 }
 
 impl From<UserRow> for UserEntity {
@@ -66,6 +140,8 @@ impl From<UserRow> for UserEntity {
             id: value.id,
             username: value.username,
             pw_hash: value.password_hash,
+            // unrecognised roles fall back to the least privileged role
+            role: value.role.parse().unwrap_or(UserRole::User),
         }
     }
 }
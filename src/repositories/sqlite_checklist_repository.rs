@@ -0,0 +1,209 @@
+use async_trait::async_trait;
+use sqlx::{types::Json, FromRow, Pool, Sqlite};
+use tracing::instrument;
+
+use crate::utils::random_id;
+
+use super::{
+    checklist_repository::{ChecklistItemEntity, ChecklistRepository},
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    todo_repository::MoveDirection,
+    RepositoryError, RepositoryResult,
+};
+
+pub struct SqliteChecklistRepository {
+    executor: SqlExecutor<Sqlite>,
+}
+
+impl SqliteChecklistRepository {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct ChecklistItemRow {
+    pub id: String,
+    pub todo_id: String,
+    pub name: String,
+    pub complete: bool,
+    pub position: i64,
+}
+
+#[async_trait]
+impl ChecklistRepository for SqliteChecklistRepository {
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn add_item(&self, user_id: &str, todo_id: &str, name: &str) -> RepositoryResult<String> {
+        let id = random_id();
+
+        let query = sqlx::query(
+            "INSERT INTO ChecklistItems (id, todo_id, name, complete, position)
+            SELECT ?1, id, ?3, false, (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM ChecklistItems WHERE todo_id=?2
+            )
+            FROM Todos WHERE id=?2 AND user_id=?4",
+        )
+        .bind(&id)
+        .bind(todo_id)
+        .bind(name)
+        .bind(user_id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(id)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn list_items(
+        &self,
+        user_id: &str,
+        todo_ids: &[String],
+    ) -> RepositoryResult<Vec<ChecklistItemEntity>> {
+        let query = sqlx::query_as::<_, ChecklistItemRow>(
+            "SELECT ChecklistItems.id, todo_id, ChecklistItems.name, ChecklistItems.complete,
+                ChecklistItems.position
+            FROM ChecklistItems JOIN Todos ON Todos.id=ChecklistItems.todo_id
+            WHERE Todos.user_id=?1 AND todo_id IN (SELECT value FROM json_each(?2))
+            ORDER BY todo_id, ChecklistItems.position",
+        )
+        .bind(user_id)
+        .bind(Json(todo_ids));
+
+        let items = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(items.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_item_complete(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> RepositoryResult<()> {
+        let query = sqlx::query(
+            "UPDATE ChecklistItems SET complete=?4
+            WHERE id=?1 AND todo_id=?2 AND todo_id IN (SELECT id FROM Todos WHERE user_id=?3)",
+        )
+        .bind(id)
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(complete);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn move_item(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        let query = sqlx::query(
+            "WITH target AS (
+                SELECT ChecklistItems.id, ChecklistItems.position
+                FROM ChecklistItems JOIN Todos ON Todos.id=ChecklistItems.todo_id
+                WHERE ChecklistItems.id=?1 AND todo_id=?2 AND Todos.user_id=?3
+            ),
+            neighbour AS (
+                SELECT ChecklistItems.id, ChecklistItems.position FROM ChecklistItems, target
+                WHERE ChecklistItems.todo_id=?2 AND CASE WHEN ?4
+                    THEN ChecklistItems.position < target.position
+                    ELSE ChecklistItems.position > target.position
+                END
+                ORDER BY CASE WHEN ?4
+                    THEN -ChecklistItems.position
+                    ELSE ChecklistItems.position
+                END
+                LIMIT 1
+            )
+            UPDATE ChecklistItems SET position=CASE
+                WHEN ChecklistItems.id=target.id THEN neighbour.position
+                ELSE target.position
+            END
+            FROM target, neighbour
+            WHERE ChecklistItems.id IN (target.id, neighbour.id)",
+        )
+        .bind(id)
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(direction == MoveDirection::Up);
+
+        let result = query.execute(&mut *conn).await?;
+
+        if result.rows_affected() == 0 {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (
+                    SELECT 1 FROM ChecklistItems JOIN Todos ON Todos.id=ChecklistItems.todo_id
+                    WHERE ChecklistItems.id=?1 AND todo_id=?2 AND Todos.user_id=?3
+                )",
+            )
+            .bind(id)
+            .bind(todo_id)
+            .bind(user_id)
+            .fetch_one(&mut *conn)
+            .await?;
+            if !exists {
+                return Err(RepositoryError::ItemNotFound);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn remove_item(&self, user_id: &str, todo_id: &str, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query(
+            "DELETE FROM ChecklistItems
+            WHERE id=?1 AND todo_id=?2 AND todo_id IN (SELECT id FROM Todos WHERE user_id=?3)",
+        )
+        .bind(id)
+        .bind(todo_id)
+        .bind(user_id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ChecklistItemRow> for ChecklistItemEntity {
+    fn from(value: ChecklistItemRow) -> Self {
+        ChecklistItemEntity {
+            id: value.id,
+            todo_id: value.todo_id,
+            name: value.name,
+            is_complete: value.complete,
+            position: value.position,
+        }
+    }
+}
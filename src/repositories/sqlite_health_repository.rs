@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use sqlx::{migrate::Migrate, Pool, Sqlite};
+use tracing::instrument;
+
+use super::{
+    health_repository::{migration_states, HealthRepository, MigrationEntity},
+    RepositoryError, RepositoryResult, SQLITE_MIGRATOR,
+};
+
+pub struct SqliteHealthRepository {
+    pool: Pool<Sqlite>,
+}
+
+impl SqliteHealthRepository {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl HealthRepository for SqliteHealthRepository {
+    #[instrument(skip_all)]
+    async fn ping(&self) -> RepositoryResult<()> {
+        sqlx::query("SELECT 1").fetch_one(&self.pool).await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn list_migrations(&self) -> RepositoryResult<Vec<MigrationEntity>> {
+        // sqlx only creates its bookkeeping table on the first migration run
+        let is_migrated: bool = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1 FROM sqlite_master WHERE type='table' AND name='_sqlx_migrations'
+            )",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let applied = if is_migrated {
+            let mut conn = self.pool.acquire().await?;
+            conn.list_applied_migrations()
+                .await
+                .map_err(|e| RepositoryError::UnknownError {
+                    info: Some(e.to_string()),
+                })?
+        } else {
+            vec![]
+        };
+
+        Ok(migration_states(&SQLITE_MIGRATOR, applied))
+    }
+}
@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, Pool, Sqlite};
+use tracing::instrument;
+
+use crate::utils::random_id;
+
+use super::{
+    list_repository::{ListEntity, ListRepository},
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    RepositoryError, RepositoryResult,
+};
+
+pub struct SqliteListRepository {
+    executor: SqlExecutor<Sqlite>,
+}
+
+impl SqliteListRepository {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct ListRow {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+#[async_trait]
+impl ListRepository for SqliteListRepository {
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn create_list(&self, user_id: &str, name: &str) -> RepositoryResult<String> {
+        let id = random_id();
+
+        let query = sqlx::query(
+            "INSERT INTO Lists (id, user_id, name, created_at) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(&id)
+        .bind(user_id)
+        .bind(name)
+        .bind(Utc::now());
+
+        query.execute(&mut *self.executor.acquire().await?).await?;
+
+        Ok(id)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn list_lists(&self, user_id: &str) -> RepositoryResult<Vec<ListEntity>> {
+        let query = sqlx::query_as::<_, ListRow>(
+            "SELECT id, name, created_at, archived_at FROM Lists
+            WHERE user_id=?1
+            ORDER BY created_at, id",
+        )
+        .bind(user_id);
+
+        let lists = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(lists.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn get_list(&self, user_id: &str, id: &str) -> RepositoryResult<Option<ListEntity>> {
+        let query = sqlx::query_as::<_, ListRow>(
+            "SELECT id, name, created_at, archived_at FROM Lists WHERE id=?1 AND user_id=?2",
+        )
+        .bind(id)
+        .bind(user_id);
+
+        let list = query
+            .fetch_optional(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(list.map(From::from))
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn rename_list(&self, user_id: &str, id: &str, name: &str) -> RepositoryResult<()> {
+        let query = sqlx::query("UPDATE Lists SET name=?3 WHERE id=?1 AND user_id=?2")
+            .bind(id)
+            .bind(user_id)
+            .bind(name);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_list_archived(
+        &self,
+        user_id: &str,
+        id: &str,
+        archived: bool,
+    ) -> RepositoryResult<()> {
+        let query = sqlx::query(
+            "UPDATE Lists SET archived_at=CASE WHEN ?3 THEN COALESCE(archived_at, ?4) END
+            WHERE id=?1 AND user_id=?2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(archived)
+        .bind(Utc::now());
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn delete_list(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query("DELETE FROM Lists WHERE id=?1 AND user_id=?2")
+            .bind(id)
+            .bind(user_id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ListRow> for ListEntity {
+    fn from(value: ListRow) -> Self {
+        ListEntity {
+            id: value.id,
+            name: value.name,
+            created_at: value.created_at,
+            archived_at: value.archived_at,
+        }
+    }
+}
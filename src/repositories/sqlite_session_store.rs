@@ -0,0 +1,143 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, Pool, Sqlite};
+use tracing::instrument;
+
+use crate::utils::random_id;
+
+use super::{
+    session_store::{NewSession, SessionEntity, SessionStore},
+    RepositoryError, RepositoryResult,
+};
+
+pub struct SqliteSessionStore {
+    pool: Pool<Sqlite>,
+}
+
+impl SqliteSessionStore {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct SessionRow {
+    pub id: String,
+    pub user_id: String,
+    pub device: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[async_trait]
+impl SessionStore for SqliteSessionStore {
+    #[instrument(skip_all)]
+    async fn create_session(&self, session: NewSession) -> RepositoryResult<SessionEntity> {
+        let id = random_id();
+        let now = Utc::now();
+
+        let query = sqlx::query(
+            "INSERT INTO Sessions (id, token_hash, user_id, device, ip, created_at, last_seen_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+        )
+        .bind(&id)
+        .bind(&session.token_hash)
+        .bind(&session.user_id)
+        .bind(&session.device)
+        .bind(&session.ip)
+        .bind(now);
+
+        query.execute(&self.pool).await?;
+
+        Ok(SessionEntity {
+            id,
+            user_id: session.user_id,
+            device: session.device,
+            ip: session.ip,
+            created_at: now,
+            last_seen_at: now,
+        })
+    }
+
+    #[instrument(skip_all)]
+    async fn get_session_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> RepositoryResult<Option<SessionEntity>> {
+        let query = sqlx::query_as::<_, SessionRow>(
+            "SELECT id, user_id, device, ip, created_at, last_seen_at FROM Sessions
+            WHERE token_hash=?1",
+        )
+        .bind(token_hash);
+
+        let session = query.fetch_optional(&self.pool).await?;
+
+        Ok(session.map(From::from))
+    }
+
+    #[instrument(skip_all)]
+    async fn touch_session(&self, id: &str, last_seen_at: DateTime<Utc>) -> RepositoryResult<()> {
+        let query = sqlx::query("UPDATE Sessions SET last_seen_at=?2 WHERE id=?1")
+            .bind(id)
+            .bind(last_seen_at);
+
+        let result = query.execute(&self.pool).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn list_user_sessions(&self, user_id: &str) -> RepositoryResult<Vec<SessionEntity>> {
+        let query = sqlx::query_as::<_, SessionRow>(
+            "SELECT id, user_id, device, ip, created_at, last_seen_at FROM Sessions
+            WHERE user_id=?1 ORDER BY last_seen_at DESC",
+        )
+        .bind(user_id);
+
+        let sessions = query.fetch_all(&self.pool).await?;
+
+        Ok(sessions.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn delete_session(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query("DELETE FROM Sessions WHERE id=?1 AND user_id=?2")
+            .bind(id)
+            .bind(user_id);
+
+        let result = query.execute(&self.pool).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_sessions_idle_since(&self, cutoff: DateTime<Utc>) -> RepositoryResult<u64> {
+        let query = sqlx::query("DELETE FROM Sessions WHERE last_seen_at < ?1").bind(cutoff);
+
+        let result = query.execute(&self.pool).await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+impl From<SessionRow> for SessionEntity {
+    fn from(value: SessionRow) -> Self {
+        SessionEntity {
+            id: value.id,
+            user_id: value.user_id,
+            device: value.device,
+            ip: value.ip,
+            created_at: value.created_at,
+            last_seen_at: value.last_seen_at,
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use async_trait::async_trait;
+use sqlx::{types::Json, FromRow, Pool, Sqlite};
+use tracing::instrument;
+
+use crate::utils::random_id;
+
+use super::{
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    tag_repository::{TagEntity, TagRepository},
+    RepositoryError, RepositoryResult,
+};
+
+pub struct SqliteTagRepository {
+    executor: SqlExecutor<Sqlite>,
+}
+
+impl SqliteTagRepository {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct TagRow {
+    pub id: String,
+    pub name: String,
+    pub todo_count: i64,
+}
+
+#[async_trait]
+impl TagRepository for SqliteTagRepository {
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_todo_tags(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        names: &[String],
+    ) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM Todos WHERE id=?1 AND user_id=?2)")
+                .bind(todo_id)
+                .bind(user_id)
+                .fetch_one(&mut *conn)
+                .await?;
+        if !exists {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        for name in names {
+            sqlx::query(
+                "INSERT INTO Tags (id, user_id, name) VALUES (?1, ?2, ?3)
+                ON CONFLICT (user_id, name) DO NOTHING",
+            )
+            .bind(random_id())
+            .bind(user_id)
+            .bind(name)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        sqlx::query("DELETE FROM TodoTags WHERE todo_id=?1")
+            .bind(todo_id)
+            .execute(&mut *conn)
+            .await?;
+
+        // SQLite has no arrays, so the names are passed as a JSON array
+        sqlx::query(
+            "INSERT INTO TodoTags (todo_id, tag_id)
+            SELECT ?1, id FROM Tags WHERE user_id=?2 AND name IN (SELECT value FROM json_each(?3))",
+        )
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(Json(names))
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn list_tags(&self, user_id: &str) -> RepositoryResult<Vec<TagEntity>> {
+        let query = sqlx::query_as::<_, TagRow>(
+            "SELECT Tags.id, Tags.name, COUNT(TodoTags.todo_id) AS todo_count
+            FROM Tags LEFT JOIN TodoTags ON TodoTags.tag_id=Tags.id
+            WHERE Tags.user_id=?1
+            GROUP BY Tags.id, Tags.name
+            ORDER BY Tags.name",
+        )
+        .bind(user_id);
+
+        let tags = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(tags.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn rename_tag(&self, user_id: &str, id: &str, name: &str) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        let taken: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM Tags WHERE user_id=?1 AND name=?2 AND id<>?3)",
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if taken {
+            return Err(RepositoryError::ItemAlreadyExists);
+        }
+
+        let result = sqlx::query("UPDATE Tags SET name=?3 WHERE id=?1 AND user_id=?2")
+            .bind(id)
+            .bind(user_id)
+            .bind(name)
+            .execute(&mut *conn)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn merge_tags(
+        &self,
+        user_id: &str,
+        source_id: &str,
+        target_id: &str,
+    ) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        let found: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM Tags WHERE user_id=?1 AND id IN (?2, ?3)")
+                .bind(user_id)
+                .bind(source_id)
+                .bind(target_id)
+                .fetch_one(&mut *conn)
+                .await?;
+        if found != 2 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        sqlx::query(
+            "INSERT INTO TodoTags (todo_id, tag_id)
+            SELECT todo_id, ?2 FROM TodoTags WHERE tag_id=?1
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(source_id)
+        .bind(target_id)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query("DELETE FROM Tags WHERE id=?1 AND user_id=?2")
+            .bind(source_id)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn delete_tag(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query("DELETE FROM Tags WHERE id=?1 AND user_id=?2")
+            .bind(id)
+            .bind(user_id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<TagRow> for TagEntity {
+    fn from(value: TagRow) -> Self {
+        TagEntity {
+            id: value.id,
+            name: value.name,
+            todo_count: value.todo_count,
+        }
+    }
+}
@@ -0,0 +1,461 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use sqlx::{types::Json, FromRow, Pool, Sqlite};
+use tracing::instrument;
+
+use crate::utils::{random_id, todo_search::TodoSearch};
+
+use super::{
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    todo_repository::{
+        ChecklistProgress, MoveDirection, TodoDetails, TodoEntity, TodoFilter, TodoPriority,
+        TodoRepository,
+    },
+    RepositoryError, RepositoryResult,
+};
+
+pub struct SqliteTodoRepository {
+    executor: SqlExecutor<Sqlite>,
+}
+
+impl SqliteTodoRepository {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct TodoRow {
+    pub id: String,
+    pub list_id: Option<String>,
+    pub name: String,
+    pub complete: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub position: i64,
+    pub due_date: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
+    pub priority: i16,
+    pub tags: Json<Vec<String>>,
+    pub checklist_done: i64,
+    pub checklist_total: i64,
+    pub auto_complete: bool,
+    pub recurrence: Option<String>,
+}
+
+#[async_trait]
+impl TodoRepository for SqliteTodoRepository {
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn add_todo(
+        &self,
+        user_id: &str,
+        list_id: Option<&str>,
+        name: &str,
+        details: &TodoDetails,
+    ) -> RepositoryResult<String> {
+        let id = random_id();
+
+        let query = sqlx::query(
+            "INSERT INTO Todos (
+                id, user_id, list_id, name, complete, created_at, updated_at, position,
+                due_date, due_time, priority, recurrence
+            )
+            VALUES (?1, ?2, ?9, ?3, ?4, ?5, ?5, (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM Todos WHERE user_id=?2 AND list_id IS ?9
+            ), ?6, ?7, ?8, ?10)",
+        )
+        .bind(&id)
+        .bind(user_id)
+        .bind(name)
+        .bind(false)
+        .bind(Utc::now())
+        .bind(details.due_date)
+        .bind(details.due_time)
+        .bind(details.priority.as_i16())
+        .bind(list_id)
+        .bind(details.recurrence.as_ref().map(ToString::to_string));
+
+        query.execute(&mut *self.executor.acquire().await?).await?;
+
+        Ok(id)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn list_todos(
+        &self,
+        user_id: &str,
+        filter: &TodoFilter,
+    ) -> RepositoryResult<Vec<TodoEntity>> {
+        // same filtering and order as the postgres repository
+        let query = sqlx::query_as::<_, TodoRow>(
+            "SELECT id, list_id, name, complete, created_at, updated_at, completed_at, position,
+                due_date, due_time, priority,
+                (
+                    SELECT json_group_array(name) FROM (
+                        SELECT Tags.name FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                        WHERE TodoTags.todo_id=Todos.id
+                        ORDER BY Tags.name
+                    )
+                ) AS tags,
+                (
+                    SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id AND complete
+                ) AS checklist_done,
+                (SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id) AS checklist_total,
+                auto_complete, recurrence
+            FROM Todos
+            WHERE user_id=?1 AND list_id IS ?6 AND (
+                SELECT COUNT(*) FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                WHERE TodoTags.todo_id=Todos.id AND Tags.name IN (SELECT value FROM json_each(?7))
+            ) = json_array_length(?7) AND CASE ?2
+                WHEN 'today' THEN due_date=?3
+                WHEN 'upcoming' THEN due_date>?3
+                WHEN 'overdue' THEN NOT complete AND (due_date<?3 OR (due_date=?3 AND due_time<?4))
+                WHEN 'no_date' THEN due_date IS NULL
+                ELSE true
+            END
+            ORDER BY
+                CASE WHEN ?5='priority' THEN priority END DESC,
+                CASE WHEN ?5 IN ('priority', 'due_date') THEN due_date END NULLS LAST,
+                CASE WHEN ?5 IN ('priority', 'due_date') THEN due_time END NULLS LAST,
+                position, created_at, id",
+        )
+        .bind(user_id)
+        .bind(filter.view.as_str())
+        .bind(filter.now.date_naive())
+        .bind(filter.now.time())
+        .bind(filter.sort.as_str())
+        .bind(filter.list_id.as_deref())
+        .bind(Json(&filter.tags));
+
+        let todos = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(todos.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn search_todos(
+        &self,
+        user_id: &str,
+        search: &TodoSearch,
+    ) -> RepositoryResult<Vec<TodoEntity>> {
+        // quoted prefix queries of the words, all of which have to match
+        let fts_query = (!search.words.is_empty()).then(|| {
+            search
+                .words
+                .iter()
+                .map(|word| format!("\"{word}\"*"))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        });
+
+        // bm25 scores are lower for better matches, and MATCH fails on NULL so it's skipped
+        let query = sqlx::query_as::<_, TodoRow>(
+            "SELECT id, list_id, name, complete, created_at, updated_at, completed_at, position,
+                due_date, due_time, priority,
+                (
+                    SELECT json_group_array(name) FROM (
+                        SELECT Tags.name FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                        WHERE TodoTags.todo_id=Todos.id
+                        ORDER BY Tags.name
+                    )
+                ) AS tags,
+                (
+                    SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id AND complete
+                ) AS checklist_done,
+                (SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id) AS checklist_total,
+                auto_complete, recurrence
+            FROM Todos LEFT JOIN (
+                SELECT todo_id, bm25(TodoSearch) AS score FROM TodoSearch
+                WHERE ?2 IS NOT NULL AND TodoSearch MATCH ?2
+            ) AS matches ON matches.todo_id=Todos.id
+            WHERE user_id=?1
+                AND (list_id IS NULL OR list_id IN (SELECT id FROM Lists WHERE archived_at IS NULL))
+                AND (?2 IS NULL OR matches.todo_id IS NOT NULL)
+                AND (?3 IS NULL OR complete=?3)
+                AND (
+                    SELECT COUNT(*) FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                    WHERE TodoTags.todo_id=Todos.id AND Tags.name IN (SELECT value FROM json_each(?4))
+                ) = json_array_length(?4)
+                AND (?5 IS NULL OR due_date>=?5)
+                AND (?6 IS NULL OR due_date<=?6)
+            ORDER BY matches.score, complete, due_date NULLS LAST, created_at, id",
+        )
+        .bind(user_id)
+        .bind(fts_query)
+        .bind(search.is_complete)
+        .bind(Json(&search.tags))
+        .bind(search.due_from)
+        .bind(search.due_until);
+
+        let todos = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(todos.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn get_todo(&self, user_id: &str, id: &str) -> RepositoryResult<Option<TodoEntity>> {
+        let query = sqlx::query_as::<_, TodoRow>(
+            "SELECT id, list_id, name, complete, created_at, updated_at, completed_at, position,
+                due_date, due_time, priority,
+                (
+                    SELECT json_group_array(name) FROM (
+                        SELECT Tags.name FROM TodoTags JOIN Tags ON Tags.id=TodoTags.tag_id
+                        WHERE TodoTags.todo_id=Todos.id
+                        ORDER BY Tags.name
+                    )
+                ) AS tags,
+                (
+                    SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id AND complete
+                ) AS checklist_done,
+                (SELECT COUNT(*) FROM ChecklistItems WHERE todo_id=Todos.id) AS checklist_total,
+                auto_complete, recurrence
+            FROM Todos
+            WHERE id=?1 AND user_id=?2",
+        )
+        .bind(id)
+        .bind(user_id);
+
+        let todo = query
+            .fetch_optional(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(todo.map(From::from))
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn update_todo_details(
+        &self,
+        user_id: &str,
+        id: &str,
+        details: &TodoDetails,
+    ) -> RepositoryResult<()> {
+        let query = sqlx::query(
+            "UPDATE Todos SET due_date=?3, due_time=?4, priority=?5, recurrence=?7, updated_at=?6
+            WHERE id=?1 AND user_id=?2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(details.due_date)
+        .bind(details.due_time)
+        .bind(details.priority.as_i16())
+        .bind(Utc::now())
+        .bind(details.recurrence.as_ref().map(ToString::to_string));
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn remove_todo(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query("DELETE FROM Todos WHERE id=?1 AND user_id=?2")
+            .bind(id)
+            .bind(user_id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_todo_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> RepositoryResult<bool> {
+        let query = sqlx::query(
+            "UPDATE Todos SET
+                complete=?3,
+                completed_at=CASE WHEN ?3 THEN COALESCE(completed_at, ?4) END,
+                updated_at=?4
+            WHERE id=?1 AND user_id=?2 AND complete<>?3",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(complete)
+        .bind(Utc::now());
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() > 0 {
+            return Ok(true);
+        }
+        // already complete or not, unless there's no such todo
+        match self.get_todo(user_id, id).await? {
+            Some(_) => Ok(false),
+            None => Err(RepositoryError::ItemNotFound),
+        }
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_todo_auto_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        auto_complete: bool,
+    ) -> RepositoryResult<()> {
+        let query = sqlx::query(
+            "UPDATE Todos SET auto_complete=?3, updated_at=?4 WHERE id=?1 AND user_id=?2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(auto_complete)
+        .bind(Utc::now());
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn move_todo(
+        &self,
+        user_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> RepositoryResult<()> {
+        let mut conn = self.executor.acquire().await?;
+
+        // same single statement swap as the postgres repository
+        let query = sqlx::query(
+            "WITH target AS (
+                SELECT id, list_id, position FROM Todos WHERE id=?1 AND user_id=?2
+            ),
+            neighbour AS (
+                SELECT Todos.id, Todos.position FROM Todos, target
+                WHERE Todos.user_id=?2
+                    AND Todos.list_id IS target.list_id
+                    AND CASE WHEN ?3
+                    THEN Todos.position < target.position
+                    ELSE Todos.position > target.position
+                END
+                ORDER BY CASE WHEN ?3 THEN -Todos.position ELSE Todos.position END
+                LIMIT 1
+            )
+            UPDATE Todos SET position=CASE
+                WHEN Todos.id=target.id THEN neighbour.position
+                ELSE target.position
+            END
+            FROM target, neighbour
+            WHERE Todos.id IN (target.id, neighbour.id)",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(direction == MoveDirection::Up);
+
+        let result = query.execute(&mut *conn).await?;
+
+        if result.rows_affected() == 0 {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM Todos WHERE id=?1 AND user_id=?2)",
+            )
+            .bind(id)
+            .bind(user_id)
+            .fetch_one(&mut *conn)
+            .await?;
+            if !exists {
+                return Err(RepositoryError::ItemNotFound);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn move_todo_to_list(
+        &self,
+        user_id: &str,
+        id: &str,
+        list_id: Option<&str>,
+    ) -> RepositoryResult<()> {
+        let query = sqlx::query(
+            "UPDATE Todos SET list_id=?3, updated_at=?4, position=(
+                SELECT COALESCE(MAX(position), 0) + 1 FROM Todos WHERE user_id=?2 AND list_id IS ?3
+            )
+            WHERE id=?1 AND user_id=?2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(list_id)
+        .bind(Utc::now());
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn remove_list_todos(&self, user_id: &str, list_id: &str) -> RepositoryResult<u64> {
+        let query = sqlx::query("DELETE FROM Todos WHERE user_id=?1 AND list_id=?2")
+            .bind(user_id)
+            .bind(list_id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[instrument(skip_all)]
+    async fn count_todos(&self) -> RepositoryResult<i64> {
+        let query = sqlx::query_scalar("SELECT COUNT(*) FROM Todos");
+
+        Ok(query
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await?)
+    }
+}
+
+impl From<TodoRow> for TodoEntity {
+    fn from(value: TodoRow) -> Self {
+        TodoEntity {
+            id: value.id,
+            list_id: value.list_id,
+            name: value.name,
+            is_complete: value.complete,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            completed_at: value.completed_at,
+            position: value.position,
+            due_date: value.due_date,
+            due_time: value.due_time,
+            priority: TodoPriority::from_i16(value.priority),
+            tags: value.tags.0,
+            checklist: ChecklistProgress {
+                done: value.checklist_done,
+                total: value.checklist_total,
+            },
+            auto_complete: value.auto_complete,
+            recurrence: value.recurrence.and_then(|rule| rule.parse().ok()),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::{Pool, Sqlite};
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use super::{
+    cached_user_repository::CachedUserRepository,
+    sql_transaction::{SharedTransaction, SqlRepositoryTransaction},
+    sqlite_audit_repository::SqliteAuditRepository,
+    sqlite_checklist_repository::SqliteChecklistRepository,
+    sqlite_list_repository::SqliteListRepository,
+    sqlite_tag_repository::SqliteTagRepository,
+    sqlite_todo_repository::SqliteTodoRepository,
+    sqlite_user_repository::SqliteUserRepository,
+    unit_of_work::{RepositoryTransaction, TransactionUserRepository, UnitOfWork},
+    RepositoryResult,
+};
+
+pub struct SqliteUnitOfWork {
+    pool: Pool<Sqlite>,
+    user_cache: Option<Arc<CachedUserRepository>>,
+}
+
+impl SqliteUnitOfWork {
+    pub fn new(pool: Pool<Sqlite>, user_cache: Option<Arc<CachedUserRepository>>) -> Self {
+        Self { pool, user_cache }
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for SqliteUnitOfWork {
+    #[instrument(skip_all)]
+    async fn begin(&self) -> RepositoryResult<Box<dyn RepositoryTransaction>> {
+        let transaction: SharedTransaction<Sqlite> =
+            Arc::new(Mutex::new(Some(self.pool.begin().await?)));
+
+        Ok(Box::new(SqlRepositoryTransaction {
+            users: TransactionUserRepository::new(
+                Arc::new(SqliteUserRepository::in_transaction(Arc::clone(
+                    &transaction,
+                ))),
+                self.user_cache.clone(),
+            ),
+            todos: Box::new(SqliteTodoRepository::in_transaction(Arc::clone(
+                &transaction,
+            ))),
+            lists: Box::new(SqliteListRepository::in_transaction(Arc::clone(
+                &transaction,
+            ))),
+            tags: Box::new(SqliteTagRepository::in_transaction(Arc::clone(
+                &transaction,
+            ))),
+            checklists: Box::new(SqliteChecklistRepository::in_transaction(Arc::clone(
+                &transaction,
+            ))),
+            audit: Box::new(SqliteAuditRepository::in_transaction(Arc::clone(
+                &transaction,
+            ))),
+            transaction,
+        }))
+    }
+}
@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use sqlx::{FromRow, Pool, Sqlite};
+use tracing::instrument;
+
+use crate::utils::random_id;
+
+use super::{
+    sql_transaction::{SharedTransaction, SqlExecutor},
+    user_repository::{UserEntity, UserRepository, UserRole},
+    RepositoryError, RepositoryResult,
+};
+
+/// [UserRepository] backed by SQLite. Queries are checked at runtime, as the `query!` macros
+/// are checked against the postgres schema.
+pub struct SqliteUserRepository {
+    executor: SqlExecutor<Sqlite>,
+}
+
+impl SqliteUserRepository {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Pool(pool),
+        }
+    }
+
+    pub fn in_transaction(transaction: SharedTransaction<Sqlite>) -> Self {
+        Self {
+            executor: SqlExecutor::Transaction(transaction),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct UserRow {
+    id: String,
+    username: String,
+    password_hash: String,
+    role: String,
+}
+
+#[async_trait]
+impl UserRepository for SqliteUserRepository {
+    #[instrument(skip_all)]
+    async fn get_user_by_id(&self, id: &str) -> RepositoryResult<Option<UserEntity>> {
+        let query = sqlx::query_as::<_, UserRow>("SELECT * FROM Users WHERE id=?1").bind(id);
+
+        let user = query
+            .fetch_optional(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(user.map(From::from))
+    }
+
+    #[instrument(skip_all, fields(username = %username))]
+    async fn get_user_by_username(&self, username: &str) -> RepositoryResult<Option<UserEntity>> {
+        let query =
+            sqlx::query_as::<_, UserRow>("SELECT * FROM Users WHERE username=?1").bind(username);
+
+        let user = query
+            .fetch_optional(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(user.map(From::from))
+    }
+
+    #[instrument(skip_all)]
+    async fn list_users(&self) -> RepositoryResult<Vec<UserEntity>> {
+        let query = sqlx::query_as::<_, UserRow>("SELECT * FROM Users ORDER BY username");
+
+        let users = query
+            .fetch_all(&mut *self.executor.acquire().await?)
+            .await?;
+
+        Ok(users.into_iter().map(From::from).collect())
+    }
+
+    #[instrument(skip_all, fields(username = %username))]
+    async fn create_user(&self, username: &str, pw_hash: &str) -> RepositoryResult<String> {
+        let id = random_id();
+
+        let query = sqlx::query(
+            "INSERT INTO Users (id, username, password_hash)
+            VALUES (?1, ?2, ?3)",
+        )
+        .bind(&id)
+        .bind(username)
+        .bind(pw_hash);
+
+        query.execute(&mut *self.executor.acquire().await?).await?;
+
+        Ok(id)
+    }
+
+    #[instrument(skip_all)]
+    async fn update_password_hash(&self, id: &str, pw_hash: &str) -> RepositoryResult<()> {
+        let query = sqlx::query("UPDATE Users SET password_hash=?2 WHERE id=?1")
+            .bind(id)
+            .bind(pw_hash);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn update_user_role(&self, id: &str, role: UserRole) -> RepositoryResult<()> {
+        let query = sqlx::query("UPDATE Users SET role=?2 WHERE id=?1")
+            .bind(id)
+            .bind(role.as_str());
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_user(&self, id: &str) -> RepositoryResult<()> {
+        let query = sqlx::query("DELETE FROM Users WHERE id=?1").bind(id);
+
+        let result = query.execute(&mut *self.executor.acquire().await?).await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::ItemNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn count_users(&self) -> RepositoryResult<i64> {
+        let query = sqlx::query_scalar("SELECT COUNT(*) FROM Users");
+
+        Ok(query
+            .fetch_one(&mut *self.executor.acquire().await?)
+            .await?)
+    }
+}
+
+impl From<UserRow> for UserEntity {
+    fn from(value: UserRow) -> Self {
+        UserEntity {
+            id: value.id,
+            username: value.username,
+            pw_hash: value.password_hash,
+            // unrecognised roles fall back to the least privileged role
+            role: value.role.parse().unwrap_or(UserRole::User),
+        }
+    }
+}
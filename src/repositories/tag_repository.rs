@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+
+use super::RepositoryResult;
+
+/// A user's free-form label, which any number of their todos can have
+#[derive(Debug, Clone)]
+pub struct TagEntity {
+    pub id: String,
+    pub name: String,
+    /// how many todos have the tag
+    pub todo_count: i64,
+}
+
+#[async_trait]
+pub trait TagRepository: Send + Sync {
+    /// Replaces the tags of the todo, creating the tags the user doesn't have yet
+    async fn set_todo_tags(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        names: &[String],
+    ) -> RepositoryResult<()>;
+    /// Tags of the user by name, including those no todo has anymore
+    async fn list_tags(&self, user_id: &str) -> RepositoryResult<Vec<TagEntity>>;
+    /// Fails with `ItemAlreadyExists` when the user has another tag with the name
+    async fn rename_tag(&self, user_id: &str, id: &str, name: &str) -> RepositoryResult<()>;
+    /// Gives the todos of the source tag the target tag instead, and deletes the source tag
+    async fn merge_tags(
+        &self,
+        user_id: &str,
+        source_id: &str,
+        target_id: &str,
+    ) -> RepositoryResult<()>;
+    /// Deletes the tag, taking it off its todos
+    async fn delete_tag(&self, user_id: &str, id: &str) -> RepositoryResult<()>;
+}
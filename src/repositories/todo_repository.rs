@@ -1,17 +1,312 @@
+use std::{fmt, str::FromStr};
+
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{recurrence::Recurrence, todo_search::TodoSearch};
 
 use super::RepositoryResult;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl TodoPriority {
+    pub const ALL: [TodoPriority; 3] =
+        [TodoPriority::Low, TodoPriority::Normal, TodoPriority::High];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoPriority::Low => "low",
+            TodoPriority::Normal => "normal",
+            TodoPriority::High => "high",
+        }
+    }
+
+    /// Stored as a number, so the databases can sort by it
+    pub fn as_i16(&self) -> i16 {
+        match self {
+            TodoPriority::Low => 0,
+            TodoPriority::Normal => 1,
+            TodoPriority::High => 2,
+        }
+    }
+
+    pub fn from_i16(value: i16) -> Self {
+        match value {
+            i16::MIN..=0 => TodoPriority::Low,
+            1 => TodoPriority::Normal,
+            2..=i16::MAX => TodoPriority::High,
+        }
+    }
+}
+
+impl fmt::Display for TodoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TodoPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TodoPriority::ALL
+            .into_iter()
+            .find(|priority| priority.as_str() == s)
+            .ok_or_else(|| format!("unknown todo priority: {s}"))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TodoEntity {
     pub id: String,
+    /// `None` for the user's inbox
+    pub list_id: Option<String>,
     pub name: String,
     pub is_complete: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// where the user placed the todo in their list, new todos go last
+    pub position: i64,
+    pub due_date: Option<NaiveDate>,
+    /// only set along with `due_date`, the todo is due by the end of the day without it
+    pub due_time: Option<NaiveTime>,
+    pub priority: TodoPriority,
+    /// names of the todo's tags, in order
+    pub tags: Vec<String>,
+    pub checklist: ChecklistProgress,
+    /// whether the todo is completed once all of its checklist items are done
+    pub auto_complete: bool,
+    /// completing the todo adds its next occurrence
+    pub recurrence: Option<Recurrence>,
+}
+
+impl TodoEntity {
+    /// Incomplete and due before `now`, with dates and times in UTC
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        if self.is_complete {
+            return false;
+        }
+        let today = now.date_naive();
+        match (self.due_date, self.due_time) {
+            (Some(date), _) if date < today => true,
+            (Some(date), Some(time)) => date == today && time < now.time(),
+            _ => false,
+        }
+    }
+}
+
+/// How many of a todo's checklist items are done
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChecklistProgress {
+    pub done: i64,
+    pub total: i64,
+}
+
+impl ChecklistProgress {
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Whether there are items and all of them are done
+    pub fn is_done(&self) -> bool {
+        self.total > 0 && self.done == self.total
+    }
+}
+
+impl fmt::Display for ChecklistProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.done, self.total)
+    }
+}
+
+/// The fields of a todo which are edited together on the todos page
+#[derive(Debug, Clone, Default)]
+pub struct TodoDetails {
+    pub due_date: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
+    pub priority: TodoPriority,
+    pub recurrence: Option<Recurrence>,
+}
+
+/// Which neighbour a todo swaps places with when moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MoveDirection {
+    Up,
+    Down,
+}
+
+/// Which todos are listed, by their due date
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoView {
+    #[default]
+    All,
+    Today,
+    /// due after today
+    Upcoming,
+    /// incomplete and past due
+    Overdue,
+    NoDate,
+}
+
+impl TodoView {
+    pub const ALL: [TodoView; 5] = [
+        TodoView::All,
+        TodoView::Today,
+        TodoView::Upcoming,
+        TodoView::Overdue,
+        TodoView::NoDate,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoView::All => "all",
+            TodoView::Today => "today",
+            TodoView::Upcoming => "upcoming",
+            TodoView::Overdue => "overdue",
+            TodoView::NoDate => "no_date",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TodoView::All => "All",
+            TodoView::Today => "Today",
+            TodoView::Upcoming => "Upcoming",
+            TodoView::Overdue => "Overdue",
+            TodoView::NoDate => "No date",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoSort {
+    /// the order the user arranged the todos in
+    #[default]
+    Position,
+    /// soonest first, todos without a due date last
+    DueDate,
+    /// highest first, then soonest due
+    Priority,
+}
+
+impl TodoSort {
+    pub const ALL: [TodoSort; 3] = [TodoSort::Position, TodoSort::DueDate, TodoSort::Priority];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoSort::Position => "position",
+            TodoSort::DueDate => "due_date",
+            TodoSort::Priority => "priority",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TodoSort::Position => "My order",
+            TodoSort::DueDate => "Due date",
+            TodoSort::Priority => "Priority",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TodoFilter {
+    /// the list to show, `None` for the inbox
+    pub list_id: Option<String>,
+    pub view: TodoView,
+    pub sort: TodoSort,
+    /// only todos with all of these tags
+    pub tags: Vec<String>,
+    /// what counts as today and overdue, in UTC
+    pub now: DateTime<Utc>,
+}
+
+impl Default for TodoFilter {
+    fn default() -> Self {
+        Self {
+            list_id: None,
+            view: TodoView::default(),
+            sort: TodoSort::default(),
+            tags: vec![],
+            now: Utc::now(),
+        }
+    }
 }
 
 #[async_trait]
 pub trait TodoRepository: Send + Sync {
-    async fn add_todo(&self, user_id: &str, name: &str) -> RepositoryResult<String>;
-    async fn list_todos(&self, user_id: &str) -> RepositoryResult<Vec<TodoEntity>>;
+    /// Adds the todo to the end of the list, or the inbox without `list_id`
+    async fn add_todo(
+        &self,
+        user_id: &str,
+        list_id: Option<&str>,
+        name: &str,
+        details: &TodoDetails,
+    ) -> RepositoryResult<String>;
+    /// Todos of the filter's list in its view and order. Todos in the same place of the order
+    /// are ordered by position, then by creation.
+    async fn list_todos(
+        &self,
+        user_id: &str,
+        filter: &TodoFilter,
+    ) -> RepositoryResult<Vec<TodoEntity>>;
+    /// Todos of the user's inbox and unarchived lists matching the search, best matches first,
+    /// then open todos before done ones and by due date
+    async fn search_todos(
+        &self,
+        user_id: &str,
+        search: &TodoSearch,
+    ) -> RepositoryResult<Vec<TodoEntity>>;
+    async fn get_todo(&self, user_id: &str, id: &str) -> RepositoryResult<Option<TodoEntity>>;
+    async fn update_todo_details(
+        &self,
+        user_id: &str,
+        id: &str,
+        details: &TodoDetails,
+    ) -> RepositoryResult<()>;
     async fn remove_todo(&self, user_id: &str, id: &str) -> RepositoryResult<()>;
+    /// Marks the todo complete or not, returning whether it changed. Only one of concurrent
+    /// transactions changing it the same way sees it change.
+    async fn set_todo_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> RepositoryResult<bool>;
+    async fn set_todo_auto_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        auto_complete: bool,
+    ) -> RepositoryResult<()>;
+    /// Swaps the positions of the todo and its neighbour in its list, doing nothing at either end
+    async fn move_todo(
+        &self,
+        user_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> RepositoryResult<()>;
+    /// Moves the todo to the end of another list, or the inbox without `list_id`
+    async fn move_todo_to_list(
+        &self,
+        user_id: &str,
+        id: &str,
+        list_id: Option<&str>,
+    ) -> RepositoryResult<()>;
+    /// Removes every todo of the list, returning how many there were
+    async fn remove_list_todos(&self, user_id: &str, list_id: &str) -> RepositoryResult<u64>;
+    /// Number of todos across all users
+    async fn count_todos(&self) -> RepositoryResult<i64>;
 }
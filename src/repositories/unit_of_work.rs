@@ -0,0 +1,113 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+use super::{
+    audit_repository::AuditRepository,
+    cached_user_repository::CachedUserRepository,
+    checklist_repository::ChecklistRepository,
+    list_repository::ListRepository,
+    tag_repository::TagRepository,
+    todo_repository::TodoRepository,
+    user_repository::{UserEntity, UserRepository, UserRole},
+    RepositoryResult,
+};
+
+/// Starts transactions spanning the user, todo, list, tag, checklist and audit repositories, so services can make
+/// several changes atomically.
+#[async_trait]
+pub trait UnitOfWork: Send + Sync {
+    async fn begin(&self) -> RepositoryResult<Box<dyn RepositoryTransaction>>;
+}
+
+/// Repositories whose changes are only kept once committed. Dropping the transaction without
+/// committing rolls it back, so returning early with `?` leaves no trace of the changes made so
+/// far.
+#[async_trait]
+pub trait RepositoryTransaction: Send {
+    fn users(&self) -> &dyn UserRepository;
+    fn todos(&self) -> &dyn TodoRepository;
+    fn lists(&self) -> &dyn ListRepository;
+    fn tags(&self) -> &dyn TagRepository;
+    fn checklists(&self) -> &dyn ChecklistRepository;
+    fn audit(&self) -> &dyn AuditRepository;
+    async fn commit(self: Box<Self>) -> RepositoryResult<()>;
+    async fn rollback(self: Box<Self>) -> RepositoryResult<()>;
+}
+
+/// User repository of a transaction, which reads past the user cache and keeps track of the
+/// users it changes so they're evicted from the cache once the transaction is finished.
+pub struct TransactionUserRepository {
+    inner: Arc<dyn UserRepository>,
+    user_cache: Option<Arc<CachedUserRepository>>,
+    written_ids: Mutex<HashSet<String>>,
+}
+
+impl TransactionUserRepository {
+    pub fn new(
+        inner: Arc<dyn UserRepository>,
+        user_cache: Option<Arc<CachedUserRepository>>,
+    ) -> Self {
+        Self {
+            inner,
+            user_cache,
+            written_ids: Default::default(),
+        }
+    }
+
+    /// Evicts the users changed in the transaction, which other requests may have cached while
+    /// it was in progress
+    pub async fn invalidate_cache(&self) {
+        if let Some(user_cache) = &self.user_cache {
+            let ids: Vec<_> = self.written_ids.lock().unwrap().drain().collect();
+            for id in ids {
+                user_cache.invalidate(&id).await;
+            }
+        }
+    }
+
+    fn written(&self, id: &str) {
+        self.written_ids.lock().unwrap().insert(id.to_owned());
+    }
+}
+
+#[async_trait]
+impl UserRepository for TransactionUserRepository {
+    async fn get_user_by_id(&self, id: &str) -> RepositoryResult<Option<UserEntity>> {
+        self.inner.get_user_by_id(id).await
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> RepositoryResult<Option<UserEntity>> {
+        self.inner.get_user_by_username(username).await
+    }
+
+    async fn list_users(&self) -> RepositoryResult<Vec<UserEntity>> {
+        self.inner.list_users().await
+    }
+
+    async fn create_user(&self, username: &str, pw_hash: &str) -> RepositoryResult<String> {
+        self.inner.create_user(username, pw_hash).await
+    }
+
+    async fn update_password_hash(&self, id: &str, pw_hash: &str) -> RepositoryResult<()> {
+        self.written(id);
+        self.inner.update_password_hash(id, pw_hash).await
+    }
+
+    async fn update_user_role(&self, id: &str, role: UserRole) -> RepositoryResult<()> {
+        self.written(id);
+        self.inner.update_user_role(id, role).await
+    }
+
+    async fn delete_user(&self, id: &str) -> RepositoryResult<()> {
+        self.written(id);
+        self.inner.delete_user(id).await
+    }
+
+    async fn count_users(&self) -> RepositoryResult<i64> {
+        self.inner.count_users().await
+    }
+}
@@ -1,17 +1,66 @@
+use std::{fmt, str::FromStr};
+
 use async_trait::async_trait;
 
 use super::RepositoryResult;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserRole {
+    User,
+    Admin,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::User => "user",
+            UserRole::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(UserRole::User),
+            "admin" => Ok(UserRole::Admin),
+            _ => Err(format!("unknown user role: {s}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UserEntity {
     pub id: String,
     pub username: String,
     pub pw_hash: String,
+    pub role: UserRole,
+}
+
+impl UserEntity {
+    pub fn is_admin(&self) -> bool {
+        self.role == UserRole::Admin
+    }
 }
 
 #[async_trait]
 pub trait UserRepository: Send + Sync {
     async fn get_user_by_id(&self, id: &str) -> RepositoryResult<Option<UserEntity>>;
     async fn get_user_by_username(&self, username: &str) -> RepositoryResult<Option<UserEntity>>;
+    /// All users, ordered by username
+    async fn list_users(&self) -> RepositoryResult<Vec<UserEntity>>;
     async fn create_user(&self, username: &str, pw_hash: &str) -> RepositoryResult<String>;
+    async fn update_password_hash(&self, id: &str, pw_hash: &str) -> RepositoryResult<()>;
+    async fn update_user_role(&self, id: &str, role: UserRole) -> RepositoryResult<()>;
+    /// Deletes the user along with their todos and sessions
+    async fn delete_user(&self, id: &str) -> RepositoryResult<()>;
+    async fn count_users(&self) -> RepositoryResult<i64>;
 }
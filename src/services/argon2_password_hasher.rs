@@ -0,0 +1,227 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use rand::rngs::OsRng;
+
+use crate::utils::global_auth::{
+    get_password_hash_costs, get_password_hash_secret, HASH_SECRET_PEPPER_ID,
+};
+
+use super::password_hasher::{
+    PasswordHasher, PasswordHasherError, PasswordHasherResult, PasswordVerification,
+};
+
+/// A secret mixed into every password hash, identified by an id of at most 8 bytes
+/// which is stored in the hash's `keyid` parameter.
+#[derive(Clone)]
+pub struct Pepper {
+    pub id: String,
+    pub secret: String,
+}
+
+impl Pepper {
+    /// Parses `<id>:<secret>`
+    pub fn from_spec(spec: &str) -> PasswordHasherResult<Self> {
+        let (id, secret) = spec.split_once(':').ok_or(PasswordHasherError::Hashing {
+            info: String::from("pepper must be in the form <id>:<secret>"),
+        })?;
+        Ok(Self {
+            id: id.to_owned(),
+            secret: secret.to_owned(),
+        })
+    }
+}
+
+/// Argon2id hashes in PHC string format, peppered with the argon2 secret input.
+///
+/// New hashes use the current pepper and cost parameters. Hashes made with a previous
+/// pepper or with different parameters still verify, but are reported as needing a rehash.
+pub struct Argon2PasswordHasher {
+    params: Params,
+    current_pepper: Pepper,
+    previous_peppers: Vec<Pepper>,
+    /// `HASH_SECRET` used by the legacy argonautica hashes
+    legacy_secret: Option<String>,
+}
+
+impl Argon2PasswordHasher {
+    /// `m_cost` is in KiB. Fails if the cost parameters or pepper ids are invalid.
+    pub fn new(
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+        current_pepper: Pepper,
+        previous_peppers: Vec<Pepper>,
+        legacy_secret: Option<String>,
+    ) -> PasswordHasherResult<Self> {
+        for pepper in std::iter::once(&current_pepper).chain(&previous_peppers) {
+            if pepper.id.is_empty() || pepper.id.len() > Params::MAX_KEYID_LEN {
+                return Err(PasswordHasherError::Hashing {
+                    info: format!(
+                        "pepper id {:?} must be 1 to {} bytes",
+                        pepper.id,
+                        Params::MAX_KEYID_LEN
+                    ),
+                });
+            }
+        }
+
+        let params = argon2::ParamsBuilder::new()
+            .m_cost(m_cost)
+            .t_cost(t_cost)
+            .p_cost(p_cost)
+            .keyid(argon2::KeyId::new(current_pepper.id.as_bytes()).map_err(hashing_error)?)
+            .build()
+            .map_err(hashing_error)?;
+
+        Ok(Self {
+            params,
+            current_pepper,
+            previous_peppers,
+            legacy_secret,
+        })
+    }
+
+    /// Loads the hasher from env:
+    /// - `PASSWORD_PEPPER`: `<id>:<secret>` pepper for new hashes, defaulting to `HASH_SECRET`
+    /// - `PASSWORD_PREVIOUS_PEPPERS`: comma separated `<id>:<secret>` peppers still accepted
+    /// - `HASH_SECRET`: secret of the legacy argonautica hashes
+    pub fn from_env() -> PasswordHasherResult<Self> {
+        let legacy_secret = get_password_hash_secret();
+
+        let current_pepper = match std::env::var("PASSWORD_PEPPER") {
+            Ok(spec) => Pepper::from_spec(&spec)?,
+            Err(_) => Pepper {
+                id: String::from(HASH_SECRET_PEPPER_ID),
+                secret: legacy_secret.clone().ok_or(PasswordHasherError::Hashing {
+                    info: String::from("PASSWORD_PEPPER or HASH_SECRET must be set"),
+                })?,
+            },
+        };
+
+        let previous_peppers = match std::env::var("PASSWORD_PREVIOUS_PEPPERS") {
+            Ok(specs) => specs
+                .split(',')
+                .map(str::trim)
+                .filter(|spec| !spec.is_empty())
+                .map(Pepper::from_spec)
+                .collect::<PasswordHasherResult<Vec<_>>>()?,
+            Err(_) => vec![],
+        };
+
+        let (m_cost, t_cost, p_cost) = get_password_hash_costs()?;
+        Self::new(
+            m_cost,
+            t_cost,
+            p_cost,
+            current_pepper,
+            previous_peppers,
+            legacy_secret,
+        )
+    }
+
+    fn secret_for(&self, hash: &PasswordHash) -> PasswordHasherResult<&str> {
+        let params = Params::try_from(hash).map_err(|_| PasswordHasherError::MalformedHash)?;
+
+        if is_legacy_argonautica_hash(hash) {
+            return self
+                .legacy_secret
+                .as_deref()
+                .ok_or(PasswordHasherError::UnknownPepper {
+                    id: String::from("<legacy HASH_SECRET>"),
+                });
+        }
+
+        let keyid = params.keyid();
+        std::iter::once(&self.current_pepper)
+            .chain(&self.previous_peppers)
+            .find(|pepper| pepper.id.as_bytes() == keyid)
+            .map(|pepper| pepper.secret.as_str())
+            .ok_or_else(|| PasswordHasherError::UnknownPepper {
+                id: String::from_utf8_lossy(keyid).into_owned(),
+            })
+    }
+
+    fn is_outdated(&self, hash: &PasswordHash) -> bool {
+        let params = match Params::try_from(hash) {
+            Ok(params) => params,
+            Err(_) => return true,
+        };
+
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+            // parsed params carry the hash's length, while configured ones may leave it unset
+            || params.output_len()
+                != Some(
+                    self.params
+                        .output_len()
+                        .unwrap_or(Params::DEFAULT_OUTPUT_LEN),
+                )
+            || params.keyid() != self.params.keyid()
+    }
+}
+
+/// Hashes made by the `argonautica` crate are argon2 PHC strings without a `keyid`,
+/// peppered with the `HASH_SECRET` env var.
+pub fn is_legacy_argonautica_hash(hash: &PasswordHash) -> bool {
+    let is_argon2 = Algorithm::new(hash.algorithm.as_str()).is_ok();
+    let has_keyid = hash.params.get("keyid").is_some();
+    is_argon2 && !has_keyid
+}
+
+fn hashing_error(e: impl std::fmt::Display) -> PasswordHasherError {
+    PasswordHasherError::Hashing {
+        info: e.to_string(),
+    }
+}
+
+impl PasswordHasher for Argon2PasswordHasher {
+    fn hash_password(&self, password: &str) -> PasswordHasherResult<String> {
+        let argon2 = Argon2::new_with_secret(
+            self.current_pepper.secret.as_bytes(),
+            Algorithm::Argon2id,
+            Version::V0x13,
+            self.params.clone(),
+        )
+        .map_err(hashing_error)?;
+
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = argon2
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(hashing_error)?;
+
+        Ok(hash.to_string())
+    }
+
+    fn verify_password(
+        &self,
+        password: &str,
+        hash: &str,
+    ) -> PasswordHasherResult<PasswordVerification> {
+        let hash = PasswordHash::new(hash).map_err(|_| PasswordHasherError::MalformedHash)?;
+        let algorithm = Algorithm::new(hash.algorithm.as_str())
+            .map_err(|_| PasswordHasherError::MalformedHash)?;
+        let secret = self.secret_for(&hash)?;
+
+        // the hash's own parameters are used for verification, these only select the secret
+        let argon2 = Argon2::new_with_secret(
+            secret.as_bytes(),
+            algorithm,
+            Version::default(),
+            Params::default(),
+        )
+        .map_err(hashing_error)?;
+
+        match argon2.verify_password(password.as_bytes(), &hash) {
+            Ok(()) => Ok(PasswordVerification::Valid {
+                needs_rehash: self.is_outdated(&hash),
+            }),
+            Err(argon2::password_hash::Error::Password) => Ok(PasswordVerification::Invalid),
+            Err(e) => Err(hashing_error(e)),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+use tracing::instrument;
+
+use crate::repositories::{
+    audit_repository::{
+        AuditEventEntity, AuditEventFilter, AuditEventType, AuditRepository, NewAuditEvent,
+    },
+    RepositoryError,
+};
+
+#[derive(Error, Debug)]
+pub enum AuditServiceError {
+    #[error("Unknown error has occurred: {info:?}")]
+    Unknown { info: Option<String> },
+}
+
+pub type AuditServiceResult<T> = Result<T, AuditServiceError>;
+
+/// Who triggered an event and where the request came from.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub actor_id: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AuditContext {
+    /// Same request origin, attributed to the given user (e.g. once a login has identified them).
+    pub fn with_actor(&self, actor_id: &str) -> Self {
+        Self {
+            actor_id: Some(actor_id.to_owned()),
+            ..self.clone()
+        }
+    }
+
+    /// Event of this context, for recording it in a transaction along with the change it audits
+    pub fn event(&self, event_type: AuditEventType, payload: serde_json::Value) -> NewAuditEvent {
+        NewAuditEvent {
+            actor_id: self.actor_id.clone(),
+            event_type,
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+            payload,
+        }
+    }
+}
+
+pub struct AuditService {
+    audit_repository: Arc<dyn AuditRepository>,
+}
+
+impl AuditService {
+    pub fn new(audit_repository: Arc<dyn AuditRepository>) -> Self {
+        Self { audit_repository }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn record(
+        &self,
+        ctx: &AuditContext,
+        event_type: AuditEventType,
+        payload: serde_json::Value,
+    ) -> AuditServiceResult<()> {
+        self.audit_repository
+            .append_event(ctx.event(event_type, payload))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn list_user_events(
+        &self,
+        user_id: &str,
+        limit: i64,
+    ) -> AuditServiceResult<Vec<AuditEventEntity>> {
+        let filter = AuditEventFilter {
+            actor_id: Some(user_id.to_owned()),
+            limit,
+            ..Default::default()
+        };
+        let events = self.audit_repository.list_events(&filter).await?;
+        Ok(events)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn list_events(
+        &self,
+        filter: &AuditEventFilter,
+    ) -> AuditServiceResult<Vec<AuditEventEntity>> {
+        let events = self.audit_repository.list_events(filter).await?;
+        Ok(events)
+    }
+}
+
+impl From<RepositoryError> for AuditServiceError {
+    fn from(value: RepositoryError) -> Self {
+        let info = match value {
+            RepositoryError::UnknownError { info } => info,
+            RepositoryError::DatabaseConnectionError { info } => info,
+            other => Some(other.to_string()),
+        };
+        AuditServiceError::Unknown { info }
+    }
+}
@@ -1,6 +1,10 @@
 use async_trait::async_trait;
 use thiserror::Error;
 
+use crate::repositories::user_repository::UserRole;
+
+use super::audit_service::AuditContext;
+
 #[derive(Error, Debug)]
 pub enum AuthServiceError {
     #[error("User already exists")]
@@ -9,6 +13,8 @@ pub enum AuthServiceError {
     UserDoesNotExists,
     #[error("Incorrect password")]
     IncorrectPassword,
+    #[error("Server is too busy to handle the request")]
+    ServerBusy,
     #[error("Unknown error has occurred: {info:?}")]
     Unknown { info: Option<String> },
 }
@@ -17,6 +23,34 @@ pub type AuthServiceResult<T> = Result<T, AuthServiceError>;
 
 #[async_trait]
 pub trait AuthService: Send + Sync {
-    async fn register_user(&self, username: &str, password: &str) -> AuthServiceResult<()>;
-    async fn authenticate_user(&self, username: &str, password: &str) -> AuthServiceResult<String>;
+    /// Creates a user with the role, which is granted along with the registration so a user
+    /// isn't left behind with a different role when it fails
+    async fn register_user(
+        &self,
+        username: &str,
+        password: &str,
+        role: UserRole,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<()>;
+    async fn authenticate_user(
+        &self,
+        username: &str,
+        password: &str,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<String>;
+    /// Replaces the password of a user, e.g. when an operator resets it
+    async fn reset_password(
+        &self,
+        user_id: &str,
+        password: &str,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<()>;
+    /// Deletes a user along with their todos and sessions
+    async fn delete_user(&self, user_id: &str, ctx: &AuditContext) -> AuthServiceResult<()>;
+    async fn set_user_role(
+        &self,
+        user_id: &str,
+        role: UserRole,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<()>;
 }
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+use tracing::instrument;
+
+use crate::repositories::{
+    checklist_repository::{ChecklistItemEntity, ChecklistRepository},
+    todo_repository::MoveDirection,
+    unit_of_work::{RepositoryTransaction, UnitOfWork},
+    RepositoryError,
+};
+
+use super::todo_service::set_todo_complete_in;
+
+/// Longest checklist item name, the size of the `name` column
+const MAX_ITEM_NAME_LEN: usize = 255;
+
+#[derive(Error, Debug)]
+pub enum ChecklistServiceError {
+    #[error("{0}")]
+    InvalidName(String),
+    #[error("Item not found")]
+    ItemNotFound,
+    #[error("Unknown error has occurred: {info:?}")]
+    Unknown { info: Option<String> },
+}
+
+pub type ChecklistServiceResult<T> = Result<T, ChecklistServiceError>;
+
+/// Manages the checklists of todos. A todo with auto-completion on is complete exactly when all
+/// of its items are done, so changes to the items are made along with the todo in a transaction.
+pub struct ChecklistService {
+    checklist_repository: Arc<dyn ChecklistRepository>,
+    unit_of_work: Arc<dyn UnitOfWork>,
+}
+
+impl ChecklistService {
+    pub fn new(
+        checklist_repository: Arc<dyn ChecklistRepository>,
+        unit_of_work: Arc<dyn UnitOfWork>,
+    ) -> Self {
+        Self {
+            checklist_repository,
+            unit_of_work,
+        }
+    }
+
+    /// Items of the todos, by todo and then position
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn list_items(
+        &self,
+        user_id: &str,
+        todo_ids: &[String],
+    ) -> ChecklistServiceResult<Vec<ChecklistItemEntity>> {
+        Ok(self
+            .checklist_repository
+            .list_items(user_id, todo_ids)
+            .await?)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn add_item(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        name: &str,
+    ) -> ChecklistServiceResult<String> {
+        let name = validate_name(name)?;
+
+        let tx = self.unit_of_work.begin().await?;
+        let id = tx.checklists().add_item(user_id, todo_id, name).await?;
+        sync_auto_complete(tx.as_ref(), user_id, todo_id).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn set_item_complete(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> ChecklistServiceResult<()> {
+        let tx = self.unit_of_work.begin().await?;
+        tx.checklists()
+            .set_item_complete(user_id, todo_id, id, complete)
+            .await?;
+        sync_auto_complete(tx.as_ref(), user_id, todo_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn move_item(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> ChecklistServiceResult<()> {
+        self.checklist_repository
+            .move_item(user_id, todo_id, id, direction)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn remove_item(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        id: &str,
+    ) -> ChecklistServiceResult<()> {
+        let tx = self.unit_of_work.begin().await?;
+        tx.checklists().remove_item(user_id, todo_id, id).await?;
+        sync_auto_complete(tx.as_ref(), user_id, todo_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Turns auto-completion of the todo on or off, completing it right away if all of its items
+    /// are already done
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn set_auto_complete(
+        &self,
+        user_id: &str,
+        todo_id: &str,
+        auto_complete: bool,
+    ) -> ChecklistServiceResult<()> {
+        let tx = self.unit_of_work.begin().await?;
+        tx.todos()
+            .set_todo_auto_complete(user_id, todo_id, auto_complete)
+            .await?;
+        sync_auto_complete(tx.as_ref(), user_id, todo_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Completes or reopens the todo to match its checklist, if it has auto-completion on and any
+/// items. Reopening keeps a todo from staying complete after an item is added or unticked, and
+/// completing a recurring todo adds its next occurrence like completing it by hand.
+async fn sync_auto_complete(
+    tx: &dyn RepositoryTransaction,
+    user_id: &str,
+    todo_id: &str,
+) -> ChecklistServiceResult<()> {
+    let todo = tx
+        .todos()
+        .get_todo(user_id, todo_id)
+        .await?
+        .ok_or(ChecklistServiceError::ItemNotFound)?;
+
+    let complete = todo.checklist.is_done();
+    if todo.auto_complete && !todo.checklist.is_empty() && todo.is_complete != complete {
+        set_todo_complete_in(tx, user_id, &todo, complete).await?;
+    }
+    Ok(())
+}
+
+/// The name without surrounding whitespace, which must be non-empty and fit the `name` column
+fn validate_name(name: &str) -> ChecklistServiceResult<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ChecklistServiceError::InvalidName(
+            "Checklist item can't be empty".to_owned(),
+        ));
+    }
+    if name.chars().count() > MAX_ITEM_NAME_LEN {
+        return Err(ChecklistServiceError::InvalidName(format!(
+            "Checklist item can't be longer than {MAX_ITEM_NAME_LEN} characters"
+        )));
+    }
+    Ok(name)
+}
+
+impl From<RepositoryError> for ChecklistServiceError {
+    fn from(value: RepositoryError) -> Self {
+        match value {
+            RepositoryError::ItemNotFound => ChecklistServiceError::ItemNotFound,
+            RepositoryError::ItemAlreadyExists => ChecklistServiceError::Unknown {
+                info: Some(value.to_string()),
+            },
+            RepositoryError::UnknownError { info } => ChecklistServiceError::Unknown { info },
+            RepositoryError::DatabaseConnectionError { info } => {
+                ChecklistServiceError::Unknown { info }
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+/// A CSP violation reported by a browser.
+#[derive(Debug, Clone)]
+pub struct CspReport {
+    pub received_at: DateTime<Utc>,
+    pub document_uri: String,
+    pub violated_directive: String,
+    pub blocked_uri: String,
+    /// the report as sent by the browser
+    pub raw: String,
+}
+
+/// Keeps the most recent CSP violation reports in memory, for checking a policy in
+/// report-only mode before enforcing it.
+pub struct CspReportService {
+    reports: Mutex<VecDeque<CspReport>>,
+    capacity: usize,
+}
+
+impl CspReportService {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            reports: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Records the reports in a report body, returning how many were found. Accepts both the
+    /// `report-uri` format (`{"csp-report": {..}}`) and the Reporting API format (an array of
+    /// `{"type": "csp-violation", "body": {..}}`).
+    pub async fn record(&self, body: &Value) -> usize {
+        let reports: Vec<&Value> = match body {
+            Value::Array(reports) => reports.iter().filter_map(|r| r.get("body")).collect(),
+            report => report.get("csp-report").into_iter().collect(),
+        };
+
+        let now = Utc::now();
+        let mut stored = self.reports.lock().await;
+        for report in &reports {
+            if stored.len() == self.capacity {
+                stored.pop_back();
+            }
+            stored.push_front(CspReport {
+                received_at: now,
+                document_uri: report_field(report, &["document-uri", "documentURL"]),
+                violated_directive: report_field(
+                    report,
+                    &["violated-directive", "effectiveDirective"],
+                ),
+                blocked_uri: report_field(report, &["blocked-uri", "blockedURL"]),
+                raw: report.to_string(),
+            });
+        }
+        reports.len()
+    }
+
+    /// Newest first
+    pub async fn list_reports(&self) -> Vec<CspReport> {
+        self.reports.lock().await.iter().cloned().collect()
+    }
+}
+
+fn report_field(report: &Value, names: &[&str]) -> String {
+    names
+        .iter()
+        .find_map(|name| report.get(name).and_then(Value::as_str))
+        .unwrap_or_default()
+        .to_owned()
+}
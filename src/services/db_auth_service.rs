@@ -1,31 +1,96 @@
 use std::sync::Arc;
 
-use argonautica::{Hasher, Verifier};
 use async_trait::async_trait;
-use chrono::Utc;
-use jwt::SignWithKey;
+use serde_json::json;
+use tokio::sync::OnceCell;
+use tracing::instrument;
 
-use crate::{
-    repositories::{user_repository::UserRepository, RepositoryError},
-    utils::global_auth::{get_jwt_signing_key, get_password_hash_secret, JWT_AUTH_EXPIRATION_MINS},
-    TokenClaims,
+use crate::repositories::{
+    audit_repository::AuditEventType,
+    unit_of_work::UnitOfWork,
+    user_repository::{UserRepository, UserRole},
+    RepositoryError,
 };
+use crate::utils::random_id;
 
-use super::auth_service::{AuthService, AuthServiceError, AuthServiceResult};
+use super::{
+    audit_service::{AuditContext, AuditService, AuditServiceError},
+    auth_service::{AuthService, AuthServiceError, AuthServiceResult},
+    metrics_service::MetricsService,
+    password_hasher::{PasswordHasherError, PasswordVerification},
+    password_hashing_pool::PasswordHashingPool,
+    session_service::{SessionService, SessionServiceError},
+};
 
 pub struct DbAuthService {
     user_repository: Arc<dyn UserRepository>,
+    unit_of_work: Arc<dyn UnitOfWork>,
+    password_hashing: PasswordHashingPool,
+    session_service: Arc<dyn SessionService>,
+    audit_service: Arc<AuditService>,
+    metrics_service: Arc<MetricsService>,
+    /// Hash of a random password, verified against for unknown usernames so they take as long
+    /// to reject as wrong passwords
+    dummy_hash: OnceCell<String>,
 }
 
 impl DbAuthService {
-    pub fn new(user_repository: Arc<dyn UserRepository>) -> Self {
-        DbAuthService { user_repository }
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        unit_of_work: Arc<dyn UnitOfWork>,
+        password_hashing: PasswordHashingPool,
+        session_service: Arc<dyn SessionService>,
+        audit_service: Arc<AuditService>,
+        metrics_service: Arc<MetricsService>,
+    ) -> Self {
+        DbAuthService {
+            user_repository,
+            unit_of_work,
+            password_hashing,
+            session_service,
+            audit_service,
+            metrics_service,
+            dummy_hash: OnceCell::new(),
+        }
     }
-}
 
-#[async_trait]
-impl AuthService for DbAuthService {
-    async fn register_user(&self, username: &str, password: &str) -> AuthServiceResult<()> {
+    /// Hashed on first use, so it has the configured costs like the stored hashes
+    async fn dummy_hash(&self) -> AuthServiceResult<&str> {
+        let hash = self
+            .dummy_hash
+            .get_or_try_init(|| async { self.password_hashing.hash_password(&random_id()).await })
+            .await?;
+        Ok(hash)
+    }
+
+    async fn record_failed_login(
+        &self,
+        ctx: &AuditContext,
+        username: &str,
+        error: AuthServiceError,
+    ) -> AuthServiceResult<String> {
+        let reason = match error {
+            AuthServiceError::UserDoesNotExists => "unknown_user",
+            AuthServiceError::IncorrectPassword => "incorrect_password",
+            _ => return Err(error),
+        };
+        self.audit_service
+            .record(
+                ctx,
+                AuditEventType::LoginFailed,
+                json!({ "username": username, "reason": reason }),
+            )
+            .await?;
+        Err(error)
+    }
+
+    async fn register(
+        &self,
+        username: &str,
+        password: &str,
+        role: UserRole,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<()> {
         let user_exists = self
             .user_repository
             .get_user_by_username(username)
@@ -35,53 +100,206 @@ impl AuthService for DbAuthService {
             return Err(AuthServiceError::UserAlreadyExists);
         }
 
-        let hash_secret = get_password_hash_secret();
-        let mut hasher = Hasher::default();
-        let hash = hasher
-            .with_password(password)
-            .with_secret_key(hash_secret)
-            .hash()
-            .map_err_unknown()?;
+        let hash = self.password_hashing.hash_password(password).await?;
 
-        self.user_repository.create_user(username, &hash).await?;
+        let tx = self.unit_of_work.begin().await?;
+        let user_id = tx.users().create_user(username, &hash).await?;
+        tx.audit()
+            .append_event(ctx.with_actor(&user_id).event(
+                AuditEventType::Registration,
+                json!({ "username": username }),
+            ))
+            .await?;
+        if role != UserRole::User {
+            tx.users().update_user_role(&user_id, role).await?;
+            tx.audit()
+                .append_event(ctx.event(
+                    AuditEventType::RoleChanged,
+                    json!({ "user_id": user_id, "from": UserRole::User.as_str(), "to": role.as_str() }),
+                ))
+                .await?;
+        }
+        tx.commit().await?;
 
         Ok(())
     }
 
-    async fn authenticate_user(&self, username: &str, password: &str) -> AuthServiceResult<String> {
-        let user = self
-            .user_repository
-            .get_user_by_username(username)
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<String> {
+        let user = match self.user_repository.get_user_by_username(username).await? {
+            Some(user) => user,
+            None => {
+                self.password_hashing
+                    .verify_password(password, self.dummy_hash().await?)
+                    .await?;
+                return self
+                    .record_failed_login(ctx, username, AuthServiceError::UserDoesNotExists)
+                    .await;
+            }
+        };
+
+        let verification = self
+            .password_hashing
+            .verify_password(password, &user.pw_hash)
+            .await?;
+
+        match verification {
+            PasswordVerification::Invalid => {
+                return self
+                    .record_failed_login(
+                        &ctx.with_actor(&user.id),
+                        username,
+                        AuthServiceError::IncorrectPassword,
+                    )
+                    .await;
+            }
+            PasswordVerification::Valid { needs_rehash: true } => {
+                // the plaintext is only available here, so outdated hashes are upgraded on login.
+                // a failed upgrade shouldn't block the login, it is retried next time.
+                let rehashed = match self.password_hashing.hash_password(password).await {
+                    Ok(hash) => self
+                        .user_repository
+                        .update_password_hash(&user.id, &hash)
+                        .await
+                        .map_err(AuthServiceError::from),
+                    Err(e) => Err(e.into()),
+                };
+                match rehashed {
+                    Ok(()) => tracing::info!(user_id = %user.id, "upgraded outdated password hash"),
+                    Err(e) => {
+                        tracing::warn!(user_id = %user.id, error = %e, "failed to upgrade password hash")
+                    }
+                }
+            }
+            PasswordVerification::Valid {
+                needs_rehash: false,
+            } => {}
+        }
+
+        let session_token = self.session_service.start_session(&user.id, ctx).await?;
+
+        self.audit_service
+            .record(
+                &ctx.with_actor(&user.id),
+                AuditEventType::Login,
+                json!({ "username": username }),
+            )
+            .await?;
+
+        Ok(session_token)
+    }
+}
+
+#[async_trait]
+impl AuthService for DbAuthService {
+    #[instrument(skip_all, fields(username = %username))]
+    async fn register_user(
+        &self,
+        username: &str,
+        password: &str,
+        role: UserRole,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<()> {
+        let res = self.register(username, password, role, ctx).await;
+        self.metrics_service
+            .record_auth_attempt("register", auth_outcome(&res));
+        res
+    }
+
+    #[instrument(skip_all, fields(username = %username))]
+    async fn authenticate_user(
+        &self,
+        username: &str,
+        password: &str,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<String> {
+        let res = self.authenticate(username, password, ctx).await;
+        self.metrics_service
+            .record_auth_attempt("login", auth_outcome(&res));
+        res
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn reset_password(
+        &self,
+        user_id: &str,
+        password: &str,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<()> {
+        let hash = self.password_hashing.hash_password(password).await?;
+
+        let tx = self.unit_of_work.begin().await?;
+        tx.users().update_password_hash(user_id, &hash).await?;
+        tx.audit()
+            .append_event(ctx.event(
+                AuditEventType::PasswordChanged,
+                json!({ "user_id": user_id }),
+            ))
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn delete_user(&self, user_id: &str, ctx: &AuditContext) -> AuthServiceResult<()> {
+        let tx = self.unit_of_work.begin().await?;
+        let user = tx
+            .users()
+            .get_user_by_id(user_id)
             .await?
             .ok_or(AuthServiceError::UserDoesNotExists)?;
+        tx.users().delete_user(user_id).await?;
+        tx.audit()
+            .append_event(ctx.event(
+                AuditEventType::UserDeleted,
+                json!({ "user_id": user_id, "username": user.username }),
+            ))
+            .await?;
+        tx.commit().await?;
 
-        let hash_secret = get_password_hash_secret();
-        let mut verifier = Verifier::default();
-        let is_valid = verifier
-            .with_hash(user.pw_hash)
-            .with_password(password)
-            .with_secret_key(hash_secret)
-            .verify()
-            .map_err_unknown()?;
-
-        if !is_valid {
-            return Err(AuthServiceError::IncorrectPassword);
-        }
+        Ok(())
+    }
 
-        let issued = Utc::now();
-        let expiration = Utc::now()
-            .checked_add_signed(chrono::Duration::minutes(JWT_AUTH_EXPIRATION_MINS))
-            .expect("valid timestamp");
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn set_user_role(
+        &self,
+        user_id: &str,
+        role: UserRole,
+        ctx: &AuditContext,
+    ) -> AuthServiceResult<()> {
+        let tx = self.unit_of_work.begin().await?;
+        let user = tx
+            .users()
+            .get_user_by_id(user_id)
+            .await?
+            .ok_or(AuthServiceError::UserDoesNotExists)?;
+        tx.users().update_user_role(user_id, role).await?;
+        tx.audit()
+            .append_event(ctx.event(
+                AuditEventType::RoleChanged,
+                json!({ "user_id": user_id, "from": user.role.as_str(), "to": role.as_str() }),
+            ))
+            .await?;
+        tx.commit().await?;
 
-        let claims = TokenClaims {
-            id: user.id,
-            expiration,
-            issued,
-        };
-        let jwt_secret = get_jwt_signing_key();
-        let access_token = claims.sign_with_key(&jwt_secret).map_err_unknown()?;
+        Ok(())
+    }
+}
 
-        Ok(access_token)
+/// Label of the outcome of a login or registration in the auth metrics
+fn auth_outcome<T>(res: &AuthServiceResult<T>) -> &'static str {
+    match res {
+        Ok(_) => "success",
+        Err(AuthServiceError::UserAlreadyExists) => "user_already_exists",
+        Err(AuthServiceError::UserDoesNotExists) => "unknown_user",
+        Err(AuthServiceError::IncorrectPassword) => "incorrect_password",
+        Err(AuthServiceError::ServerBusy) => "server_busy",
+        Err(AuthServiceError::Unknown { .. }) => "error",
     }
 }
 
@@ -96,17 +314,32 @@ impl From<RepositoryError> for AuthServiceError {
     }
 }
 
-trait MapUnknown {
-    type MappedRes;
-    fn map_err_unknown(self) -> Self::MappedRes;
+impl From<AuditServiceError> for AuthServiceError {
+    fn from(value: AuditServiceError) -> Self {
+        match value {
+            AuditServiceError::Unknown { info } => AuthServiceError::Unknown { info },
+        }
+    }
 }
 
-impl<T, E: std::fmt::Display> MapUnknown for Result<T, E> {
-    type MappedRes = Result<T, AuthServiceError>;
+impl From<SessionServiceError> for AuthServiceError {
+    fn from(value: SessionServiceError) -> Self {
+        match value {
+            SessionServiceError::Unknown { info } => AuthServiceError::Unknown { info },
+            other => AuthServiceError::Unknown {
+                info: Some(other.to_string()),
+            },
+        }
+    }
+}
 
-    fn map_err_unknown(self) -> Self::MappedRes {
-        self.map_err(|e| AuthServiceError::Unknown {
-            info: Some(e.to_string()),
-        })
+impl From<PasswordHasherError> for AuthServiceError {
+    fn from(value: PasswordHasherError) -> Self {
+        match value {
+            PasswordHasherError::Busy => AuthServiceError::ServerBusy,
+            other => AuthServiceError::Unknown {
+                info: Some(other.to_string()),
+            },
+        }
     }
 }
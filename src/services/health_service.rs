@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::repositories::{
+    health_repository::{HealthRepository, MigrationState},
+    RepositoryResult,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Overall status with the status of each checked component, serialized as the response of
+/// the health endpoints.
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    pub components: BTreeMap<&'static str, ComponentHealth>,
+}
+
+impl HealthReport {
+    pub fn is_up(&self) -> bool {
+        self.status == HealthStatus::Up
+    }
+}
+
+pub struct HealthService {
+    health_repository: Arc<dyn HealthRepository>,
+    /// checks taking longer than this are reported as down, so probes don't hang
+    check_timeout: Duration,
+}
+
+impl HealthService {
+    pub fn new(health_repository: Arc<dyn HealthRepository>, check_timeout: Duration) -> Self {
+        Self {
+            health_repository,
+            check_timeout,
+        }
+    }
+
+    /// Whether the process is running and serving requests. Doesn't check any dependencies,
+    /// as restarting the app won't fix them.
+    pub fn liveness(&self) -> HealthReport {
+        HealthReport {
+            status: HealthStatus::Up,
+            latency_ms: 0,
+            components: BTreeMap::new(),
+        }
+    }
+
+    /// Whether the app can handle requests: the database answers and its schema is current.
+    pub async fn readiness(&self) -> HealthReport {
+        let started = Instant::now();
+        let (database, migrations) = futures_util::join!(
+            self.check(self.health_repository.ping(), |_| Ok(())),
+            self.check(self.health_repository.list_migrations(), |migrations| {
+                let outdated: Vec<_> = migrations
+                    .iter()
+                    .filter(|m| m.state != MigrationState::Applied)
+                    .map(|m| format!("{} {} ({})", m.version, m.description, m.state))
+                    .collect();
+                if outdated.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!("outdated migrations: {}", outdated.join(", ")))
+                }
+            }),
+        );
+
+        let components = BTreeMap::from([("database", database), ("migrations", migrations)]);
+        let status = if components.values().all(|c| c.status == HealthStatus::Up) {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        };
+        HealthReport {
+            status,
+            latency_ms: started.elapsed().as_millis() as u64,
+            components,
+        }
+    }
+
+    /// Runs a check with the timeout. `validate` turns the result of a check which ran into
+    /// an error message if it isn't healthy.
+    async fn check<T>(
+        &self,
+        check: impl Future<Output = RepositoryResult<T>>,
+        validate: impl FnOnce(T) -> Result<(), String>,
+    ) -> ComponentHealth {
+        let started = Instant::now();
+        let result = match tokio::time::timeout(self.check_timeout, check).await {
+            Ok(Ok(value)) => validate(value),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!(
+                "timed out after {}ms",
+                self.check_timeout.as_millis()
+            )),
+        };
+        ComponentHealth {
+            status: match result {
+                Ok(()) => HealthStatus::Up,
+                Err(_) => HealthStatus::Down,
+            },
+            latency_ms: started.elapsed().as_millis() as u64,
+            error: result.err(),
+        }
+    }
+}
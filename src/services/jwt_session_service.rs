@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::instrument;
+
+use crate::{
+    repositories::session_store::SessionEntity,
+    utils::{global_auth::JWT_AUTH_EXPIRATION_MINS, jwt_keyring::JwtKeyring},
+    TokenClaims,
+};
+
+use super::{
+    audit_service::AuditContext,
+    session_service::{SessionInfo, SessionService, SessionServiceError, SessionServiceResult},
+};
+
+/// Stateless sessions: the session cookie is a JWT of [TokenClaims] signed by the [JwtKeyring].
+/// Individual sessions can't be listed or revoked, they simply expire.
+pub struct JwtSessionService {
+    keyring: Arc<JwtKeyring>,
+}
+
+impl JwtSessionService {
+    pub fn new(keyring: Arc<JwtKeyring>) -> Self {
+        Self { keyring }
+    }
+}
+
+#[async_trait]
+impl SessionService for JwtSessionService {
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn start_session(
+        &self,
+        user_id: &str,
+        _ctx: &AuditContext,
+    ) -> SessionServiceResult<String> {
+        let issued = Utc::now();
+        let expiration = Utc::now()
+            .checked_add_signed(chrono::Duration::minutes(JWT_AUTH_EXPIRATION_MINS))
+            .expect("valid timestamp");
+
+        let claims = TokenClaims {
+            id: user_id.to_owned(),
+            expiration,
+            issued,
+        };
+        self.keyring
+            .sign(&claims)
+            .map_err(|e| SessionServiceError::Unknown {
+                info: Some(e.to_string()),
+            })
+    }
+
+    #[instrument(skip_all)]
+    async fn resolve_session(&self, token: &str) -> SessionServiceResult<Option<SessionInfo>> {
+        // expiration is validated by the keyring
+        let claims = match self.keyring.verify::<TokenClaims>(token) {
+            Some(claims) => claims,
+            None => return Ok(None),
+        };
+
+        Ok(Some(SessionInfo {
+            user_id: claims.id,
+            session_id: None,
+            issued: claims.issued,
+        }))
+    }
+
+    #[instrument(skip_all)]
+    async fn list_sessions(&self, _user_id: &str) -> SessionServiceResult<Vec<SessionEntity>> {
+        Err(SessionServiceError::Unsupported)
+    }
+
+    #[instrument(skip_all)]
+    async fn revoke_session(
+        &self,
+        _user_id: &str,
+        _session_id: &str,
+        _ctx: &AuditContext,
+    ) -> SessionServiceResult<()> {
+        Err(SessionServiceError::Unsupported)
+    }
+
+    /// JWTs aren't stored, so there is nothing to remove
+    async fn delete_expired_sessions(&self) -> SessionServiceResult<u64> {
+        Ok(0)
+    }
+}
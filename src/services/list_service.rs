@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use thiserror::Error;
+use tracing::instrument;
+
+use crate::repositories::{
+    audit_repository::AuditEventType,
+    list_repository::{ListEntity, ListRepository},
+    unit_of_work::UnitOfWork,
+    RepositoryError,
+};
+
+use super::audit_service::AuditContext;
+
+/// Longest list name, the size of the `name` column
+const MAX_LIST_NAME_LEN: usize = 255;
+
+#[derive(Error, Debug)]
+pub enum ListServiceError {
+    #[error("{0}")]
+    InvalidName(String),
+    #[error("Item not found")]
+    ItemNotFound,
+    #[error("Unknown error has occurred: {info:?}")]
+    Unknown { info: Option<String> },
+}
+
+pub type ListServiceResult<T> = Result<T, ListServiceError>;
+
+pub struct ListService {
+    list_repository: Arc<dyn ListRepository>,
+    unit_of_work: Arc<dyn UnitOfWork>,
+}
+
+impl ListService {
+    pub fn new(
+        list_repository: Arc<dyn ListRepository>,
+        unit_of_work: Arc<dyn UnitOfWork>,
+    ) -> Self {
+        Self {
+            list_repository,
+            unit_of_work,
+        }
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn create_list(&self, user_id: &str, name: &str) -> ListServiceResult<String> {
+        let name = validate_name(name)?;
+        Ok(self.list_repository.create_list(user_id, name).await?)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn list_lists(&self, user_id: &str) -> ListServiceResult<Vec<ListEntity>> {
+        Ok(self.list_repository.list_lists(user_id).await?)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn get_list(&self, user_id: &str, id: &str) -> ListServiceResult<ListEntity> {
+        self.list_repository
+            .get_list(user_id, id)
+            .await?
+            .ok_or(ListServiceError::ItemNotFound)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn rename_list(&self, user_id: &str, id: &str, name: &str) -> ListServiceResult<()> {
+        let name = validate_name(name)?;
+        self.list_repository.rename_list(user_id, id, name).await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn set_list_archived(
+        &self,
+        user_id: &str,
+        id: &str,
+        archived: bool,
+    ) -> ListServiceResult<()> {
+        self.list_repository
+            .set_list_archived(user_id, id, archived)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the list along with its todos
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn delete_list(
+        &self,
+        user_id: &str,
+        id: &str,
+        ctx: &AuditContext,
+    ) -> ListServiceResult<()> {
+        let tx = self.unit_of_work.begin().await?;
+        let todos_deleted = tx.todos().remove_list_todos(user_id, id).await?;
+        tx.lists().delete_list(user_id, id).await?;
+        tx.audit()
+            .append_event(ctx.with_actor(user_id).event(
+                AuditEventType::ListDeleted,
+                json!({ "list_id": id, "todos_deleted": todos_deleted }),
+            ))
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// The name without surrounding whitespace, which must be non-empty and fit the `name` column
+fn validate_name(name: &str) -> ListServiceResult<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ListServiceError::InvalidName(
+            "List name can't be empty".to_owned(),
+        ));
+    }
+    if name.chars().count() > MAX_LIST_NAME_LEN {
+        return Err(ListServiceError::InvalidName(format!(
+            "List name can't be longer than {MAX_LIST_NAME_LEN} characters"
+        )));
+    }
+    Ok(name)
+}
+
+impl From<RepositoryError> for ListServiceError {
+    fn from(value: RepositoryError) -> Self {
+        match value {
+            RepositoryError::ItemNotFound => ListServiceError::ItemNotFound,
+            RepositoryError::ItemAlreadyExists => ListServiceError::Unknown {
+                info: Some(value.to_string()),
+            },
+            RepositoryError::UnknownError { info } => ListServiceError::Unknown { info },
+            RepositoryError::DatabaseConnectionError { info } => ListServiceError::Unknown { info },
+        }
+    }
+}
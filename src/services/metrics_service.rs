@@ -0,0 +1,256 @@
+use std::{sync::Arc, time::Duration};
+
+use prometheus::{
+    core::{Collector, Desc},
+    proto::MetricFamily,
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use thiserror::Error;
+
+use crate::{
+    repositories::cached_user_repository::{CachedUserRepository, UserCacheStats},
+    utils::database::DatabasePool,
+};
+
+/// Buckets of the repository latency histogram, in seconds. Queries are expected to be a lot
+/// faster than requests, which can include password hashing.
+const REPOSITORY_DURATION_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+#[derive(Error, Debug)]
+pub enum MetricsServiceError {
+    #[error("Failed to encode metrics: {info}")]
+    Encoding { info: String },
+}
+
+pub type MetricsServiceResult<T> = Result<T, MetricsServiceError>;
+
+/// Collects the app's Prometheus metrics and renders them in the text exposition format.
+///
+/// Request, login and repository metrics are recorded as they happen. Gauges of things kept
+/// elsewhere (the connection pool, and user and todo totals) are updated just before
+/// rendering, and the user cache's metrics are read from the cache while rendering.
+pub struct MetricsService {
+    registry: Registry,
+    http_request_duration: HistogramVec,
+    auth_attempts: IntCounterVec,
+    repository_operation_duration: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    db_pool_max_connections: IntGauge,
+    users: IntGauge,
+    todos: IntGauge,
+}
+
+impl MetricsService {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Latency of HTTP requests by route and status",
+            ),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let auth_attempts = IntCounterVec::new(
+            Opts::new("auth_attempts_total", "Logins and registrations by outcome"),
+            &["operation", "outcome"],
+        )
+        .unwrap();
+        let repository_operation_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "repository_operation_duration_seconds",
+                "Latency of SQL repository operations",
+            )
+            .buckets(REPOSITORY_DURATION_BUCKETS.to_vec()),
+            &["repository", "operation"],
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Open database connections by state"),
+            &["state"],
+        )
+        .unwrap();
+        let db_pool_max_connections = IntGauge::new(
+            "db_pool_max_connections",
+            "Maximum number of database connections",
+        )
+        .unwrap();
+        let users = IntGauge::new("users", "Registered users").unwrap();
+        let todos = IntGauge::new("todos", "Todos across all users").unwrap();
+
+        for collector in [
+            Box::new(http_request_duration.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(auth_attempts.clone()),
+            Box::new(repository_operation_duration.clone()),
+            Box::new(db_pool_connections.clone()),
+            Box::new(db_pool_max_connections.clone()),
+            Box::new(users.clone()),
+            Box::new(todos.clone()),
+        ] {
+            // names are unique, so registering can't fail
+            registry.register(collector).unwrap();
+        }
+
+        Self {
+            registry,
+            http_request_duration,
+            auth_attempts,
+            repository_operation_duration,
+            db_pool_connections,
+            db_pool_max_connections,
+            users,
+            todos,
+        }
+    }
+
+    /// `route` is the matched route pattern rather than the path, so IDs in paths don't
+    /// create a series per ID.
+    pub fn observe_http_request(&self, method: &str, route: &str, status: u16, latency: Duration) {
+        self.http_request_duration
+            .with_label_values(&[method, route, &status.to_string()])
+            .observe(latency.as_secs_f64());
+    }
+
+    /// `operation` is `login` or `register`.
+    pub fn record_auth_attempt(&self, operation: &str, outcome: &str) {
+        self.auth_attempts
+            .with_label_values(&[operation, outcome])
+            .inc();
+    }
+
+    pub fn observe_repository_operation(
+        &self,
+        repository: &str,
+        operation: &str,
+        latency: Duration,
+    ) {
+        self.repository_operation_duration
+            .with_label_values(&[repository, operation])
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn set_db_pool_stats(&self, pool: &DatabasePool) {
+        let idle = pool.num_idle() as i64;
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(i64::from(pool.size()) - idle);
+        self.db_pool_max_connections
+            .set(i64::from(pool.max_connections()));
+    }
+
+    /// Adds the hit, miss and entry counts of the cache to the metrics. Only one user cache
+    /// can be registered.
+    pub fn register_user_cache(&self, user_cache: Arc<CachedUserRepository>) {
+        self.registry
+            .register(Box::new(UserCacheCollector::new(user_cache)))
+            .expect("a user cache is already registered");
+    }
+
+    pub fn set_totals(&self, users: i64, todos: i64) {
+        self.users.set(users);
+        self.todos.set(todos);
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> MetricsServiceResult<String> {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| MetricsServiceError::Encoding {
+                info: e.to_string(),
+            })?;
+        String::from_utf8(buffer).map_err(|e| MetricsServiceError::Encoding {
+            info: e.to_string(),
+        })
+    }
+}
+
+impl Default for MetricsService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The cache keeps its own running totals, which are turned into metrics on every scrape.
+struct UserCacheCollector {
+    user_cache: Arc<CachedUserRepository>,
+    descs: Vec<Desc>,
+}
+
+impl UserCacheCollector {
+    fn new(user_cache: Arc<CachedUserRepository>) -> Self {
+        let descs = Self::metrics(UserCacheStats::default())
+            .iter()
+            .flat_map(|metric| metric.desc().into_iter().cloned())
+            .collect();
+        Self { user_cache, descs }
+    }
+
+    fn metrics(stats: UserCacheStats) -> Vec<Box<dyn Collector>> {
+        let hits = IntCounter::new(
+            "user_cache_hits_total",
+            "User lookups served from the cache",
+        )
+        .unwrap();
+        hits.inc_by(stats.hits);
+        let misses = IntCounter::new(
+            "user_cache_misses_total",
+            "User lookups which went to the database",
+        )
+        .unwrap();
+        misses.inc_by(stats.misses);
+        let entries = IntGauge::new("user_cache_entries", "Users currently in the cache").unwrap();
+        entries.set(stats.entries as i64);
+        vec![Box::new(hits), Box::new(misses), Box::new(entries)]
+    }
+}
+
+impl Collector for UserCacheCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.descs.iter().collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        Self::metrics(self.user_cache.stats())
+            .iter()
+            .flat_map(|metric| metric.collect())
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::*;
+    use crate::repositories::{
+        in_memory_user_repository::InMemoryUserRepository, user_repository::UserRepository,
+    };
+
+    #[tokio::test]
+    async fn renders_the_user_cache_stats_at_scrape_time() {
+        let user_cache = Arc::new(CachedUserRepository::new(
+            Arc::new(InMemoryUserRepository::default()),
+            NonZeroUsize::new(10).unwrap(),
+            Duration::from_secs(60),
+        ));
+        let metrics_service = MetricsService::new();
+        metrics_service.register_user_cache(Arc::clone(&user_cache));
+
+        let id = user_cache.create_user("gina", "hash").await.unwrap();
+        user_cache.get_user_by_id(&id).await.unwrap();
+        user_cache.get_user_by_id(&id).await.unwrap();
+
+        let rendered = metrics_service.render().unwrap();
+        assert!(rendered.contains("user_cache_hits_total 1\n"));
+        assert!(rendered.contains("user_cache_misses_total 1\n"));
+        assert!(rendered.contains("user_cache_entries 1\n"));
+    }
+}
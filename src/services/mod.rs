@@ -1,3 +1,16 @@
+pub mod argon2_password_hasher;
+pub mod audit_service;
 pub mod auth_service;
+pub mod checklist_service;
+pub mod csp_report_service;
 pub mod db_auth_service;
+pub mod health_service;
+pub mod jwt_session_service;
+pub mod list_service;
+pub mod metrics_service;
+pub mod password_hasher;
+pub mod password_hashing_pool;
+pub mod server_session_service;
+pub mod session_service;
+pub mod tag_service;
 pub mod todo_service;
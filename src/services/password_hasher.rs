@@ -0,0 +1,38 @@
+use thiserror::Error;
+
+use crate::utils::global_auth::ConfigError;
+
+#[derive(Error, Debug)]
+pub enum PasswordHasherError {
+    #[error("Malformed or unsupported password hash")]
+    MalformedHash,
+    #[error("No pepper configured for password hash with key id {id:?}")]
+    UnknownPepper { id: String },
+    #[error("Too many passwords are being hashed, try again later")]
+    Busy,
+    #[error("Password hashing failed: {info}")]
+    Hashing { info: String },
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+pub type PasswordHasherResult<T> = Result<T, PasswordHasherError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordVerification {
+    Invalid,
+    /// The password matches. `needs_rehash` is set when the hash was made with an outdated
+    /// algorithm, cost parameters or pepper, and should be replaced with a fresh hash.
+    Valid {
+        needs_rehash: bool,
+    },
+}
+
+pub trait PasswordHasher: Send + Sync {
+    fn hash_password(&self, password: &str) -> PasswordHasherResult<String>;
+    fn verify_password(
+        &self,
+        password: &str,
+        hash: &str,
+    ) -> PasswordHasherResult<PasswordVerification>;
+}
@@ -0,0 +1,78 @@
+use std::{num::NonZeroUsize, sync::Arc, time::Duration};
+
+use tokio::sync::Semaphore;
+use tracing::instrument;
+
+use super::password_hasher::{
+    PasswordHasher, PasswordHasherError, PasswordHasherResult, PasswordVerification,
+};
+
+/// Runs a [`PasswordHasher`] on tokio's blocking thread pool, so hashing doesn't stall the
+/// async workers serving other requests.
+///
+/// At most `max_concurrency` hashes run at once. Callers wait up to `queue_timeout` for a
+/// slot, after which [`PasswordHasherError::Busy`] is returned rather than queueing forever.
+pub struct PasswordHashingPool {
+    hasher: Arc<dyn PasswordHasher>,
+    permits: Arc<Semaphore>,
+    queue_timeout: Duration,
+}
+
+impl PasswordHashingPool {
+    pub fn new(
+        hasher: Arc<dyn PasswordHasher>,
+        max_concurrency: NonZeroUsize,
+        queue_timeout: Duration,
+    ) -> Self {
+        Self {
+            hasher,
+            permits: Arc::new(Semaphore::new(max_concurrency.get())),
+            queue_timeout,
+        }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn hash_password(&self, password: &str) -> PasswordHasherResult<String> {
+        let password = password.to_owned();
+        self.run(move |hasher| hasher.hash_password(&password))
+            .await
+    }
+
+    #[instrument(skip_all)]
+    pub async fn verify_password(
+        &self,
+        password: &str,
+        hash: &str,
+    ) -> PasswordHasherResult<PasswordVerification> {
+        let password = password.to_owned();
+        let hash = hash.to_owned();
+        self.run(move |hasher| hasher.verify_password(&password, &hash))
+            .await
+    }
+
+    async fn run<T, F>(&self, f: F) -> PasswordHasherResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn PasswordHasher) -> PasswordHasherResult<T> + Send + 'static,
+    {
+        let permit = tokio::time::timeout(
+            self.queue_timeout,
+            Arc::clone(&self.permits).acquire_owned(),
+        )
+        .await
+        .map_err(|_| PasswordHasherError::Busy)?
+        .map_err(|_| PasswordHasherError::Busy)?;
+
+        let hasher = Arc::clone(&self.hasher);
+        // the permit moves into the task, so an abandoned request still holds its slot until
+        // the hash it started has finished
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            f(hasher.as_ref())
+        })
+        .await
+        .map_err(|e| PasswordHasherError::Hashing {
+            info: e.to_string(),
+        })?
+    }
+}
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::instrument;
+
+use crate::{
+    repositories::{
+        audit_repository::AuditEventType,
+        session_store::{NewSession, SessionEntity, SessionStore},
+        RepositoryError,
+    },
+    utils::random_token,
+};
+
+use super::{
+    audit_service::{AuditContext, AuditService},
+    session_service::{SessionInfo, SessionService, SessionServiceResult},
+};
+
+/// `last_seen_at` is only written back when it is at least this stale, to avoid a write per request.
+const LAST_SEEN_UPDATE_INTERVAL_SECS: i64 = 60;
+
+/// Server-side sessions: the session cookie is an opaque random token, and the session
+/// (device, IP, created and last seen times) lives in a [SessionStore] keyed by the token's hash.
+pub struct ServerSessionService {
+    session_store: Arc<dyn SessionStore>,
+    audit_service: Arc<AuditService>,
+    idle_timeout: chrono::Duration,
+    max_age: chrono::Duration,
+}
+
+impl ServerSessionService {
+    pub fn new(
+        session_store: Arc<dyn SessionStore>,
+        audit_service: Arc<AuditService>,
+        idle_timeout: chrono::Duration,
+        max_age: chrono::Duration,
+    ) -> Self {
+        Self {
+            session_store,
+            audit_service,
+            idle_timeout,
+            max_age,
+        }
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[async_trait]
+impl SessionService for ServerSessionService {
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn start_session(
+        &self,
+        user_id: &str,
+        ctx: &AuditContext,
+    ) -> SessionServiceResult<String> {
+        let token = random_token();
+        let session = NewSession {
+            token_hash: hash_token(&token),
+            user_id: user_id.to_owned(),
+            device: ctx.user_agent.clone(),
+            ip: ctx.ip.clone(),
+        };
+        self.session_store.create_session(session).await?;
+
+        Ok(token)
+    }
+
+    #[instrument(skip_all)]
+    async fn resolve_session(&self, token: &str) -> SessionServiceResult<Option<SessionInfo>> {
+        let session = match self
+            .session_store
+            .get_session_by_token_hash(&hash_token(token))
+            .await?
+        {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+
+        let now = Utc::now();
+        let idle_expired = now - session.last_seen_at > self.idle_timeout;
+        let age_expired = now - session.created_at > self.max_age;
+        // the session may be revoked or cleaned up concurrently, which signs it out all the same
+        if idle_expired || age_expired {
+            match self
+                .session_store
+                .delete_session(&session.user_id, &session.id)
+                .await
+            {
+                Ok(()) | Err(RepositoryError::ItemNotFound) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        if now - session.last_seen_at > chrono::Duration::seconds(LAST_SEEN_UPDATE_INTERVAL_SECS) {
+            match self.session_store.touch_session(&session.id, now).await {
+                Ok(()) => {}
+                Err(RepositoryError::ItemNotFound) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(Some(SessionInfo {
+            user_id: session.user_id,
+            session_id: Some(session.id),
+            issued: session.created_at,
+        }))
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn list_sessions(&self, user_id: &str) -> SessionServiceResult<Vec<SessionEntity>> {
+        let sessions = self.session_store.list_user_sessions(user_id).await?;
+        Ok(sessions)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    async fn revoke_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        ctx: &AuditContext,
+    ) -> SessionServiceResult<()> {
+        self.session_store
+            .delete_session(user_id, session_id)
+            .await?;
+        self.audit_service
+            .record(
+                &ctx.with_actor(user_id),
+                AuditEventType::SessionRevoked,
+                json!({ "session_id": session_id }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_expired_sessions(&self) -> SessionServiceResult<u64> {
+        let deleted = self
+            .session_store
+            .delete_sessions_idle_since(Utc::now() - self.idle_timeout)
+            .await?;
+        Ok(deleted)
+    }
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::repositories::{
+        in_memory_audit_repository::InMemoryAuditRepository,
+        in_memory_session_store::InMemorySessionStore, RepositoryResult,
+    };
+
+    /// Revokes each session right after it's looked up, as a concurrent request could
+    struct RevokedAfterLookup(InMemorySessionStore);
+
+    #[async_trait]
+    impl SessionStore for RevokedAfterLookup {
+        async fn create_session(&self, session: NewSession) -> RepositoryResult<SessionEntity> {
+            self.0.create_session(session).await
+        }
+        async fn get_session_by_token_hash(
+            &self,
+            token_hash: &str,
+        ) -> RepositoryResult<Option<SessionEntity>> {
+            let session = self.0.get_session_by_token_hash(token_hash).await?;
+            if let Some(ref session) = session {
+                self.0.delete_session(&session.user_id, &session.id).await?;
+            }
+            Ok(session)
+        }
+        async fn touch_session(
+            &self,
+            id: &str,
+            last_seen_at: DateTime<Utc>,
+        ) -> RepositoryResult<()> {
+            self.0.touch_session(id, last_seen_at).await
+        }
+        async fn list_user_sessions(&self, user_id: &str) -> RepositoryResult<Vec<SessionEntity>> {
+            self.0.list_user_sessions(user_id).await
+        }
+        async fn delete_session(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+            self.0.delete_session(user_id, id).await
+        }
+        async fn delete_sessions_idle_since(&self, cutoff: DateTime<Utc>) -> RepositoryResult<u64> {
+            self.0.delete_sessions_idle_since(cutoff).await
+        }
+    }
+
+    /// Starts a session last seen `idle` ago, returning its token
+    async fn start_idle_session(
+        service: &ServerSessionService,
+        store: &RevokedAfterLookup,
+        idle: chrono::Duration,
+    ) -> String {
+        let token = service
+            .start_session("user", &AuditContext::default())
+            .await
+            .unwrap();
+        let session = store.0.list_user_sessions("user").await.unwrap().remove(0);
+        store
+            .0
+            .touch_session(&session.id, Utc::now() - idle)
+            .await
+            .unwrap();
+        token
+    }
+
+    #[tokio::test]
+    async fn sessions_revoked_while_resolving_are_signed_out() {
+        let store = Arc::new(RevokedAfterLookup(InMemorySessionStore::new()));
+        let service = ServerSessionService::new(
+            Arc::clone(&store) as Arc<dyn SessionStore>,
+            Arc::new(AuditService::new(Arc::new(InMemoryAuditRepository::new()))),
+            chrono::Duration::hours(1),
+            chrono::Duration::days(1),
+        );
+
+        // expired, so it's deleted
+        let token = start_idle_session(&service, &store, chrono::Duration::hours(2)).await;
+        assert!(service.resolve_session(&token).await.unwrap().is_none());
+
+        // last seen a while ago, so it's touched
+        let token = start_idle_session(&service, &store, chrono::Duration::minutes(5)).await;
+        assert!(service.resolve_session(&token).await.unwrap().is_none());
+    }
+}
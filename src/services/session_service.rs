@@ -0,0 +1,73 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::repositories::{session_store::SessionEntity, RepositoryError};
+
+use super::audit_service::{AuditContext, AuditServiceError};
+
+#[derive(Error, Debug)]
+pub enum SessionServiceError {
+    #[error("Not supported by the configured session mode")]
+    Unsupported,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unknown error has occurred: {info:?}")]
+    Unknown { info: Option<String> },
+}
+
+pub type SessionServiceResult<T> = Result<T, SessionServiceError>;
+
+/// The session a request was authenticated with.
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub user_id: String,
+    /// ID of the server-side session, `None` for stateless sessions
+    pub session_id: Option<String>,
+    pub issued: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait SessionService: Send + Sync {
+    /// Starts a session for an authenticated user, returning the value for the session cookie.
+    async fn start_session(
+        &self,
+        user_id: &str,
+        ctx: &AuditContext,
+    ) -> SessionServiceResult<String>;
+    /// Resolves a session cookie value. `None` if it is invalid, expired or revoked.
+    async fn resolve_session(&self, token: &str) -> SessionServiceResult<Option<SessionInfo>>;
+    async fn list_sessions(&self, user_id: &str) -> SessionServiceResult<Vec<SessionEntity>>;
+    async fn revoke_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        ctx: &AuditContext,
+    ) -> SessionServiceResult<()>;
+    /// Removes sessions that have expired, returning how many were removed. Run periodically,
+    /// as expired sessions are otherwise only removed when they are next used.
+    async fn delete_expired_sessions(&self) -> SessionServiceResult<u64>;
+}
+
+impl From<RepositoryError> for SessionServiceError {
+    fn from(value: RepositoryError) -> Self {
+        match value {
+            RepositoryError::ItemNotFound => SessionServiceError::SessionNotFound,
+            RepositoryError::UnknownError { info } => SessionServiceError::Unknown { info },
+            RepositoryError::DatabaseConnectionError { info } => {
+                SessionServiceError::Unknown { info }
+            }
+            RepositoryError::ItemAlreadyExists => SessionServiceError::Unknown {
+                info: Some(String::from("session already exists")),
+            },
+        }
+    }
+}
+
+impl From<AuditServiceError> for SessionServiceError {
+    fn from(value: AuditServiceError) -> Self {
+        match value {
+            AuditServiceError::Unknown { info } => SessionServiceError::Unknown { info },
+        }
+    }
+}
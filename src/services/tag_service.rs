@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+use tracing::instrument;
+
+use crate::repositories::{
+    tag_repository::{TagEntity, TagRepository},
+    unit_of_work::UnitOfWork,
+    RepositoryError,
+};
+
+/// Longest tag name, the size of the `name` column
+const MAX_TAG_NAME_LEN: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum TagServiceError {
+    #[error("{0}")]
+    InvalidName(String),
+    #[error("You already have a tag with that name")]
+    NameTaken,
+    #[error("Item not found")]
+    ItemNotFound,
+    #[error("Unknown error has occurred: {info:?}")]
+    Unknown { info: Option<String> },
+}
+
+pub type TagServiceResult<T> = Result<T, TagServiceError>;
+
+/// Manages the user's tags. Tags are created by giving them to todos, see `TodoService`.
+pub struct TagService {
+    tag_repository: Arc<dyn TagRepository>,
+    unit_of_work: Arc<dyn UnitOfWork>,
+}
+
+impl TagService {
+    pub fn new(tag_repository: Arc<dyn TagRepository>, unit_of_work: Arc<dyn UnitOfWork>) -> Self {
+        Self {
+            tag_repository,
+            unit_of_work,
+        }
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn list_tags(&self, user_id: &str) -> TagServiceResult<Vec<TagEntity>> {
+        Ok(self.tag_repository.list_tags(user_id).await?)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn rename_tag(&self, user_id: &str, id: &str, name: &str) -> TagServiceResult<()> {
+        let name = validate_tag_name(name)?;
+        self.tag_repository.rename_tag(user_id, id, name).await?;
+        Ok(())
+    }
+
+    /// Moves the todos of the source tag to the target tag, which is what renaming a tag to the
+    /// name of another would do
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn merge_tags(
+        &self,
+        user_id: &str,
+        source_id: &str,
+        target_id: &str,
+    ) -> TagServiceResult<()> {
+        if source_id == target_id {
+            return Ok(());
+        }
+
+        let tx = self.unit_of_work.begin().await?;
+        tx.tags().merge_tags(user_id, source_id, target_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn delete_tag(&self, user_id: &str, id: &str) -> TagServiceResult<()> {
+        self.tag_repository.delete_tag(user_id, id).await?;
+        Ok(())
+    }
+}
+
+/// The name without surrounding whitespace, which must be non-empty, fit the `name` column and
+/// have no commas, as the todo forms take tags separated by commas
+pub fn validate_tag_name(name: &str) -> TagServiceResult<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(TagServiceError::InvalidName(
+            "Tag name can't be empty".to_owned(),
+        ));
+    }
+    if name.contains(',') {
+        return Err(TagServiceError::InvalidName(format!(
+            "Tag name can't contain commas: {name}"
+        )));
+    }
+    if name.chars().count() > MAX_TAG_NAME_LEN {
+        return Err(TagServiceError::InvalidName(format!(
+            "Tag name can't be longer than {MAX_TAG_NAME_LEN} characters: {name}"
+        )));
+    }
+    Ok(name)
+}
+
+impl From<RepositoryError> for TagServiceError {
+    fn from(value: RepositoryError) -> Self {
+        match value {
+            RepositoryError::ItemNotFound => TagServiceError::ItemNotFound,
+            RepositoryError::ItemAlreadyExists => TagServiceError::NameTaken,
+            RepositoryError::UnknownError { info } => TagServiceError::Unknown { info },
+            RepositoryError::DatabaseConnectionError { info } => TagServiceError::Unknown { info },
+        }
+    }
+}
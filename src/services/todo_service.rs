@@ -1,14 +1,29 @@
+use std::{slice, sync::Arc};
+
+use chrono::{NaiveDate, Utc};
+use serde_json::json;
 use thiserror::Error;
+use tracing::instrument;
 
-use crate::repositories::{
-    todo_repository::{TodoEntity, TodoRepository},
-    RepositoryError,
+use crate::{
+    repositories::{
+        audit_repository::AuditEventType,
+        list_repository::ListRepository,
+        todo_repository::{MoveDirection, TodoDetails, TodoEntity, TodoFilter, TodoRepository},
+        unit_of_work::{RepositoryTransaction, UnitOfWork},
+        RepositoryError, RepositoryResult,
+    },
+    utils::todo_search::TodoSearch,
 };
 
+use super::{audit_service::AuditContext, tag_service::validate_tag_name};
+
 #[derive(Error, Debug)]
 pub enum TodoServiceError {
     #[error("Duplicate item")]
     DuplicateItem,
+    #[error("{0}")]
+    InvalidTag(String),
     #[error("Item not found")]
     ItemNotFound,
     #[error("Unknown error has occurred: {info:?}")]
@@ -18,23 +33,264 @@ pub enum TodoServiceError {
 pub type TodoServiceResult<T> = Result<T, TodoServiceError>;
 
 pub struct TodoService {
-    todo_repository: Box<dyn TodoRepository>,
+    todo_repository: Arc<dyn TodoRepository>,
+    list_repository: Arc<dyn ListRepository>,
+    unit_of_work: Arc<dyn UnitOfWork>,
 }
 
 impl TodoService {
-    pub fn new(todo_repository: Box<dyn TodoRepository>) -> Self {
-        Self { todo_repository }
+    pub fn new(
+        todo_repository: Arc<dyn TodoRepository>,
+        list_repository: Arc<dyn ListRepository>,
+        unit_of_work: Arc<dyn UnitOfWork>,
+    ) -> Self {
+        Self {
+            todo_repository,
+            list_repository,
+            unit_of_work,
+        }
     }
 
-    pub async fn add_todo(&self, user_id: &str, name: &str) -> TodoServiceResult<()> {
-        self.todo_repository.add_todo(user_id, name).await?;
-        Ok(())
+    /// Adds the todo to the user's list, or their inbox without `list_id`
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn add_todo(
+        &self,
+        user_id: &str,
+        list_id: Option<&str>,
+        name: &str,
+        details: &TodoDetails,
+        tags: &[String],
+    ) -> TodoServiceResult<String> {
+        let tags = validate_tags(tags)?;
+        self.check_list_owner(user_id, list_id).await?;
+
+        let tx = self.unit_of_work.begin().await?;
+        let id = tx.todos().add_todo(user_id, list_id, name, details).await?;
+        if !tags.is_empty() {
+            tx.tags().set_todo_tags(user_id, &id, &tags).await?;
+        }
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn list_todos(
+        &self,
+        user_id: &str,
+        filter: &TodoFilter,
+    ) -> TodoServiceResult<Vec<TodoEntity>> {
+        let todos = self.todo_repository.list_todos(user_id, filter).await?;
+        Ok(todos)
     }
 
-    pub async fn list_todos(&self, user_id: &str) -> TodoServiceResult<Vec<TodoEntity>> {
-        let todos = self.todo_repository.list_todos(user_id).await?;
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn search_todos(
+        &self,
+        user_id: &str,
+        search: &TodoSearch,
+    ) -> TodoServiceResult<Vec<TodoEntity>> {
+        let todos = self.todo_repository.search_todos(user_id, search).await?;
         Ok(todos)
     }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn get_todo(&self, user_id: &str, id: &str) -> TodoServiceResult<TodoEntity> {
+        self.todo_repository
+            .get_todo(user_id, id)
+            .await?
+            .ok_or(TodoServiceError::ItemNotFound)
+    }
+
+    /// Updates the due date and priority of the todo, and replaces its tags
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn update_todo_details(
+        &self,
+        user_id: &str,
+        id: &str,
+        details: &TodoDetails,
+        tags: &[String],
+    ) -> TodoServiceResult<()> {
+        let tags = validate_tags(tags)?;
+
+        let tx = self.unit_of_work.begin().await?;
+        tx.todos().update_todo_details(user_id, id, details).await?;
+        tx.tags().set_todo_tags(user_id, id, &tags).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn remove_todo(
+        &self,
+        user_id: &str,
+        id: &str,
+        ctx: &AuditContext,
+    ) -> TodoServiceResult<()> {
+        let tx = self.unit_of_work.begin().await?;
+        tx.todos().remove_todo(user_id, id).await?;
+        tx.audit()
+            .append_event(
+                ctx.with_actor(user_id)
+                    .event(AuditEventType::TodoDeleted, json!({ "todo_id": id })),
+            )
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Marks the todo complete or not, adding the next occurrence of a recurring todo
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn set_todo_complete(
+        &self,
+        user_id: &str,
+        id: &str,
+        complete: bool,
+    ) -> TodoServiceResult<()> {
+        let tx = self.unit_of_work.begin().await?;
+        let todo = tx
+            .todos()
+            .get_todo(user_id, id)
+            .await?
+            .ok_or(TodoServiceError::ItemNotFound)?;
+        set_todo_complete_in(tx.as_ref(), user_id, &todo, complete).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn move_todo(
+        &self,
+        user_id: &str,
+        id: &str,
+        direction: MoveDirection,
+    ) -> TodoServiceResult<()> {
+        self.todo_repository
+            .move_todo(user_id, id, direction)
+            .await?;
+        Ok(())
+    }
+
+    /// Moves the todo to another of the user's lists, or their inbox without `list_id`
+    #[instrument(skip_all, fields(user_id = %user_id))]
+    pub async fn move_todo_to_list(
+        &self,
+        user_id: &str,
+        id: &str,
+        list_id: Option<&str>,
+    ) -> TodoServiceResult<()> {
+        self.check_list_owner(user_id, list_id).await?;
+        self.todo_repository
+            .move_todo_to_list(user_id, id, list_id)
+            .await?;
+        Ok(())
+    }
+
+    /// The foreign key only checks that a list exists, so todos could be put in another user's
+    /// list without this
+    async fn check_list_owner(
+        &self,
+        user_id: &str,
+        list_id: Option<&str>,
+    ) -> TodoServiceResult<()> {
+        if let Some(list_id) = list_id {
+            if self
+                .list_repository
+                .get_list(user_id, list_id)
+                .await?
+                .is_none()
+            {
+                return Err(TodoServiceError::ItemNotFound);
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of todos across all users
+    pub async fn count_todos(&self) -> TodoServiceResult<i64> {
+        Ok(self.todo_repository.count_todos().await?)
+    }
+}
+
+/// Marks the todo complete or not in the transaction. Completing a recurring todo adds its next
+/// occurrence to the end of its list, which takes the recurrence over from it, so reopening and
+/// completing the todo again doesn't add another. The occurrence is only added by the
+/// transaction which actually completed the todo, not by one completing it concurrently.
+pub async fn set_todo_complete_in(
+    tx: &dyn RepositoryTransaction,
+    user_id: &str,
+    todo: &TodoEntity,
+    complete: bool,
+) -> RepositoryResult<()> {
+    let changed = tx
+        .todos()
+        .set_todo_complete(user_id, &todo.id, complete)
+        .await?;
+    if !complete || !changed {
+        return Ok(());
+    }
+    if let Some(recurrence) = &todo.recurrence {
+        let today = Utc::now().date_naive();
+        if let Some(due_date) = recurrence.next_due_date(todo.due_date, today) {
+            add_next_occurrence(tx, user_id, todo, due_date).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the todo with the due date, along with its tags, unticked checklist and recurrence
+async fn add_next_occurrence(
+    tx: &dyn RepositoryTransaction,
+    user_id: &str,
+    todo: &TodoEntity,
+    due_date: NaiveDate,
+) -> RepositoryResult<()> {
+    let details = TodoDetails {
+        due_date: Some(due_date),
+        due_time: todo.due_time,
+        priority: todo.priority,
+        recurrence: todo.recurrence.clone(),
+    };
+    let id = tx
+        .todos()
+        .add_todo(user_id, todo.list_id.as_deref(), &todo.name, &details)
+        .await?;
+    if !todo.tags.is_empty() {
+        tx.tags().set_todo_tags(user_id, &id, &todo.tags).await?;
+    }
+    let items = tx
+        .checklists()
+        .list_items(user_id, slice::from_ref(&todo.id))
+        .await?;
+    for item in items {
+        tx.checklists().add_item(user_id, &id, &item.name).await?;
+    }
+    if todo.auto_complete {
+        tx.todos()
+            .set_todo_auto_complete(user_id, &id, true)
+            .await?;
+    }
+
+    let details = TodoDetails {
+        due_date: todo.due_date,
+        recurrence: None,
+        ..details
+    };
+    tx.todos()
+        .update_todo_details(user_id, &todo.id, &details)
+        .await
+}
+
+/// The tag names without surrounding whitespace or duplicates, in the order given
+fn validate_tags(tags: &[String]) -> TodoServiceResult<Vec<String>> {
+    let mut valid: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag =
+            validate_tag_name(tag).map_err(|e| TodoServiceError::InvalidTag(e.to_string()))?;
+        if !valid.iter().any(|other| other == tag) {
+            valid.push(tag.to_owned());
+        }
+    }
+    Ok(valid)
 }
 
 impl From<RepositoryError> for TodoServiceError {
@@ -47,3 +303,148 @@ impl From<RepositoryError> for TodoServiceError {
         }
     }
 }
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use crate::{
+        repositories::{
+            repository_tests::{date, in_memory, Repositories},
+            todo_repository::{ChecklistProgress, TodoPriority},
+        },
+        utils::recurrence::Recurrence,
+    };
+
+    use super::*;
+
+    async fn list_todos(repos: &Repositories, user_id: &str, list_id: &str) -> Vec<TodoEntity> {
+        let filter = TodoFilter {
+            list_id: Some(list_id.to_owned()),
+            ..Default::default()
+        };
+        repos.todos.list_todos(user_id, &filter).await.unwrap()
+    }
+
+    async fn item_names(repos: &Repositories, user_id: &str, todo_id: &str) -> Vec<(String, bool)> {
+        let items = repos
+            .checklists
+            .list_items(user_id, &[todo_id.to_owned()])
+            .await
+            .unwrap();
+        items
+            .into_iter()
+            .map(|item| (item.name, item.is_complete))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn completing_a_recurring_todo_adds_its_next_occurrence_once() {
+        let repos = in_memory();
+        let service = TodoService::new(
+            Arc::clone(&repos.todos),
+            Arc::clone(&repos.lists),
+            Arc::clone(&repos.unit_of_work),
+        );
+        let user = repos.users.create_user("gina", "hash").await.unwrap();
+        let list = repos.lists.create_list(&user, "Chores").await.unwrap();
+        let recurrence = Recurrence::Daily { interval: 2 };
+        // due in the future, so the next occurrence doesn't depend on today
+        let details = TodoDetails {
+            due_date: Some(date(2100, 1, 1)),
+            due_time: None,
+            priority: TodoPriority::High,
+            recurrence: Some(recurrence.clone()),
+        };
+        let tags = ["home".to_owned(), "garden".to_owned()];
+        let id = service
+            .add_todo(&user, Some(&list), "Water plants", &details, &tags)
+            .await
+            .unwrap();
+        for name in ["Fill can", "Water"] {
+            repos.checklists.add_item(&user, &id, name).await.unwrap();
+        }
+        let items = repos
+            .checklists
+            .list_items(&user, slice::from_ref(&id))
+            .await
+            .unwrap();
+        repos
+            .checklists
+            .set_item_complete(&user, &id, &items[0].id, true)
+            .await
+            .unwrap();
+        repos
+            .todos
+            .set_todo_auto_complete(&user, &id, true)
+            .await
+            .unwrap();
+
+        service.set_todo_complete(&user, &id, true).await.unwrap();
+
+        let todos = list_todos(&repos, &user, &list).await;
+        assert_eq!(todos.len(), 2);
+        let (done, next) = (&todos[0], &todos[1]);
+        assert_eq!(done.id, id);
+        assert!(done.is_complete);
+        assert_eq!(done.recurrence, None);
+        assert_eq!(done.due_date, Some(date(2100, 1, 1)));
+        assert_eq!(next.name, "Water plants");
+        assert!(!next.is_complete);
+        assert_eq!(next.list_id.as_deref(), Some(list.as_str()));
+        assert_eq!(next.due_date, Some(date(2100, 1, 3)));
+        assert_eq!(next.priority, TodoPriority::High);
+        assert_eq!(next.tags, ["garden", "home"]);
+        assert_eq!(next.recurrence, Some(recurrence));
+        assert!(next.auto_complete);
+        assert_eq!(next.checklist, ChecklistProgress { done: 0, total: 2 });
+        assert_eq!(
+            item_names(&repos, &user, &next.id).await,
+            [("Fill can".to_owned(), false), ("Water".to_owned(), false)]
+        );
+        // the original's checklist is left as it was
+        assert_eq!(
+            item_names(&repos, &user, &id).await,
+            [("Fill can".to_owned(), true), ("Water".to_owned(), false)]
+        );
+
+        // the recurrence moved to the next occurrence, so the original doesn't add another
+        service.set_todo_complete(&user, &id, false).await.unwrap();
+        service.set_todo_complete(&user, &id, true).await.unwrap();
+        service.set_todo_complete(&user, &id, true).await.unwrap();
+        let ids: Vec<_> = list_todos(&repos, &user, &list)
+            .await
+            .into_iter()
+            .map(|todo| todo.id)
+            .collect();
+        assert_eq!(ids, [id.clone(), next.id.clone()]);
+
+        service
+            .set_todo_complete(&user, &next.id, true)
+            .await
+            .unwrap();
+        let todos = list_todos(&repos, &user, &list).await;
+        assert_eq!(todos.len(), 3);
+        assert_eq!(todos[2].due_date, Some(date(2100, 1, 5)));
+    }
+
+    #[tokio::test]
+    async fn completing_a_todo_without_recurrence_adds_nothing() {
+        let repos = in_memory();
+        let service = TodoService::new(
+            Arc::clone(&repos.todos),
+            Arc::clone(&repos.lists),
+            Arc::clone(&repos.unit_of_work),
+        );
+        let user = repos.users.create_user("gina", "hash").await.unwrap();
+        let list = repos.lists.create_list(&user, "Chores").await.unwrap();
+        let id = service
+            .add_todo(&user, Some(&list), "Once", &Default::default(), &[])
+            .await
+            .unwrap();
+
+        service.set_todo_complete(&user, &id, true).await.unwrap();
+
+        let todos = list_todos(&repos, &user, &list).await;
+        assert_eq!(todos.len(), 1);
+        assert!(todos[0].is_complete);
+    }
+}
@@ -0,0 +1,35 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+};
+
+use actix_web::{dev::Payload, http::header::USER_AGENT, FromRequest, HttpMessage, HttpRequest};
+
+use crate::{services::audit_service::AuditContext, utils::authenticated_user::AuthenticatedUser};
+
+/// Builds an [AuditContext] from the request. The actor is taken from the session user
+/// (if `JwtSession` has run), and the IP honours `Forwarded`/`X-Forwarded-For` as we
+/// expect to be deployed behind a proxy.
+impl FromRequest for AuditContext {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let actor_id = req
+            .extensions()
+            .get::<AuthenticatedUser>()
+            .map(|u| u.id.clone());
+        let ip = req.connection_info().realip_remote_addr().map(String::from);
+        let user_agent = req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+
+        ready(Ok(AuditContext {
+            actor_id,
+            ip,
+            user_agent,
+        }))
+    }
+}
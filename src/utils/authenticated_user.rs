@@ -0,0 +1,119 @@
+use actix_web::{
+    dev::Payload,
+    error::{ErrorInternalServerError, InternalError},
+    http::{header::LOCATION, Method},
+    web::Data,
+    Error, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use chrono::{DateTime, Utc};
+use futures_util::future::LocalBoxFuture;
+
+use crate::{
+    repositories::user_repository::{UserEntity, UserRole},
+    services::session_service::SessionInfo,
+    utils::{global_auth::SESSION_COOKIE_NAME, redirect_target::login_redirect_location},
+    AppState,
+};
+
+/// The signed in user and their session. Only carries what handlers need to know about the
+/// user, so credentials like the password hash never reach them.
+///
+/// Inside the `/home` scope this is what `JwtSession` resolved. Elsewhere the session cookie
+/// is resolved on demand, so `Option<AuthenticatedUser>` can be used by public pages to
+/// check whether someone is signed in.
+///
+/// If there's no signed in user, GET requests are redirected to the login page and other
+/// requests are rejected with 401 Unauthorized.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub id: String,
+    pub username: String,
+    pub role: UserRole,
+    /// ID of the server-side session, `None` for stateless sessions
+    pub session_id: Option<String>,
+    /// When the session was started, i.e. when the user logged in
+    pub issued: DateTime<Utc>,
+}
+
+impl AuthenticatedUser {
+    pub fn new(user: UserEntity, session: SessionInfo) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            role: user.role,
+            session_id: session.session_id,
+            issued: session.issued,
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.role == UserRole::Admin
+    }
+
+    /// Resolves the session cookie of the request, `None` if it's missing, invalid or expired.
+    pub async fn from_session_cookie(req: &HttpRequest) -> Result<Option<Self>, Error> {
+        let token_cookie = match req.cookie(SESSION_COOKIE_NAME) {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+
+        let app_state = req
+            .app_data::<Data<AppState>>()
+            .expect("Fatal: could not access app data");
+
+        let session = app_state
+            .session_service
+            .resolve_session(token_cookie.value())
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        let session = match session {
+            Some(val) => val,
+            None => return Ok(None),
+        };
+
+        let user = app_state
+            .user_repository
+            .get_user_by_id(&session.user_id)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(user.map(|user| AuthenticatedUser::new(user, session)))
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let resolved = req.extensions().get::<AuthenticatedUser>().cloned();
+
+        let req = req.clone();
+        Box::pin(async move {
+            let user = match resolved {
+                Some(user) => Some(user),
+                None => {
+                    let user = AuthenticatedUser::from_session_cookie(&req).await?;
+                    if let Some(user) = &user {
+                        // so the request is logged with the user, as if `JwtSession` had run
+                        req.extensions_mut().insert(user.clone());
+                    }
+                    user
+                }
+            };
+            user.ok_or_else(|| not_authenticated_error(&req))
+        })
+    }
+}
+
+fn not_authenticated_error(req: &HttpRequest) -> Error {
+    let response = if req.method() == Method::GET {
+        HttpResponse::Found()
+            .insert_header((LOCATION, login_redirect_location(req)))
+            .finish()
+    } else {
+        HttpResponse::Unauthorized().body("Unauthorized")
+    };
+    InternalError::from_response("Not signed in", response).into()
+}
@@ -1,14 +1,161 @@
-use hmac::{Hmac, Mac};
-use sha2::Sha256;
+use std::{num::NonZeroUsize, str::FromStr, time::Duration};
 
-pub const JWT_AUTH_COOKIE_NAME: &str = "JWT";
+use thiserror::Error;
+
+pub const SESSION_COOKIE_NAME: &str = "SESSION";
 pub const JWT_AUTH_EXPIRATION_MINS: i64 = 10;
+pub const SERVER_SESSION_IDLE_TIMEOUT_MINS: i64 = 30;
+pub const SERVER_SESSION_MAX_AGE_MINS: i64 = 7 * 24 * 60;
+/// Argon2id costs for new password hashes, per the OWASP minimum recommendation
+pub const PASSWORD_HASH_M_COST_KIB: u32 = 19 * 1024;
+pub const PASSWORD_HASH_T_COST: u32 = 2;
+pub const PASSWORD_HASH_P_COST: u32 = 1;
+/// How long a login or registration waits for a free hashing slot before giving up
+pub const PASSWORD_HASH_QUEUE_TIMEOUT_MS: u64 = 2000;
+/// Number of users kept in the user lookup cache, 0 disables the cache
+pub const USER_CACHE_CAPACITY: usize = 1024;
+pub const USER_CACHE_TTL_SECS: u64 = 60;
+/// How long in-flight requests get to finish when shutting down
+pub const SHUTDOWN_TIMEOUT_SECS: u64 = 25;
+/// Pepper id given to `HASH_SECRET` when `PASSWORD_PEPPER` isn't set
+pub const HASH_SECRET_PEPPER_ID: &str = "hs";
+
+/// A setting in env the app can't run with
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("{name} must be {expected}, got '{value}'")]
+    Invalid {
+        name: String,
+        expected: &'static str,
+        value: String,
+    },
+    #[error("{0} must be set")]
+    Missing(&'static str),
+    #[error("{0} and {1} must be set together")]
+    Unpaired(&'static str, &'static str),
+}
+
+pub type ConfigResult<T> = Result<T, ConfigError>;
+
+/// How sessions are represented in the session cookie, selected with `SESSION_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionMode {
+    /// Stateless signed JWTs (`SESSION_MODE=jwt`, the default)
+    Jwt,
+    /// Opaque session IDs backed by a server-side session store (`SESSION_MODE=server`)
+    Server,
+}
+
+pub fn get_session_mode() -> ConfigResult<SessionMode> {
+    match std::env::var("SESSION_MODE").as_deref() {
+        Err(_) | Ok("jwt") => Ok(SessionMode::Jwt),
+        Ok("server") => Ok(SessionMode::Server),
+        Ok(other) => Err(ConfigError::Invalid {
+            name: String::from("SESSION_MODE"),
+            expected: "'jwt' or 'server'",
+            value: other.to_owned(),
+        }),
+    }
+}
+
+/// Server-side sessions not seen for this long are expired (`SESSION_IDLE_TIMEOUT_MINS`).
+pub fn get_session_idle_timeout() -> ConfigResult<chrono::Duration> {
+    Ok(chrono::Duration::minutes(parse_env(
+        "SESSION_IDLE_TIMEOUT_MINS",
+        "a number of minutes",
+        SERVER_SESSION_IDLE_TIMEOUT_MINS,
+    )?))
+}
+
+/// Server-side sessions older than this are expired regardless of activity (`SESSION_MAX_AGE_MINS`).
+pub fn get_session_max_age() -> ConfigResult<chrono::Duration> {
+    Ok(chrono::Duration::minutes(parse_env(
+        "SESSION_MAX_AGE_MINS",
+        "a number of minutes",
+        SERVER_SESSION_MAX_AGE_MINS,
+    )?))
+}
+
+/// Capacity of the user lookup cache (`USER_CACHE_CAPACITY`), `None` when disabled.
+pub fn get_user_cache_capacity() -> ConfigResult<Option<NonZeroUsize>> {
+    let capacity = parse_env("USER_CACHE_CAPACITY", "a number", USER_CACHE_CAPACITY)?;
+    Ok(NonZeroUsize::new(capacity))
+}
+
+/// How long users stay in the lookup cache (`USER_CACHE_TTL_SECS`).
+pub fn get_user_cache_ttl() -> ConfigResult<Duration> {
+    Ok(Duration::from_secs(parse_env(
+        "USER_CACHE_TTL_SECS",
+        "a number of seconds",
+        USER_CACHE_TTL_SECS,
+    )?))
+}
+
+/// Whether cookies are marked `Secure` (`SECURE_COOKIES`), defaulting to whether the app is
+/// serving HTTPS itself. Set it when TLS is terminated by a proxy instead.
+pub fn get_secure_cookies() -> ConfigResult<bool> {
+    let serving_https = std::env::var("TLS_CERT_PATH").is_ok();
+    parse_env("SECURE_COOKIES", "'true' or 'false'", serving_https)
+}
+
+/// Bearer token required to scrape `/metrics` (`METRICS_TOKEN`), `None` disables the endpoint.
+pub fn get_metrics_token() -> Option<String> {
+    std::env::var("METRICS_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+/// Seconds in-flight requests get to finish on shutdown (`SHUTDOWN_TIMEOUT_SECS`), after
+/// which they are dropped. Keep it below the orchestrator's grace period before it kills us.
+pub fn get_shutdown_timeout_secs() -> ConfigResult<u64> {
+    parse_env(
+        "SHUTDOWN_TIMEOUT_SECS",
+        "a number of seconds",
+        SHUTDOWN_TIMEOUT_SECS,
+    )
+}
+
+/// Legacy password hash secret, also used as the pepper if `PASSWORD_PEPPER` isn't set.
+pub fn get_password_hash_secret() -> Option<String> {
+    std::env::var("HASH_SECRET").ok()
+}
+
+/// Argon2id memory, time and parallelism costs (`PASSWORD_HASH_{M,T,P}_COST`).
+pub fn get_password_hash_costs() -> ConfigResult<(u32, u32, u32)> {
+    let positive = "a positive number";
+    Ok((
+        parse_env("PASSWORD_HASH_M_COST", positive, PASSWORD_HASH_M_COST_KIB)?,
+        parse_env("PASSWORD_HASH_T_COST", positive, PASSWORD_HASH_T_COST)?,
+        parse_env("PASSWORD_HASH_P_COST", positive, PASSWORD_HASH_P_COST)?,
+    ))
+}
+
+/// Maximum number of concurrent password hashes (`PASSWORD_HASH_MAX_CONCURRENCY`),
+/// defaulting to the number of CPUs. With no slots every login would time out in the queue,
+/// so 0 is rejected.
+pub fn get_password_hash_max_concurrency() -> ConfigResult<NonZeroUsize> {
+    let cpus = std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+    parse_env("PASSWORD_HASH_MAX_CONCURRENCY", "a positive number", cpus)
+}
 
-pub fn get_jwt_signing_key() -> Hmac<Sha256> {
-    let jwt_secret: String = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set!");
-    Hmac::new_from_slice(jwt_secret.as_bytes()).expect("JWT_SECRET is not valid")
+/// How long to wait for a free hashing slot (`PASSWORD_HASH_QUEUE_TIMEOUT_MS`).
+pub fn get_password_hash_queue_timeout() -> ConfigResult<Duration> {
+    Ok(Duration::from_millis(parse_env(
+        "PASSWORD_HASH_QUEUE_TIMEOUT_MS",
+        "a number",
+        PASSWORD_HASH_QUEUE_TIMEOUT_MS,
+    )?))
 }
 
-pub fn get_password_hash_secret() -> String {
-    std::env::var("HASH_SECRET").expect("HASH_SECRET must be set!")
+/// Parses the setting `name`, `expected` describing what it must be, or returns `default`
+/// when it isn't set.
+pub fn parse_env<T: FromStr>(name: &str, expected: &'static str, default: T) -> ConfigResult<T> {
+    match std::env::var(name) {
+        Ok(value) => value.parse().map_err(|_| ConfigError::Invalid {
+            name: name.to_owned(),
+            expected,
+            value,
+        }),
+        Err(_) => Ok(default),
+    }
 }
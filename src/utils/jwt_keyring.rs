@@ -0,0 +1,360 @@
+//! Keys used to sign and verify session JWTs.
+//!
+//! Exactly one key signs new tokens, every token carries the `kid` of the key that signed it,
+//! and any key in the ring can verify. Keys are configured from the env:
+//! * `JWT_SIGNING_KEY` - `<kid>:<alg>:<path>` of the key that signs new tokens. Falls back
+//!   to an HS256 key made from `JWT_SECRET` when unset.
+//! * `JWT_VERIFICATION_KEYS` - comma separated `<kid>:<alg>:<path>[@<retired at>]` of
+//!   additional keys accepted for verification. A key with a retirement time (RFC 3339) is
+//!   accepted until tokens it signed before retiring have expired, and is then dropped.
+//!
+//! `<alg>` is one of `EdDSA` or `ES256` with `<path>` to a PEM file (a PKCS#8 private key, or
+//! a public key for verification-only keys), or `HS256` with `<path>` to a file holding the secret.
+//! Public keys of the asymmetric keys are published as a JWKS.
+
+use std::fs;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use super::global_auth::JWT_AUTH_EXPIRATION_MINS;
+
+/// kid of the HS256 key derived from `JWT_SECRET`
+pub const JWT_SECRET_KID: &str = "jwt-secret";
+
+#[derive(Error, Debug)]
+pub enum JwtKeyringError {
+    #[error("invalid key spec '{spec}', expected <kid>:<alg>:<path>[@<retired at>]")]
+    InvalidKeySpec { spec: String },
+    #[error("unsupported algorithm '{alg}' for key '{kid}', expected EdDSA, ES256 or HS256")]
+    UnsupportedAlgorithm { kid: String, alg: String },
+    #[error("failed to read key '{kid}' from {path}: {source}")]
+    Io {
+        kid: String,
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid key '{kid}': {info}")]
+    InvalidKey { kid: String, info: String },
+    #[error("signing key '{kid}' must be a private key")]
+    SigningKeyNotPrivate { kid: String },
+    #[error("duplicate key id '{kid}'")]
+    DuplicateKid { kid: String },
+    #[error("no signing key, set JWT_SIGNING_KEY or JWT_SECRET")]
+    NoSigningKey,
+}
+
+pub struct JwtKey {
+    kid: String,
+    algorithm: Algorithm,
+    /// only present for private keys
+    encoding_key: Option<EncodingKey>,
+    decoding_key: DecodingKey,
+    /// public JWK, `None` for symmetric keys which must never be published
+    jwk: Option<Jwk>,
+    retired_at: Option<DateTime<Utc>>,
+}
+
+impl JwtKey {
+    pub fn from_secret(kid: &str, secret: &[u8]) -> Self {
+        Self {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::HS256,
+            encoding_key: Some(EncodingKey::from_secret(secret)),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+            retired_at: None,
+        }
+    }
+
+    pub fn from_ed_pem(kid: &str, pem: &str) -> Result<Self, JwtKeyringError> {
+        let invalid = |info: String| JwtKeyringError::InvalidKey {
+            kid: kid.to_owned(),
+            info,
+        };
+
+        let (encoding_key, public_key) = if is_private_pem(pem) {
+            let signing_key = ed25519_dalek::SigningKey::from_pkcs8_pem(pem)
+                .map_err(|e| invalid(e.to_string()))?;
+            let encoding_key =
+                EncodingKey::from_ed_pem(pem.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+            (Some(encoding_key), signing_key.verifying_key())
+        } else {
+            let public_key = ed25519_dalek::VerifyingKey::from_public_key_pem(pem)
+                .map_err(|e| invalid(e.to_string()))?;
+            (None, public_key)
+        };
+
+        let x = URL_SAFE_NO_PAD.encode(public_key.as_bytes());
+        let decoding_key =
+            DecodingKey::from_ed_components(&x).map_err(|e| invalid(e.to_string()))?;
+        let jwk = public_jwk(
+            kid,
+            KeyAlgorithm::EdDSA,
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x,
+            }),
+        );
+
+        Ok(Self {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::EdDSA,
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+            retired_at: None,
+        })
+    }
+
+    pub fn from_es256_pem(kid: &str, pem: &str) -> Result<Self, JwtKeyringError> {
+        let invalid = |info: String| JwtKeyringError::InvalidKey {
+            kid: kid.to_owned(),
+            info,
+        };
+
+        let (encoding_key, public_key) = if is_private_pem(pem) {
+            let secret_key =
+                p256::SecretKey::from_pkcs8_pem(pem).map_err(|e| invalid(e.to_string()))?;
+            let encoding_key =
+                EncodingKey::from_ec_pem(pem.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+            (Some(encoding_key), secret_key.public_key())
+        } else {
+            let public_key =
+                p256::PublicKey::from_public_key_pem(pem).map_err(|e| invalid(e.to_string()))?;
+            (None, public_key)
+        };
+
+        let point = p256::EncodedPoint::from(public_key);
+        let (x, y) = match (point.x(), point.y()) {
+            (Some(x), Some(y)) => (URL_SAFE_NO_PAD.encode(x), URL_SAFE_NO_PAD.encode(y)),
+            _ => return Err(invalid(String::from("unexpected EC point encoding"))),
+        };
+        let decoding_key =
+            DecodingKey::from_ec_components(&x, &y).map_err(|e| invalid(e.to_string()))?;
+        let jwk = public_jwk(
+            kid,
+            KeyAlgorithm::ES256,
+            AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve: EllipticCurve::P256,
+                x,
+                y,
+            }),
+        );
+
+        Ok(Self {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::ES256,
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+            retired_at: None,
+        })
+    }
+
+    /// Parses and loads a `<kid>:<alg>:<path>[@<retired at>]` key spec.
+    pub fn from_spec(spec: &str) -> Result<Self, JwtKeyringError> {
+        let mut parts = spec.trim().splitn(3, ':');
+        let (kid, alg, rest) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(kid), Some(alg), Some(rest)) if !kid.is_empty() => (kid, alg, rest),
+            _ => {
+                return Err(JwtKeyringError::InvalidKeySpec {
+                    spec: spec.to_owned(),
+                })
+            }
+        };
+        // paths may contain '@' too, so only a timestamp after the last one is a retirement time
+        let retirement = rest.rsplit_once('@').and_then(|(path, retired_at)| {
+            let retired_at = DateTime::parse_from_rfc3339(retired_at).ok()?;
+            Some((path, retired_at.with_timezone(&Utc)))
+        });
+        let (path, retired_at) = match retirement {
+            Some((path, retired_at)) => (path, Some(retired_at)),
+            None => (rest, None),
+        };
+
+        let contents = fs::read_to_string(path).map_err(|source| JwtKeyringError::Io {
+            kid: kid.to_owned(),
+            path: path.to_owned(),
+            source,
+        })?;
+
+        let key = match alg {
+            "EdDSA" => Self::from_ed_pem(kid, &contents)?,
+            "ES256" => Self::from_es256_pem(kid, &contents)?,
+            "HS256" => Self::from_secret(kid, contents.trim_end().as_bytes()),
+            _ => {
+                return Err(JwtKeyringError::UnsupportedAlgorithm {
+                    kid: kid.to_owned(),
+                    alg: alg.to_owned(),
+                })
+            }
+        };
+
+        Ok(Self { retired_at, ..key })
+    }
+
+    /// Whether tokens signed by this key are still accepted at `now`. Retired keys are
+    /// kept for the lifetime of the last tokens they signed.
+    fn is_accepted_at(&self, now: DateTime<Utc>) -> bool {
+        match self.retired_at {
+            Some(retired_at) => {
+                now < retired_at + chrono::Duration::minutes(JWT_AUTH_EXPIRATION_MINS)
+            }
+            None => true,
+        }
+    }
+}
+
+fn is_private_pem(pem: &str) -> bool {
+    pem.contains("PRIVATE KEY-----")
+}
+
+fn public_jwk(kid: &str, alg: KeyAlgorithm, algorithm: AlgorithmParameters) -> Jwk {
+    Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(alg),
+            key_id: Some(kid.to_owned()),
+            ..Default::default()
+        },
+        algorithm,
+    }
+}
+
+pub struct JwtKeyring {
+    signing_kid: String,
+    keys: Vec<JwtKey>,
+}
+
+impl JwtKeyring {
+    pub fn new(
+        signing_key: JwtKey,
+        verification_keys: Vec<JwtKey>,
+    ) -> Result<Self, JwtKeyringError> {
+        if signing_key.encoding_key.is_none() {
+            return Err(JwtKeyringError::SigningKeyNotPrivate {
+                kid: signing_key.kid,
+            });
+        }
+
+        let mut keys: Vec<JwtKey> = vec![];
+        for key in std::iter::once(signing_key).chain(verification_keys) {
+            if keys.iter().any(|k| k.kid == key.kid) {
+                return Err(JwtKeyringError::DuplicateKid { kid: key.kid });
+            }
+            keys.push(key);
+        }
+
+        Ok(Self {
+            signing_kid: keys[0].kid.clone(),
+            keys,
+        })
+    }
+
+    pub fn from_env() -> Result<Self, JwtKeyringError> {
+        let signing_key = match std::env::var("JWT_SIGNING_KEY") {
+            Ok(spec) => JwtKey::from_spec(&spec)?,
+            Err(_) => {
+                let secret =
+                    std::env::var("JWT_SECRET").map_err(|_| JwtKeyringError::NoSigningKey)?;
+                JwtKey::from_secret(JWT_SECRET_KID, secret.as_bytes())
+            }
+        };
+
+        let verification_keys = std::env::var("JWT_VERIFICATION_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|spec| !spec.trim().is_empty())
+            .map(JwtKey::from_spec)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::new(signing_key, verification_keys)
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> jsonwebtoken::errors::Result<String> {
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.kid == self.signing_kid)
+            .expect("signing key is in the keyring");
+        let encoding_key = key
+            .encoding_key
+            .as_ref()
+            .expect("signing key is a private key");
+
+        let header = Header {
+            kid: Some(key.kid.clone()),
+            ..Header::new(key.algorithm)
+        };
+        encode(&header, claims, encoding_key)
+    }
+
+    /// Verifies the token against the key named by its `kid`, returning the claims if the
+    /// signature is valid, the key is still accepted and the token hasn't expired.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Option<T> {
+        let header = decode_header(token).ok()?;
+        let kid = header.kid?;
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.kid == kid && k.is_accepted_at(Utc::now()))?;
+
+        // only the key's own algorithm is accepted, the header's `alg` is not trusted
+        let validation = Validation::new(key.algorithm);
+        decode::<T>(token, &key.decoding_key, &validation)
+            .ok()
+            .map(|data| data.claims)
+    }
+
+    /// Public keys of the asymmetric keys which are still accepted.
+    pub fn jwks(&self) -> JwkSet {
+        let now = Utc::now();
+        JwkSet {
+            keys: self
+                .keys
+                .iter()
+                .filter(|k| k.is_accepted_at(now))
+                .filter_map(|k| k.jwk.clone())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::random_id;
+
+    #[test]
+    fn key_paths_may_contain_at_signs() {
+        let dir = std::env::temp_dir().join(format!("jwt-keys@{}", random_id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secret@2024");
+        fs::write(&path, "secret").unwrap();
+        let path = path.to_str().unwrap();
+
+        let key = JwtKey::from_spec(&format!("k1:HS256:{path}")).unwrap();
+        assert_eq!(key.retired_at, None);
+
+        let key = JwtKey::from_spec(&format!("k1:HS256:{path}@2024-06-01T00:00:00Z")).unwrap();
+        assert_eq!(
+            key.retired_at,
+            Some("2024-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap())
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::services::metrics_service::MetricsService;
+
+use super::{
+    global_auth::{ConfigError, ConfigResult},
+    repository_metrics_layer::RepositoryMetricsLayer,
+};
+
+/// Logged when `RUST_LOG` isn't set. sqlx logs every query at info level.
+const DEFAULT_LOG_FILTER: &str = "info,sqlx=warn";
+/// Admin commands only log problems by default, so their output isn't buried
+const DEFAULT_CLI_LOG_FILTER: &str = "warn";
+
+/// Output format of the logs, selected with `LOG_FORMAT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable multi-line logs (`LOG_FORMAT=pretty`, the default)
+    Pretty,
+    /// One JSON object per line, with the fields of the enclosing spans (`LOG_FORMAT=json`)
+    Json,
+}
+
+pub fn get_log_format() -> ConfigResult<LogFormat> {
+    match std::env::var("LOG_FORMAT").as_deref() {
+        Err(_) | Ok("pretty") => Ok(LogFormat::Pretty),
+        Ok("json") => Ok(LogFormat::Json),
+        Ok(other) => Err(ConfigError::Invalid {
+            name: String::from("LOG_FORMAT"),
+            expected: "'pretty' or 'json'",
+            value: other.to_owned(),
+        }),
+    }
+}
+
+/// Installs the global `tracing` subscriber. Logs are filtered with `RUST_LOG`, while
+/// repository spans are always timed for the metrics.
+pub fn init_logging(metrics_service: Arc<MetricsService>) -> ConfigResult<()> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let fmt_layer = match get_log_format()? {
+        LogFormat::Pretty => fmt::layer().pretty().boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(filter))
+        .with(RepositoryMetricsLayer::new(metrics_service))
+        .init();
+    Ok(())
+}
+
+/// Installs the global `tracing` subscriber for admin commands, which log to stderr so their
+/// output on stdout can be piped.
+pub fn init_cli_logging() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(DEFAULT_CLI_LOG_FILTER));
+
+    tracing_subscriber::registry()
+        .with(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(filter),
+        )
+        .init();
+}
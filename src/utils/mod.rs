@@ -1,7 +1,23 @@
 pub mod askama_to_actix_responder;
+pub mod audit_context_extractor;
+pub mod authenticated_user;
+pub mod database;
 pub mod global_auth;
+pub mod jwt_keyring;
+pub mod logging;
+pub mod recurrence;
+pub mod redirect_target;
+pub mod repository_metrics_layer;
 pub mod service_error_http_response;
+pub mod tls;
+pub mod todo_search;
 
 pub fn random_id() -> String {
     uuid::Uuid::new_v4().to_string()
 }
+
+/// 256 bits of randomness, hex encoded. Suitable as an opaque bearer token.
+pub fn random_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    hex::encode(bytes)
+}
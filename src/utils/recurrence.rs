@@ -0,0 +1,644 @@
+//! Recurrence rules of todos, stored as a subset of the iCalendar `RRULE` syntax (RFC 5545):
+//!
+//! - `FREQ=DAILY`, every day
+//! - `FREQ=WEEKLY;BYDAY=MO,TH`, every week on the given weekdays
+//! - `FREQ=MONTHLY;BYMONTHDAY=15`, every month on the given day
+//! - `FREQ=DAILY;X-FROM=COMPLETION`, a day after the todo was completed, whatever its due date
+//!
+//! each with an optional `INTERVAL=n` to repeat every `n` days, weeks or months instead. Due
+//! dates are calendar dates in UTC, so occurrences don't shift with daylight saving time.
+
+use std::{fmt, str::FromStr};
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// Longest interval, so a mistyped one is caught rather than scheduling centuries ahead
+pub const MAX_INTERVAL: u32 = 999;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// How often a todo repeats, as chosen on the todos page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecurrenceFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+    AfterCompletion,
+}
+
+impl RecurrenceFrequency {
+    pub const ALL: [RecurrenceFrequency; 4] = [
+        RecurrenceFrequency::Daily,
+        RecurrenceFrequency::Weekly,
+        RecurrenceFrequency::Monthly,
+        RecurrenceFrequency::AfterCompletion,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurrenceFrequency::Daily => "daily",
+            RecurrenceFrequency::Weekly => "weekly",
+            RecurrenceFrequency::Monthly => "monthly",
+            RecurrenceFrequency::AfterCompletion => "after_completion",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RecurrenceFrequency::Daily => "Daily",
+            RecurrenceFrequency::Weekly => "Weekly",
+            RecurrenceFrequency::Monthly => "Monthly",
+            RecurrenceFrequency::AfterCompletion => "Days after completion",
+        }
+    }
+}
+
+impl FromStr for RecurrenceFrequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RecurrenceFrequency::ALL
+            .into_iter()
+            .find(|frequency| frequency.as_str() == s)
+            .ok_or_else(|| format!("unknown repeat frequency: {s}"))
+    }
+}
+
+/// Serialized as its `RRULE`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Recurrence {
+    /// every `interval` days from the due date
+    Daily { interval: u32 },
+    /// on the weekdays of every `interval` weeks from the week of the due date, where weeks
+    /// start on Monday
+    Weekly {
+        interval: u32,
+        /// in order from Monday, without duplicates
+        weekdays: Vec<Weekday>,
+    },
+    /// on the day of every `interval` months from the month of the due date, or on the last
+    /// day of months without it. `RRULE` skips such months instead, which would make a
+    /// monthly chore on the 31st skip every other month.
+    Monthly { interval: u32, day: u32 },
+    /// `interval` days after the todo is completed
+    AfterCompletion { interval: u32 },
+}
+
+impl Recurrence {
+    /// Checks the interval, weekdays and day, putting the weekdays in order
+    pub fn new(
+        frequency: RecurrenceFrequency,
+        interval: u32,
+        mut weekdays: Vec<Weekday>,
+        day: u32,
+    ) -> Result<Self, String> {
+        if !(1..=MAX_INTERVAL).contains(&interval) {
+            return Err(format!(
+                "Repeat interval must be between 1 and {MAX_INTERVAL}"
+            ));
+        }
+        Ok(match frequency {
+            RecurrenceFrequency::Daily => Recurrence::Daily { interval },
+            RecurrenceFrequency::Weekly => {
+                if weekdays.is_empty() {
+                    return Err("Weekly repeats need at least one weekday".to_owned());
+                }
+                weekdays.sort_by_key(Weekday::num_days_from_monday);
+                weekdays.dedup();
+                Recurrence::Weekly { interval, weekdays }
+            }
+            RecurrenceFrequency::Monthly => {
+                if !(1..=31).contains(&day) {
+                    return Err("Day of the month must be between 1 and 31".to_owned());
+                }
+                Recurrence::Monthly { interval, day }
+            }
+            RecurrenceFrequency::AfterCompletion => Recurrence::AfterCompletion { interval },
+        })
+    }
+
+    pub fn frequency(&self) -> RecurrenceFrequency {
+        match self {
+            Recurrence::Daily { .. } => RecurrenceFrequency::Daily,
+            Recurrence::Weekly { .. } => RecurrenceFrequency::Weekly,
+            Recurrence::Monthly { .. } => RecurrenceFrequency::Monthly,
+            Recurrence::AfterCompletion { .. } => RecurrenceFrequency::AfterCompletion,
+        }
+    }
+
+    pub fn interval(&self) -> u32 {
+        match self {
+            Recurrence::Daily { interval }
+            | Recurrence::Weekly { interval, .. }
+            | Recurrence::Monthly { interval, .. }
+            | Recurrence::AfterCompletion { interval } => *interval,
+        }
+    }
+
+    /// The weekdays or day of the month it repeats on, as entered on the todos page
+    pub fn on(&self) -> String {
+        match self {
+            Recurrence::Weekly { weekdays, .. } => weekday_names(weekdays),
+            Recurrence::Monthly { day, .. } => day.to_string(),
+            Recurrence::Daily { .. } | Recurrence::AfterCompletion { .. } => String::new(),
+        }
+    }
+
+    /// e.g. "Every 2 weeks on Mon, Thu"
+    pub fn describe(&self) -> String {
+        let every = |interval: u32, unit: &str| match interval {
+            1 => format!("Every {unit}"),
+            _ => format!("Every {interval} {unit}s"),
+        };
+        match self {
+            Recurrence::Daily { interval } => every(*interval, "day"),
+            Recurrence::Weekly { interval, weekdays } => {
+                format!(
+                    "{} on {}",
+                    every(*interval, "week"),
+                    weekday_names(weekdays)
+                )
+            }
+            Recurrence::Monthly { interval, day } => {
+                format!("{} on day {day}", every(*interval, "month"))
+            }
+            Recurrence::AfterCompletion { interval: 1 } => "1 day after completion".to_owned(),
+            Recurrence::AfterCompletion { interval } => {
+                format!("{interval} days after completion")
+            }
+        }
+    }
+
+    /// The due date of the occurrence after a todo due on `due_date` and completed on
+    /// `completed_on`. Occurrences are counted from the due date, or from the completion
+    /// without one, and the next one is after both, so a todo completed late isn't followed by
+    /// the occurrences it missed. `None` if that's past the dates chrono can represent.
+    pub fn next_due_date(
+        &self,
+        due_date: Option<NaiveDate>,
+        completed_on: NaiveDate,
+    ) -> Option<NaiveDate> {
+        let start = due_date.unwrap_or(completed_on);
+        let after = start.max(completed_on);
+        match self {
+            Recurrence::Daily { interval } => {
+                let interval = u64::from(*interval);
+                let elapsed = (after - start).num_days().unsigned_abs();
+                start.checked_add_days(Days::new((elapsed / interval + 1) * interval))
+            }
+            Recurrence::Weekly { interval, weekdays } => {
+                let first_week = week_start(start);
+                // any weekday of the right week is at most `interval` weeks away
+                (1..=7 * u64::from(*interval))
+                    .map_while(|days| after.checked_add_days(Days::new(days)))
+                    .find(|date| {
+                        let weeks = (week_start(*date) - first_week).num_weeks();
+                        weekdays.contains(&date.weekday()) && weeks % i64::from(*interval) == 0
+                    })
+            }
+            Recurrence::Monthly { interval, day } => {
+                let first_month = start.with_day(1)?;
+                (0..)
+                    .map_while(|n| first_month.checked_add_months(Months::new(n * interval)))
+                    .map(|month| with_day_clamped(month, *day))
+                    .find(|date| *date > after)
+            }
+            Recurrence::AfterCompletion { interval } => {
+                completed_on.checked_add_days(Days::new(u64::from(*interval)))
+            }
+        }
+    }
+}
+
+fn weekday_names(weekdays: &[Weekday]) -> String {
+    weekdays
+        .iter()
+        .map(Weekday::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The Monday of the date's week
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(u64::from(date.weekday().num_days_from_monday()))
+}
+
+/// The day of the month starting on `month`, or the month's last day if it's shorter
+fn with_day_clamped(month: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|day| month.with_day(day))
+        .unwrap_or(month)
+}
+
+/// Weekday names or abbreviations separated by commas, e.g. "mon, thursday"
+pub fn parse_weekdays(value: &str) -> Result<Vec<Weekday>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            name.parse::<Weekday>()
+                .map_err(|_| format!("Invalid weekday: {name}"))
+        })
+        .collect()
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let freq = match self {
+            Recurrence::Daily { .. } | Recurrence::AfterCompletion { .. } => "DAILY",
+            Recurrence::Weekly { .. } => "WEEKLY",
+            Recurrence::Monthly { .. } => "MONTHLY",
+        };
+        write!(f, "FREQ={freq}")?;
+        if self.interval() != 1 {
+            write!(f, ";INTERVAL={}", self.interval())?;
+        }
+        match self {
+            Recurrence::Weekly { weekdays, .. } => {
+                let codes: Vec<_> = weekdays.iter().copied().map(weekday_code).collect();
+                write!(f, ";BYDAY={}", codes.join(","))
+            }
+            Recurrence::Monthly { day, .. } => write!(f, ";BYMONTHDAY={day}"),
+            Recurrence::AfterCompletion { .. } => f.write_str(";X-FROM=COMPLETION"),
+            Recurrence::Daily { .. } => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    /// Parses the subset of `RRULE` written by [Recurrence]'s `Display`, in any order and case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("unsupported recurrence rule: {s}");
+
+        let mut freq = None;
+        let mut interval = 1;
+        let mut weekdays = vec![];
+        let mut day = None;
+        let mut from_completion = false;
+        for part in s.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part.split_once('=').ok_or_else(invalid)?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => freq = Some(value.to_ascii_uppercase()),
+                "INTERVAL" => interval = value.parse().map_err(|_| invalid())?,
+                "BYDAY" => {
+                    weekdays = value
+                        .split(',')
+                        .map(|code| {
+                            WEEKDAYS
+                                .into_iter()
+                                .find(|weekday| weekday_code(*weekday).eq_ignore_ascii_case(code))
+                                .ok_or_else(invalid)
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => day = Some(value.parse().map_err(|_| invalid())?),
+                "X-FROM" if value.eq_ignore_ascii_case("COMPLETION") => from_completion = true,
+                _ => return Err(invalid()),
+            }
+        }
+
+        let frequency = match (freq.as_deref(), from_completion) {
+            (Some("DAILY"), false) => RecurrenceFrequency::Daily,
+            (Some("DAILY"), true) => RecurrenceFrequency::AfterCompletion,
+            (Some("WEEKLY"), false) => RecurrenceFrequency::Weekly,
+            (Some("MONTHLY"), false) => RecurrenceFrequency::Monthly,
+            _ => return Err(invalid()),
+        };
+        // parts of other frequencies would be silently ignored
+        if (frequency != RecurrenceFrequency::Weekly && !weekdays.is_empty())
+            || (frequency != RecurrenceFrequency::Monthly && day.is_some())
+        {
+            return Err(invalid());
+        }
+        Recurrence::new(frequency, interval, weekdays, day.unwrap_or(0))
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(value: Recurrence) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn weekly(interval: u32, weekdays: &[Weekday]) -> Recurrence {
+        Recurrence::Weekly {
+            interval,
+            weekdays: weekdays.to_vec(),
+        }
+    }
+
+    /// Due dates of the next `n` occurrences, each completed on the day it's due
+    fn occurrences(recurrence: &Recurrence, due_date: NaiveDate, n: usize) -> Vec<NaiveDate> {
+        let mut due_date = due_date;
+        (0..n)
+            .map(|_| {
+                due_date = recurrence.next_due_date(Some(due_date), due_date).unwrap();
+                due_date
+            })
+            .collect()
+    }
+
+    #[test]
+    fn monthly_on_the_31st_clamps_to_shorter_months_without_drifting() {
+        let monthly = Recurrence::Monthly {
+            interval: 1,
+            day: 31,
+        };
+        assert_eq!(
+            occurrences(&monthly, date(2031, 1, 31), 4),
+            [
+                date(2031, 2, 28),
+                date(2031, 3, 31),
+                date(2031, 4, 30),
+                date(2031, 5, 31)
+            ]
+        );
+        // leap year
+        assert_eq!(
+            occurrences(&monthly, date(2032, 1, 31), 2),
+            [date(2032, 2, 29), date(2032, 3, 31)]
+        );
+        let every_other = Recurrence::Monthly {
+            interval: 2,
+            day: 31,
+        };
+        assert_eq!(
+            occurrences(&every_other, date(2031, 12, 31), 4),
+            [
+                date(2032, 2, 29),
+                date(2032, 4, 30),
+                date(2032, 6, 30),
+                date(2032, 8, 31)
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_starts_from_the_month_of_the_due_date() {
+        let monthly = Recurrence::Monthly {
+            interval: 1,
+            day: 15,
+        };
+        // due before the day in its month, so the next one is still in that month
+        assert_eq!(
+            monthly.next_due_date(Some(date(2030, 1, 10)), date(2030, 1, 10)),
+            Some(date(2030, 1, 15))
+        );
+        assert_eq!(
+            monthly.next_due_date(Some(date(2030, 1, 15)), date(2030, 1, 15)),
+            Some(date(2030, 2, 15))
+        );
+    }
+
+    #[test]
+    fn weekly_with_an_interval_keeps_to_the_weeks_of_the_due_date() {
+        // 2030-01-07 is a Monday
+        let every_other = weekly(2, &[Weekday::Mon, Weekday::Thu]);
+        assert_eq!(
+            occurrences(&every_other, date(2030, 1, 7), 4),
+            [
+                date(2030, 1, 10),
+                date(2030, 1, 21),
+                date(2030, 1, 24),
+                date(2030, 2, 4)
+            ]
+        );
+        // due midweek, on a day it doesn't repeat on
+        assert_eq!(
+            occurrences(&every_other, date(2030, 1, 9), 2),
+            [date(2030, 1, 10), date(2030, 1, 21)]
+        );
+        let every_third = weekly(3, &[Weekday::Sun]);
+        assert_eq!(
+            occurrences(&every_third, date(2030, 1, 13), 2),
+            [date(2030, 2, 3), date(2030, 2, 24)]
+        );
+    }
+
+    #[test]
+    fn completing_late_skips_the_missed_occurrences() {
+        let daily = Recurrence::Daily { interval: 1 };
+        assert_eq!(
+            daily.next_due_date(Some(date(2030, 1, 1)), date(2030, 1, 10)),
+            Some(date(2030, 1, 11))
+        );
+        let every_third_day = Recurrence::Daily { interval: 3 };
+        assert_eq!(
+            every_third_day.next_due_date(Some(date(2030, 1, 1)), date(2030, 1, 10)),
+            Some(date(2030, 1, 13))
+        );
+        assert_eq!(
+            every_third_day.next_due_date(Some(date(2030, 1, 1)), date(2030, 1, 11)),
+            Some(date(2030, 1, 13))
+        );
+        let every_other_monday = weekly(2, &[Weekday::Mon]);
+        assert_eq!(
+            every_other_monday.next_due_date(Some(date(2030, 1, 7)), date(2030, 1, 16)),
+            Some(date(2030, 1, 21))
+        );
+        assert_eq!(
+            every_other_monday.next_due_date(Some(date(2030, 1, 7)), date(2030, 1, 21)),
+            Some(date(2030, 2, 4))
+        );
+        let monthly = Recurrence::Monthly {
+            interval: 1,
+            day: 15,
+        };
+        assert_eq!(
+            monthly.next_due_date(Some(date(2030, 1, 15)), date(2030, 3, 20)),
+            Some(date(2030, 4, 15))
+        );
+    }
+
+    #[test]
+    fn completing_early_or_without_a_due_date() {
+        let daily = Recurrence::Daily { interval: 2 };
+        assert_eq!(
+            daily.next_due_date(Some(date(2030, 1, 10)), date(2030, 1, 5)),
+            Some(date(2030, 1, 12))
+        );
+        assert_eq!(
+            daily.next_due_date(None, date(2030, 1, 5)),
+            Some(date(2030, 1, 7))
+        );
+        let mondays = weekly(1, &[Weekday::Mon]);
+        assert_eq!(
+            mondays.next_due_date(None, date(2030, 1, 9)),
+            Some(date(2030, 1, 14))
+        );
+    }
+
+    #[test]
+    fn after_completion_counts_from_the_completion() {
+        let after_completion = Recurrence::AfterCompletion { interval: 3 };
+        assert_eq!(
+            after_completion.next_due_date(Some(date(2030, 1, 1)), date(2030, 1, 10)),
+            Some(date(2030, 1, 13))
+        );
+        assert_eq!(
+            after_completion.next_due_date(Some(date(2030, 1, 20)), date(2030, 1, 10)),
+            Some(date(2030, 1, 13))
+        );
+        assert_eq!(
+            after_completion.next_due_date(None, date(2030, 12, 30)),
+            Some(date(2031, 1, 2))
+        );
+    }
+
+    #[test]
+    fn past_the_last_representable_date() {
+        let daily = Recurrence::Daily { interval: 1 };
+        assert_eq!(daily.next_due_date(None, NaiveDate::MAX), None);
+        let monthly = Recurrence::Monthly {
+            interval: 1,
+            day: 31,
+        };
+        assert_eq!(monthly.next_due_date(None, NaiveDate::MAX), None);
+    }
+
+    #[test]
+    fn due_dates_are_unaffected_by_daylight_saving_time() {
+        // clocks change on 2030-03-10 and 2030-11-03 in the US, and on 2030-03-31 and
+        // 2030-10-27 in the EU, all Sundays
+        let daily = Recurrence::Daily { interval: 1 };
+        let sundays = weekly(1, &[Weekday::Sun]);
+        let monthly = Recurrence::Monthly {
+            interval: 1,
+            day: 31,
+        };
+        for change in [
+            date(2030, 3, 10),
+            date(2030, 3, 31),
+            date(2030, 10, 27),
+            date(2030, 11, 3),
+        ] {
+            let day_before = change.pred_opt().unwrap();
+            assert_eq!(
+                occurrences(&daily, day_before, 2),
+                [change, change.succ_opt().unwrap()]
+            );
+            let week_before = change - Days::new(7);
+            assert_eq!(
+                occurrences(&sundays, week_before, 2),
+                [change, change + Days::new(7)]
+            );
+        }
+        assert_eq!(
+            occurrences(&monthly, date(2030, 3, 31), 1),
+            [date(2030, 4, 30)]
+        );
+    }
+
+    #[test]
+    fn rules_round_trip_through_rrule() {
+        let rules = [
+            (Recurrence::Daily { interval: 1 }, "FREQ=DAILY"),
+            (Recurrence::Daily { interval: 3 }, "FREQ=DAILY;INTERVAL=3"),
+            (
+                weekly(2, &[Weekday::Mon, Weekday::Thu]),
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH",
+            ),
+            (
+                Recurrence::Monthly {
+                    interval: 1,
+                    day: 31,
+                },
+                "FREQ=MONTHLY;BYMONTHDAY=31",
+            ),
+            (
+                Recurrence::AfterCompletion { interval: 7 },
+                "FREQ=DAILY;INTERVAL=7;X-FROM=COMPLETION",
+            ),
+        ];
+        for (recurrence, rrule) in rules {
+            assert_eq!(recurrence.to_string(), rrule);
+            assert_eq!(rrule.parse::<Recurrence>(), Ok(recurrence.clone()));
+            let json = serde_json::to_string(&recurrence).unwrap();
+            assert_eq!(json, format!("\"{rrule}\""));
+            assert_eq!(
+                serde_json::from_str::<Recurrence>(&json).unwrap(),
+                recurrence
+            );
+        }
+    }
+
+    #[test]
+    fn parses_parts_in_any_order_and_case() {
+        assert_eq!(
+            "byday=th,mo,th;freq=weekly".parse(),
+            Ok(weekly(1, &[Weekday::Mon, Weekday::Thu]))
+        );
+        assert_eq!(
+            "X-FROM=completion;FREQ=daily".parse(),
+            Ok(Recurrence::AfterCompletion { interval: 1 })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rrule in [
+            "",
+            "FREQ",
+            "INTERVAL=2",
+            "FREQ=YEARLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=1000",
+            "FREQ=DAILY;INTERVAL=-1",
+            "FREQ=DAILY;COUNT=3",
+            "FREQ=DAILY;BYDAY=MO",
+            "FREQ=WEEKLY",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=WEEKLY;BYDAY=MO,",
+            "FREQ=WEEKLY;X-FROM=COMPLETION",
+            "FREQ=MONTHLY",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=DAILY;X-FROM=DUE",
+        ] {
+            assert!(rrule.parse::<Recurrence>().is_err(), "{rrule}");
+        }
+        assert!(serde_json::from_str::<Recurrence>("\"FREQ=YEARLY\"").is_err());
+    }
+}
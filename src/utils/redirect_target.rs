@@ -0,0 +1,43 @@
+use actix_web::{http::Method, HttpRequest};
+
+/// Where signed in users are sent by default, e.g. after logging in.
+pub const DEFAULT_SIGNED_IN_PATH: &str = "/home/todos";
+
+/// Validates a `next` redirect target, only allowing paths on this site so the login page
+/// can't be used as an open redirect. Falls back to [DEFAULT_SIGNED_IN_PATH].
+pub fn safe_redirect_target(next: Option<&str>) -> &str {
+    match next {
+        Some(next) if is_local_path(next) => next,
+        _ => DEFAULT_SIGNED_IN_PATH,
+    }
+}
+
+fn is_local_path(path: &str) -> bool {
+    // "//host" and "/\host" are treated as other hosts by browsers
+    path.starts_with('/')
+        && !path.starts_with("//")
+        && !path.contains('\\')
+        && !path.chars().any(|c| c.is_control())
+}
+
+/// Where to send a request without a session. GET requests pass their path as the `next`
+/// parameter, other requests can't be safely repeated after logging in.
+pub fn login_redirect_location(req: &HttpRequest) -> String {
+    if req.method() == Method::GET {
+        let path = req
+            .uri()
+            .path_and_query()
+            .map_or(req.path(), |p| p.as_str());
+        login_path_returning_to(path)
+    } else {
+        String::from("/login")
+    }
+}
+
+/// The login page URL which returns to `path` after logging in.
+pub fn login_path_returning_to(path: &str) -> String {
+    match serde_urlencoded::to_string([("next", path)]) {
+        Ok(query) => format!("/login?{query}"),
+        Err(_) => String::from("/login"),
+    }
+}
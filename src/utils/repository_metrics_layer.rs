@@ -0,0 +1,68 @@
+use std::{sync::Arc, time::Instant};
+
+use tracing::{span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::services::metrics_service::MetricsService;
+
+/// Module path prefixes of the database repositories, whose methods are instrumented with spans
+const REPOSITORY_TARGET_PREFIXES: &[&str] = &[
+    "shaat_stack_demo::repositories::sql_",
+    "shaat_stack_demo::repositories::sqlite_",
+];
+
+/// Times the spans of database repository methods and records them as repository operation
+/// latencies, e.g. `get_user_by_id` of `sql_user_repository`. This reuses the spans the
+/// repositories already have for logging, rather than timing every method by hand.
+///
+/// Must not be filtered by `RUST_LOG`, or latencies would only be recorded at some log levels.
+pub struct RepositoryMetricsLayer {
+    metrics_service: Arc<MetricsService>,
+}
+
+impl RepositoryMetricsLayer {
+    pub fn new(metrics_service: Arc<MetricsService>) -> Self {
+        Self { metrics_service }
+    }
+}
+
+struct SpanStarted(Instant);
+
+impl<S> Layer<S> for RepositoryMetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let target = attrs.metadata().target();
+        if !REPOSITORY_TARGET_PREFIXES
+            .iter()
+            .any(|prefix| target.starts_with(prefix))
+        {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStarted(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(&id) {
+            Some(span) => span,
+            None => return,
+        };
+        let extensions = span.extensions();
+        if let Some(SpanStarted(started)) = extensions.get::<SpanStarted>() {
+            let repository = span
+                .metadata()
+                .target()
+                .rsplit("::")
+                .next()
+                .unwrap_or_default();
+            self.metrics_service.observe_repository_operation(
+                repository,
+                span.name(),
+                started.elapsed(),
+            );
+        }
+    }
+}
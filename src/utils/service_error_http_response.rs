@@ -1,10 +1,40 @@
-use actix_web::{http::StatusCode, HttpResponse, HttpResponseBuilder};
+use actix_web::{
+    http::{
+        header::{HeaderValue, RETRY_AFTER},
+        StatusCode,
+    },
+    HttpResponse, HttpResponseBuilder,
+};
 
+use crate::middleware::request_tracing::current_request_id;
+
+/// Logs the error and responds with 500 Internal Server Error. The body includes the request
+/// ID, so a report of the error can be matched with the logs.
 pub fn http_service_error_response(description: Option<String>) -> HttpResponse {
+    tracing::error!(
+        error = description.as_deref().unwrap_or("unknown"),
+        "service error"
+    );
+
     let mut err_res = HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR);
-    if let Some(desc) = description {
-        err_res.body(desc)
-    } else {
-        err_res.finish()
+    match description {
+        Some(desc) => err_res.body(with_request_id(&desc)),
+        None => err_res.body(with_request_id("Internal server error")),
     }
 }
+
+/// Appends the current request ID to an error message shown to the user.
+pub fn with_request_id(message: &str) -> String {
+    match current_request_id() {
+        Some(request_id) => format!("{message} (request ID {request_id})"),
+        None => message.to_owned(),
+    }
+}
+
+/// Marks a response as 503 Service Unavailable, asking the client to retry shortly.
+pub fn with_server_busy_status(mut res: HttpResponse) -> HttpResponse {
+    *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    res.headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    res
+}
@@ -0,0 +1,147 @@
+//! Searching todos by the words of their names, e.g. `milk tag:home is:open due:<2026-11-01`.
+//! Each word of the search has to start a word of a todo's name, so todos can be found while
+//! their words are still being typed. Words are letters and digits separated by anything else,
+//! matched regardless of case.
+
+use chrono::NaiveDate;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TodoSearch {
+    /// lowercase words which each start a word of the todo's name
+    pub words: Vec<String>,
+    /// `is:done` or `is:open`
+    pub is_complete: Option<bool>,
+    /// `tag:name`, only todos with all of these tags
+    pub tags: Vec<String>,
+    /// earliest due date, from `due:>date`, `due:>=date` or `due:date`
+    pub due_from: Option<NaiveDate>,
+    /// latest due date, from `due:<date`, `due:<=date` or `due:date`
+    pub due_until: Option<NaiveDate>,
+}
+
+impl TodoSearch {
+    /// Parses words and `is:`, `tag:` and `due:` filters separated by spaces. Double quotes
+    /// keep spaces in a term, e.g. `tag:"grocery list"`, and due dates are `YYYY-MM-DD` or
+    /// `today`, which is `today` in UTC.
+    pub fn parse(query: &str, today: NaiveDate) -> Result<Self, String> {
+        let mut search = TodoSearch::default();
+        for term in split_terms(query) {
+            let filter = term
+                .split_once(':')
+                .map(|(name, value)| (name.to_ascii_lowercase(), value));
+            match filter.as_ref().map(|(name, value)| (name.as_str(), *value)) {
+                Some(("is", value)) => {
+                    search.is_complete = match value.to_ascii_lowercase().as_str() {
+                        "done" => Some(true),
+                        "open" => Some(false),
+                        _ => {
+                            return Err(format!(
+                                "Unknown filter is:{value}, try is:done or is:open"
+                            ))
+                        }
+                    }
+                }
+                Some(("tag", tag)) if !tag.trim().is_empty() => {
+                    search.tags.push(tag.trim().to_owned());
+                }
+                Some(("due", bound)) => search.parse_due(bound, today)?,
+                _ => search.words.extend(search_words(&term)),
+            }
+        }
+        Ok(search)
+    }
+
+    fn parse_due(&mut self, bound: &str, today: NaiveDate) -> Result<(), String> {
+        let (op, date) = match ["<=", ">=", "<", ">"]
+            .into_iter()
+            .find_map(|op| Some((op, bound.strip_prefix(op)?)))
+        {
+            Some((op, date)) => (op, date),
+            None => ("", bound),
+        };
+        let date = match date {
+            "today" => today,
+            date => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Invalid due date filter due:{bound}, try due:<YYYY-MM-DD"))?,
+        };
+        match op {
+            "<" => self.due_until = date.pred_opt(),
+            "<=" => self.due_until = Some(date),
+            ">" => self.due_from = date.succ_opt(),
+            ">=" => self.due_from = Some(date),
+            _ => {
+                self.due_from = Some(date);
+                self.due_until = Some(date);
+            }
+        }
+        Ok(())
+    }
+
+    /// How many words of the name are matched, if each word of the search matches one
+    pub fn rank_name(&self, name: &str) -> Option<usize> {
+        let name_words = search_words(name);
+        let matches =
+            |name_word: &String| self.words.iter().any(|word| name_word.starts_with(word));
+        self.words
+            .iter()
+            .all(|word| {
+                name_words
+                    .iter()
+                    .any(|name_word| name_word.starts_with(word))
+            })
+            .then(|| {
+                name_words
+                    .iter()
+                    .filter(|name_word| matches(name_word))
+                    .count()
+            })
+    }
+
+    /// The name split into parts, marking the words matched by the search
+    pub fn highlight<'a>(&self, name: &'a str) -> Vec<(&'a str, bool)> {
+        let mut parts = vec![];
+        let mut rest = name;
+        while let Some(c) = rest.chars().next() {
+            let is_word = c.is_alphanumeric();
+            let len = rest
+                .find(|c: char| c.is_alphanumeric() != is_word)
+                .unwrap_or(rest.len());
+            let (part, next) = rest.split_at(len);
+            let lowercase = part.to_lowercase();
+            let matched = is_word && self.words.iter().any(|word| lowercase.starts_with(word));
+            parts.push((part, matched));
+            rest = next;
+        }
+        parts
+    }
+}
+
+/// The lowercase words of the text
+pub fn search_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Splits on whitespace outside of double quotes, dropping the quotes
+fn split_terms(query: &str) -> Vec<String> {
+    let mut terms = vec![];
+    let mut term = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    terms
+}